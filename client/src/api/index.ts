@@ -1,6 +1,6 @@
 import axios from "axios"
 import { API } from "../context/auth";
-import { Policy, PolicyVersion, reasonerConnectorInfo, AuthDataViewmodel, Workflow, DeliberationType, Option, WorkflowConvResult } from "./types";
+import { Policy, PolicyVersion, reasonerConnectorInfo, AuthDataViewmodel, Workflow, DeliberationType, Option, WorkflowConvResult, CheckerInfo, CheckerStatus, PolicyEvent } from "./types";
 import { helloWorldPolicy } from "./hello-world-example";
 
 
@@ -12,6 +12,37 @@ const buildUrl = (...path : string[]) : string => {
     return [baseAddr, ...path].join('/')
 }
 
+// The checker that requests go to; the backend's default if not set
+let selectedChecker : string | null = null
+
+axios.interceptors.request.use((config) => {
+    if (selectedChecker) {
+        config.params = { ...config.params, checker: selectedChecker }
+    }
+    return config
+})
+
+export const selectChecker = (name: string | null) => {
+    selectedChecker = name
+}
+
+export const getCheckers = async () : Promise<CheckerInfo[]> => {
+    return (await axios.get(buildUrl('checkers'))).data as CheckerInfo[]
+}
+
+export const getCheckerStatus = async () : Promise<CheckerStatus[]> => {
+    return (await axios.get(buildUrl('checker', 'status'))).data as CheckerStatus[]
+}
+
+// Calls `onEvent` for every change to the selected checker's policies, and `onLagged` if some were missed; returns a function that stops listening
+export const subscribeToEvents = (onEvent: (event: PolicyEvent) => void, onLagged: () => void) : () => void => {
+    const url = buildUrl('events') + (selectedChecker ? `?checker=${encodeURIComponent(selectedChecker)}` : '')
+    const source = new EventSource(url, { withCredentials: true })
+    source.addEventListener('policy', (e) => onEvent(JSON.parse((e as MessageEvent).data) as PolicyEvent))
+    source.addEventListener('lagged', () => onLagged())
+    return () => source.close()
+}
+
 export const NEW_VERSION = -1
 
 export const newPolicy = (parent: Policy | null) : Policy => ({
@@ -134,4 +165,11 @@ export const deliberate = async ({type, req}:  {type: DeliberationType, req: any
     const body = JSON.stringify(req).replace('18446744073709552000', '18446744073709551615')
 
     return await axios.post(buildUrl('deliberation', type), body, { headers: {'Content-Type': 'application/json'} })
-}
\ No newline at end of file
+}
+// Asks every question about the workflow at once, as the workflow's own user unless another `user` is given
+export const deliberateBatch = async ({wir, user}: {wir: WorkflowConvResult, user?: string}) => {
+    const body = JSON.stringify(wir).replace('18446744073709552000', '18446744073709551615')
+    const url = buildUrl('deliberation', 'batch') + (user ? `?user=${encodeURIComponent(user)}` : '')
+
+    return await axios.post(url, body, { headers: {'Content-Type': 'application/json'} })
+}
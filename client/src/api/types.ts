@@ -1,5 +1,47 @@
 import { API } from "../context/auth";
 
+export interface CheckerInfo {
+    name: string;
+    address: string;
+    default: boolean;
+}
+
+export interface CheckerProbe {
+    at: string;
+    reachable: boolean;
+    latency_ms: number | null;
+    context_hash: string | null;
+    active_version: number | null;
+    error: string | null;
+}
+
+export interface CheckerStatus {
+    checker: string;
+    circuit_open: boolean;
+    since: string | null;
+    latest: CheckerProbe | null;
+    history: CheckerProbe[];
+}
+
+export interface ApiError {
+    code: string;
+    message: string;
+    causes: string[];
+    upstream_status: number | null;
+    retry_after: number | null;
+    request_id: string | null;
+}
+
+export interface PolicyEvent {
+    id: number;
+    at: string;
+    checker: string;
+    kind: 'added' | 'activated' | 'deactivated';
+    version: number | null;
+    origin: 'backend' | 'detected';
+    user: string | null;
+}
+
 export interface PolicyVersion {
     version: number;
 }
@@ -74,13 +116,39 @@ export interface Workflow {
 export interface TaskOption {
     "pg": ProgramCounter,
     "name": string,
-    "datasets": string[]
+    "datasets": string[],
+    "at": string | null,
+    "result": string | null,
+    "function": string,
+    "in_loop": boolean,
+    "in_branch": boolean,
+    "in_parallel": boolean,
+    "reachable": boolean,
+    "call_chain": ProgramCounter[]
 }
 
 export interface WorkflowConvResult {
     "workflow": Workflow,
     "tasks": TaskOption[],
-    "results": string[]
+    "results": string[],
+    "lineage"?: Lineage,
+    "unreachable_functions"?: string[]
+}
+
+export type LineageNode =
+    { "kind": "dataset", "id": string, "locations": string[] }
+    | { "kind": "intermediate_result", "id": string, "location": string | null }
+    | { "kind": "task", "pg": ProgramCounter, "name": string, "at": string | null }
+
+export interface LineageEdge {
+    "kind": "produced_by" | "consumed_by",
+    "data": string,
+    "task": ProgramCounter
+}
+
+export interface Lineage {
+    "nodes": LineageNode[],
+    "edges": LineageEdge[]
 }
 
 export type ProgramCounter = ["<main>" | number, number]
@@ -1,4 +1,5 @@
 import {
+  Alert,
   AppBar,
   Box,
   Button,
@@ -6,11 +7,15 @@ import {
   Container,
   Menu,
   MenuItem,
+  Select,
   Typography,
 } from "@mui/material";
-import React, { FC, useContext } from "react";
+import React, { FC, useContext, useState } from "react";
+import { useQuery, useQueryClient } from "@tanstack/react-query";
 import { Link, Outlet, useLocation, useMatch, useNavigate } from "react-router-dom";
 import { API, AuthContext } from "../context/auth";
+import { getCheckers, getCheckerStatus, selectChecker } from "../api";
+import usePolicyEvents from "../hooks/usePolicyEvents";
 
 const Layout: FC = () => {
   const authData = useContext(AuthContext)
@@ -19,6 +24,14 @@ const Layout: FC = () => {
   const isDeliberationRoute = useMatch("/deliberation")
   const isIndexRoute = useMatch("/")
   const isConnInfoRoute = useMatch("/reasoner-connector-info")
+  const client = useQueryClient()
+  const {data: checkers} = useQuery({ queryKey: ['checkers'], queryFn: getCheckers })
+  const [checker, setChecker] = useState<string>('')
+  const {data: statuses} = useQuery({ queryKey: ['checker-status'], queryFn: getCheckerStatus, refetchInterval: 15000 })
+  const selected = checker || checkers?.find(c => c.default)?.name
+  const status = statuses?.find(s => s.checker === selected)
+  const isDown = !!status && (status.circuit_open || status.latest?.reachable === false)
+  usePolicyEvents(authData, selected)
     
   return (
     <div>
@@ -36,6 +49,22 @@ const Layout: FC = () => {
                       Deliberation
                 </Button>
                 </div>
+
+                {checkers && checkers.length > 1 ? (
+                  <Select
+                    size="small"
+                    value={checker || checkers.find(c => c.default)?.name || ''}
+                    onChange={(e) => {
+                      setChecker(e.target.value)
+                      selectChecker(e.target.value)
+                      // Everything shown so far came from the previous checker
+                      client.invalidateQueries()
+                    }}
+                    sx={{ my: 2, color: 'white' }}
+                  >
+                    {checkers.map(c => <MenuItem key={c.name} value={c.name}>{c.name}</MenuItem>)}
+                  </Select>
+                ) : null}
                
                 {authData?.authenticated(API.DELIBERATION) || authData?.authenticated(API.POLICY) ? (
                    <Button
@@ -54,6 +83,13 @@ const Layout: FC = () => {
           </div>
       </AppBar> 
       <div style={{padding: '58px 25px 0 25px'}}>
+        {isDown && status ? (
+          <Alert severity="error" sx={{ mt: 1 }}>
+            The checker{checkers && checkers.length > 1 ? ` '${status.checker}'` : ''} cannot be reached
+            {status.since ? ` since ${new Date(status.since).toLocaleString()}` : ''}
+            {status.latest?.error ? `: ${status.latest.error}` : ''}. Requests to it will fail until it is back.
+          </Alert>
+        ) : null}
         <Box sx={{ flexGrow: 1, p: 1 }}>
           <Outlet />
         </Box>
@@ -1,5 +1,6 @@
 import { AxiosError } from "axios"
 import { AuthState, API } from "../context/auth"
+import { ApiError } from "../api/types"
 
 export const handleError = (error: any, setErrors: React.Dispatch<React.SetStateAction<string[]>>, authData: AuthState) => {
     if (!error) {
@@ -20,12 +21,22 @@ export const handleError = (error: any, setErrors: React.Dispatch<React.SetState
         return
     } 
 
-    const err = (response.data as any).detail || response.data as string
+    const data = response.data as ApiError | string | undefined
+    let err = typeof data === 'string' ? data : data?.message
+    if (err && typeof data === 'object') {
+        if (data.causes?.length) {
+            err += `\n${data.causes.join('\n')}`
+        }
+        if (data.request_id) {
+            err += ` (request ${data.request_id})`
+        }
+    }
 
     if (!err) {
         setErrors(e => [...e, `Call returned invalid statuscode: ${response.status} (${response.statusText})`])
         return
     }
 
-    setErrors(e => [...e, err])
+    const msg = err
+    setErrors(e => [...e, msg])
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+import { useQueryClient } from "@tanstack/react-query"
+import { useEffect } from "react"
+import { subscribeToEvents } from "../api"
+import { API, AuthState } from "../context/auth"
+
+// Refreshes the policies whenever they change on the checker, whoever changed them
+const usePolicyEvents = (authData: AuthState | null, checker: string | undefined) => {
+    const client = useQueryClient()
+    const authenticated = !!(authData?.authenticated(API.POLICY) || authData?.authenticated(API.DELIBERATION))
+
+    useEffect(() => {
+        if (!authenticated) {
+            return
+        }
+        const refresh = () => {
+            client.invalidateQueries({ queryKey: ['policies'] })
+        }
+        return subscribeToEvents(refresh, refresh)
+    }, [authenticated, checker])
+}
+
+export default usePolicyEvents
@@ -4,7 +4,7 @@ import { javascript } from "@codemirror/lang-javascript";
 import CodeMirror, { EditorView } from '@uiw/react-codemirror';
 import Login from "../components/Login";
 import { API, AuthContext } from "../context/auth";
-import { convBS, deliberate } from "../api";
+import { convBS, deliberate, deliberateBatch } from "../api";
 import { DeliberationType, Workflow, Option, TaskOption, WorkflowConvResult } from "../api/types";
 import { useMutation } from "@tanstack/react-query";
 import { handleError } from "../errors/util";
@@ -71,6 +71,20 @@ const menuItems : MenuItemData[] = [{
     }
 }]
 
+const taskLabel = (t: TaskOption) : string => {
+    const scopes = [t.in_loop ? 'loop' : '', t.in_branch ? 'branch' : '', t.in_parallel ? 'parallel' : ''].filter(s => !!s)
+
+    let label = t.name
+    if (t.at) {
+        label += ` @ ${t.at}`
+    }
+    if (t.result) {
+        label += ` → ${t.result}`
+    }
+    label += ` (${t.function !== '<main>' ? `in ${t.function}, ` : ''}${scopes.length ? `${scopes.join(', ')}, ` : ''}${t.pg[0]}:${t.pg[1]})`
+    return label
+}
+
 const validate = (item : MenuItemData, task : Option | undefined, dataset : string, workflow: string) : boolean => {
     return !!workflow && item.validate(task, dataset)
 }
@@ -134,7 +148,7 @@ const DeliberationPage: FC = () => {
             setWorkflow(wf)
             const convResult = await convBS(wf)
             setJsonWorkflow(convResult.workflow)
-            setTaskOptions(convResult.tasks)
+            setTaskOptions(convResult.tasks.filter(t => t.reachable))
             setWorkflowResults(convResult.results || [])
         } catch(err) {
             console.log('go and check', reportErr)    
@@ -156,6 +170,14 @@ const DeliberationPage: FC = () => {
         onError: (error) => handleError(error, (err) => setResponse(err[0]), authData!)
     })
 
+    const batchMutation = useMutation({
+        mutationFn: deliberateBatch,
+        onSuccess: (r) => {
+            setResponse(typeof r.data === "string" ? r.data : JSON.stringify(r.data, null, '    '))
+        },
+        onError: (error) => handleError(error, (err) => setResponse(err[0]), authData!)
+    })
+
     const exec = async  () => {
         const req = await curItem.generateRequest(jsonWorkflow!, task != -1 ? taskOptions[task] : undefined, dataset)
         mutation.mutate({type: curItem.id, req})
@@ -213,7 +235,7 @@ const DeliberationPage: FC = () => {
                                     <InputLabel shrink>Task ID</InputLabel>
                                     <Select value={task} sx={{width: '100%'}} onChange={e => updateTask(e.target.value as number)} label="Task ID">
                                         <MenuItem disabled={curItem.id == DeliberationType.data ? false : true} value={-1}><em>{ curItem.id == DeliberationType.data ? "Workflow result": "Select task" }</em></MenuItem>
-                                        {taskOptions.map((t, idx) => <MenuItem key={idx} value={idx}>{t.pg[0]}:{t.pg[1]} {t.name}</MenuItem>)}
+                                        {taskOptions.map((t, idx) => <MenuItem key={idx} value={idx}>{taskLabel(t)}</MenuItem>)}
                                     </Select>
                                 </FormControl>
                             ): null
@@ -243,6 +265,9 @@ const DeliberationPage: FC = () => {
                             }
                             setLoading(false)
                         }}>Execute</Button>
+                        <Button disabled={!jsonWorkflow} size="large" variant="outlined" sx={{width: '100%'}} onClick={() => {
+                            batchMutation.mutate({wir: {workflow: jsonWorkflow!, tasks: taskOptions, results: workflowResults}})
+                        }}>Check all steps</Button>
                     </Stack>
                 </div>
                 <div style={{flex:1, display: 'flex', maxWidth: 'calc(100% - 225px)'}}>
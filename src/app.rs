@@ -0,0 +1,132 @@
+//  APP.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 15:44:52
+//  Last edited:
+//    19 Oct 2026, 15:44:52
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Defines the routes of the backend server, so they can be served by
+//!   the binary and driven in-process by tests alike.
+//
+
+use std::time::Duration;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, Method};
+use axum::middleware;
+use axum::routing::{delete, get, post};
+use axum::Router;
+use log::warn;
+use tower_http::catch_panic::CatchPanicLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::services::{ServeDir, ServeFile};
+use tower_http::timeout::TimeoutLayer;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
+
+use crate::approval::{delete_proposal, get_proposals, post_approve_proposal, post_propose_activation};
+use crate::audit::{get_audit, get_audit_export, get_audit_verify};
+use crate::auth::{get_authenticate, logout, migrate_cookies, post_authenticate, AppState};
+use crate::batch::post_batch;
+use crate::checker::get_checkers;
+use crate::config::ServerConfig;
+use crate::conv::post_conv;
+use crate::deliberation::{post_access_data, post_exec_task, post_validate_workflow};
+use crate::errors::{report_errors, report_panic, REQUEST_ID_HEADER};
+use crate::events::get_events;
+use crate::federated::post_federated;
+use crate::health::{get_checker_status, get_health};
+use crate::history::{get_history, get_history_record, post_replay};
+use crate::policy::{delete_deactivate_policy, get_active_policy, get_policies, get_policy, post_activate_policy, post_add_policy};
+use crate::reasoner_conn::get_reasoner_connector_info;
+use crate::rules::post_check_workflow;
+use crate::schedule::{delete_schedule, get_schedules, post_schedule};
+use crate::sync::{get_compare_policies, post_sync_policies};
+use crate::verdict::post_explain;
+
+
+/***** LIBRARY *****/
+/// Builds the [`Router`] that serves both the client and the API.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] shared by all handlers.
+/// - `server`: The [`ServerConfig`] that tells us where the client files are, which origins may use the API and how long requests may take.
+///
+/// # Returns
+/// A new [`Router`] that is ready to be served.
+pub fn build_router(state: AppState, server: &ServerConfig) -> Router {
+    let mut router: Router<AppState> = Router::new()
+        .nest_service("/", ServeDir::new(&server.static_dir).not_found_service(ServeFile::new(server.static_dir.join("index.html"))))
+        .route("/api/conv", post(post_conv))
+        .route("/api/authenticate", post(post_authenticate))
+        .route("/api/authenticate", get(get_authenticate))
+        .route("/api/authenticate", delete(logout))
+        .route("/api/health", get(get_health))
+        .route("/api/checkers", get(get_checkers))
+        .route("/api/checker/status", get(get_checker_status))
+        .route("/api/events", get(get_events))
+        .route("/api/checkers/policies", get(get_compare_policies))
+        .route("/api/checkers/sync", post(post_sync_policies))
+        .route("/api/policies", get(get_policies))
+        .route("/api/policies", post(post_add_policy))
+        .route("/api/policies/active", get(get_active_policy))
+        .route("/api/policies/active", post(post_activate_policy))
+        .route("/api/policies/active", delete(delete_deactivate_policy))
+        .route("/api/policies/:version", get(get_policy))
+        .route("/api/proposals", get(get_proposals))
+        .route("/api/proposals", post(post_propose_activation))
+        .route("/api/proposals/:id", delete(delete_proposal))
+        .route("/api/proposals/:id/approve", post(post_approve_proposal))
+        .route("/api/schedules", get(get_schedules))
+        .route("/api/schedules", post(post_schedule))
+        .route("/api/schedules/:id", delete(delete_schedule))
+        .route("/api/audit", get(get_audit))
+        .route("/api/audit/export", get(get_audit_export))
+        .route("/api/audit/verify", get(get_audit_verify))
+        .route("/api/reasoner-connector-info", get(get_reasoner_connector_info))
+        .route("/api/workflow/check", post(post_check_workflow))
+        .route("/api/deliberation/task", post(post_exec_task))
+        .route("/api/deliberation/data", post(post_access_data))
+        .route("/api/deliberation/workflow", post(post_validate_workflow))
+        .route("/api/deliberation/batch", post(post_batch))
+        .route("/api/deliberation/federated", post(post_federated))
+        .route("/api/deliberation/explain", post(post_explain))
+        .route("/api/deliberation/history", get(get_history))
+        .route("/api/deliberation/history/:id", get(get_history_record))
+        .route("/api/deliberation/history/:id/replay", post(post_replay))
+        .layer(middleware::from_fn_with_state(state.clone(), migrate_cookies))
+        .layer(CatchPanicLayer::custom(report_panic))
+        .layer(TimeoutLayer::new(Duration::from_secs(server.request_timeout)))
+        .layer(middleware::from_fn(report_errors))
+        .layer(TraceLayer::new_for_http().on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis)));
+
+    // Allow other sites to use the API, if configured
+    let origins: Vec<HeaderValue> = server
+        .cors_origins
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(origin) => Some(origin),
+            Err(err) => {
+                warn!("Ignoring invalid CORS origin '{origin}': {err}");
+                None
+            },
+        })
+        .collect();
+    if !origins.is_empty() {
+        router = router.layer(
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::list(origins))
+                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+                .allow_headers([CONTENT_TYPE, REQUEST_ID_HEADER])
+                .expose_headers([REQUEST_ID_HEADER])
+                .allow_credentials(true),
+        );
+    }
+
+    router.with_state(state)
+}
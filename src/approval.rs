@@ -0,0 +1,501 @@
+//  APPROVAL.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 09:24:51
+//  Last edited:
+//    19 Oct 2026, 09:24:51
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements the four-eyes principle for policy activation: one user
+//!   proposes to activate a policy version, and only once a different user
+//!   approves it is the activation sent to the checker.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::future::Future;
+use std::path::PathBuf;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{Method, StatusCode, Uri};
+use axum::Json;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
+use chrono::{DateTime, Duration, Utc};
+use enum_debug::EnumDebug;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::audit::{describe, AuditAction, AuditLog};
+use crate::auth::{verified_subject, AppState, AuthDataType};
+use crate::checker::{default_checker, Checker, CheckerError, CheckerResponse, SelectedChecker};
+use crate::errors::ApiError;
+use crate::events::{EventBus, PolicyEventKind};
+use crate::store::{load_json, save_json, StoreError};
+
+
+/***** ERRORS *****/
+/// Defines errors originating from handling proposals.
+#[derive(Debug)]
+pub enum ProposalError {
+    /// There is no proposal with the given identifier.
+    UnknownProposal { id: u64 },
+    /// The proposal was already decided upon (or expired).
+    NotPending { id: u64, status: ProposalStatus },
+    /// The user approving the proposal is the one that proposed it.
+    SelfApproval { id: u64, user: String },
+    /// The proposal is about another checker than the one selected.
+    OtherChecker { id: u64, checker: String },
+    /// Failed to persist the proposals.
+    Store { err: StoreError },
+    /// The checker refused the activation.
+    Checker { id: u64, err: CheckerError },
+}
+impl ProposalError {
+    /// Returns the status code that best represents this error to the client.
+    pub fn status_code(&self) -> StatusCode {
+        use ProposalError::*;
+        match self {
+            UnknownProposal { .. } => StatusCode::NOT_FOUND,
+            NotPending { .. } => StatusCode::CONFLICT,
+            SelfApproval { .. } => StatusCode::FORBIDDEN,
+            OtherChecker { .. } => StatusCode::CONFLICT,
+            Store { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Checker { err, .. } => err.status_code(),
+        }
+    }
+}
+impl Display for ProposalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use ProposalError::*;
+        match self {
+            UnknownProposal { id } => write!(f, "Unknown proposal {id}"),
+            NotPending { id, status } => write!(f, "Proposal {id} is no longer pending (it is {})", status.variant()),
+            SelfApproval { id, user } => write!(f, "User '{user}' cannot approve proposal {id} because they proposed it themselves"),
+            OtherChecker { id, checker } => write!(f, "Proposal {id} is about checker '{checker}'; select that checker to decide on it"),
+            Store { .. } => write!(f, "Failed to persist proposals"),
+            Checker { id, .. } => write!(f, "Failed to activate the policy of proposal {id}"),
+        }
+    }
+}
+impl Error for ProposalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use ProposalError::*;
+        match self {
+            UnknownProposal { .. } | NotPending { .. } | SelfApproval { .. } | OtherChecker { .. } => None,
+            Store { err } => Some(err),
+            Checker { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// The state a [`Proposal`] can be in.
+#[derive(Clone, Copy, Debug, Deserialize, EnumDebug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProposalStatus {
+    /// The proposal awaits approval.
+    Pending,
+    /// The proposal was approved and the policy activated.
+    Approved,
+    /// The proposal was rejected (or withdrawn).
+    Rejected,
+    /// The proposal was approved, but the checker refused the activation.
+    Failed,
+    /// Nobody decided on the proposal in time.
+    Expired,
+}
+
+/// A proposal to activate a particular policy version.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Proposal {
+    /// The identifier of this proposal.
+    pub id: u64,
+    /// The name of the checker to activate the policy on.
+    #[serde(default = "default_checker")]
+    pub checker: String,
+    /// The policy version to activate.
+    pub version: i64,
+    /// The user that proposed the activation.
+    pub proposed_by: String,
+    /// When the activation was proposed.
+    pub proposed_at: DateTime<Utc>,
+    /// When the proposal expires if nobody decided on it.
+    pub expires_at: DateTime<Utc>,
+    /// The current state of the proposal.
+    pub status: ProposalStatus,
+    /// The user that approved or rejected the proposal, if any.
+    pub decided_by: Option<String>,
+    /// When the proposal was approved or rejected, if ever.
+    pub decided_at: Option<DateTime<Utc>>,
+    /// The response of the checker to the activation (or the reason it failed), if any.
+    pub checker_response: Option<String>,
+}
+
+/// The contents of the [`ProposalStore`] as persisted on disk.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Proposals {
+    /// The identifier of the next proposal.
+    next_id:   u64,
+    /// All proposals made so far.
+    proposals: Vec<Proposal>,
+}
+impl Proposals {
+    /// Marks all pending proposals that outlived their expiry time as expired.
+    ///
+    /// # Returns
+    /// Whether any proposal was changed.
+    fn expire(&mut self, now: DateTime<Utc>) -> bool {
+        let mut changed: bool = false;
+        for proposal in &mut self.proposals {
+            if proposal.status == ProposalStatus::Pending && proposal.expires_at <= now {
+                info!("Proposal {} to activate policy version {} expired", proposal.id, proposal.version);
+                proposal.status = ProposalStatus::Expired;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Finds a pending proposal.
+    ///
+    /// # Errors
+    /// This function errors if the proposal does not exist, is about another checker or is not pending anymore.
+    fn pending_mut(&mut self, id: u64, checker: &str) -> Result<&mut Proposal, ProposalError> {
+        let proposal: &mut Proposal = match self.proposals.iter_mut().find(|p| p.id == id) {
+            Some(proposal) => proposal,
+            None => return Err(ProposalError::UnknownProposal { id }),
+        };
+        if proposal.checker != checker {
+            return Err(ProposalError::OtherChecker { id, checker: proposal.checker.clone() });
+        }
+        if proposal.status != ProposalStatus::Pending {
+            return Err(ProposalError::NotPending { id, status: proposal.status });
+        }
+        Ok(proposal)
+    }
+}
+
+
+
+/// Keeps track of activation proposals and persists them to disk.
+#[derive(Debug)]
+pub struct ProposalStore {
+    /// The file to which proposals are persisted.
+    path: PathBuf,
+    /// How long proposals remain pending before they expire.
+    ttl: Duration,
+    /// The proposals themselves.
+    proposals: Mutex<Proposals>,
+}
+impl ProposalStore {
+    /// Constructor for the ProposalStore that loads any existing proposals from disk.
+    ///
+    /// # Arguments
+    /// - `path`: The file to which proposals are persisted.
+    /// - `ttl`: How long proposals remain pending before they expire.
+    ///
+    /// # Returns
+    /// A new ProposalStore.
+    ///
+    /// # Errors
+    /// This function errors if the file exists but could not be loaded.
+    pub fn new(path: impl Into<PathBuf>, ttl: Duration) -> Result<Self, StoreError> {
+        let path: PathBuf = path.into();
+        let proposals: Proposals = load_json(&path)?;
+        Ok(Self { path, ttl, proposals: Mutex::new(proposals) })
+    }
+
+    /// Returns all proposals made so far about the given checker, newest first.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker to return the proposals of.
+    ///
+    /// # Errors
+    /// This function errors if we failed to persist newly expired proposals.
+    pub async fn list(&self, checker: &str) -> Result<Vec<Proposal>, ProposalError> {
+        let mut proposals = self.proposals.lock().await;
+        if proposals.expire(Utc::now()) {
+            save_json(&self.path, &*proposals).map_err(|err| ProposalError::Store { err })?;
+        }
+        Ok(proposals.proposals.iter().rev().filter(|p| p.checker == checker).cloned().collect())
+    }
+
+    /// Proposes to activate the given policy version.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker to activate the policy on.
+    /// - `version`: The policy version to activate.
+    /// - `user`: The user proposing the activation.
+    ///
+    /// # Returns
+    /// The new [`Proposal`].
+    ///
+    /// # Errors
+    /// This function errors if we failed to persist the proposal.
+    pub async fn propose(&self, checker: &str, version: i64, user: String) -> Result<Proposal, ProposalError> {
+        let mut proposals = self.proposals.lock().await;
+        let now: DateTime<Utc> = Utc::now();
+        proposals.expire(now);
+
+        let proposal: Proposal = Proposal {
+            id: proposals.next_id,
+            checker: checker.into(),
+            version,
+            proposed_by: user,
+            proposed_at: now,
+            expires_at: now + self.ttl,
+            status: ProposalStatus::Pending,
+            decided_by: None,
+            decided_at: None,
+            checker_response: None,
+        };
+        proposals.next_id += 1;
+        proposals.proposals.push(proposal.clone());
+        save_json(&self.path, &*proposals).map_err(|err| ProposalError::Store { err })?;
+        info!("User '{}' proposed to activate policy version {} (proposal {})", proposal.proposed_by, proposal.version, proposal.id);
+        Ok(proposal)
+    }
+
+    /// Approves a pending proposal and activates its policy version.
+    ///
+    /// The store remains locked while the policy is activated, so a proposal can never be approved twice.
+    ///
+    /// # Arguments
+    /// - `id`: The identifier of the proposal to approve.
+    /// - `checker`: The name of the selected checker. Must be the one the proposal is about.
+    /// - `user`: The user approving the proposal. Must be different from the one that proposed it.
+    /// - `activate`: A closure that activates the given policy version on the checker.
+    ///
+    /// # Returns
+    /// The approved [`Proposal`].
+    ///
+    /// # Errors
+    /// This function errors if the proposal cannot be approved by this user, if the checker refused the activation or if we failed to
+    /// persist the result.
+    pub async fn approve<F, R>(&self, id: u64, checker: &str, user: String, activate: F) -> Result<Proposal, ProposalError>
+    where
+        F: FnOnce(i64) -> R,
+        R: Future<Output = Result<CheckerResponse, CheckerError>>,
+    {
+        let mut proposals = self.proposals.lock().await;
+        let now: DateTime<Utc> = Utc::now();
+        let expired: bool = proposals.expire(now);
+
+        // Assert we can approve it
+        let proposal: &mut Proposal = match proposals.pending_mut(id, checker) {
+            Ok(proposal) => proposal,
+            Err(err) => {
+                if expired {
+                    save_json(&self.path, &*proposals).map_err(|err| ProposalError::Store { err })?;
+                }
+                return Err(err);
+            },
+        };
+        if proposal.proposed_by == user {
+            return Err(ProposalError::SelfApproval { id, user });
+        }
+
+        // Only now do we go to the checker
+        let res: Result<CheckerResponse, CheckerError> = activate(proposal.version).await;
+        proposal.decided_at = Some(now);
+        let res: Result<Proposal, ProposalError> = match res {
+            Ok(res) => {
+                info!("Proposal {} approved by '{}'; activated policy version {}", id, user, proposal.version);
+                proposal.decided_by = Some(user);
+                proposal.status = ProposalStatus::Approved;
+                proposal.checker_response = Some(res.body);
+                Ok(proposal.clone())
+            },
+            Err(err) => {
+                proposal.decided_by = Some(user);
+                proposal.status = ProposalStatus::Failed;
+                proposal.checker_response = Some(err.to_string());
+                Err(ProposalError::Checker { id, err })
+            },
+        };
+        save_json(&self.path, &*proposals).map_err(|err| ProposalError::Store { err })?;
+        res
+    }
+
+    /// Rejects (or withdraws) a pending proposal.
+    ///
+    /// # Arguments
+    /// - `id`: The identifier of the proposal to reject.
+    /// - `checker`: The name of the selected checker. Must be the one the proposal is about.
+    /// - `user`: The user rejecting the proposal.
+    ///
+    /// # Returns
+    /// The rejected [`Proposal`].
+    ///
+    /// # Errors
+    /// This function errors if the proposal is not pending or if we failed to persist the result.
+    pub async fn reject(&self, id: u64, checker: &str, user: String) -> Result<Proposal, ProposalError> {
+        let mut proposals = self.proposals.lock().await;
+        let now: DateTime<Utc> = Utc::now();
+        proposals.expire(now);
+
+        let proposal: &mut Proposal = proposals.pending_mut(id, checker)?;
+        info!("User '{}' rejected proposal {} to activate policy version {}", user, id, proposal.version);
+        proposal.status = ProposalStatus::Rejected;
+        proposal.decided_by = Some(user);
+        proposal.decided_at = Some(now);
+        let proposal: Proposal = proposal.clone();
+        save_json(&self.path, &*proposals).map_err(|err| ProposalError::Store { err })?;
+        Ok(proposal)
+    }
+}
+
+
+
+/// The body sent by the client to propose an activation.
+#[derive(Deserialize, Serialize)]
+pub struct ProposalPostModel {
+    /// The policy version to activate.
+    pub version: i64,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Serializes a (list of) proposal(s) to send back to the client.
+fn to_response<T: Serialize>(value: &T) -> (StatusCode, String) {
+    match serde_json::to_string(value) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+// List proposals
+pub async fn get_proposals(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, String) {
+    if jar.get(&AuthDataType::Policy.cookie(&checker.name)).is_none() {
+        return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+    }
+
+    match state.proposals.list(&checker.name).await {
+        Ok(proposals) => to_response(&proposals),
+        Err(err) => err!(err.status_code(), err),
+    }
+}
+
+// Propose to activate a version
+pub async fn post_propose_activation(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    raw: Bytes,
+) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    // Parse the body ourselves, as the audit log needs it exactly as it was sent
+    let body: ProposalPostModel = match Json::from_bytes(&raw) {
+        Ok(Json(body)) => body,
+        Err(err) => return ApiError::new(err.status(), err.body_text()).into_parts(),
+    };
+    let user: String = match verified_subject(&checker, policy_auth_token.value()).await {
+        Ok(user) => user,
+        Err(err) => return err.into_parts(),
+    };
+
+    match state.proposals.propose(&checker.name, body.version, user).await {
+        Ok(proposal) => {
+            state
+                .audit
+                .record_by(
+                    &proposal.proposed_by,
+                    &checker.name,
+                    AuditAction::ProposeActivation,
+                    Some(proposal.version),
+                    None,
+                    &raw,
+                )
+                .await;
+            to_response(&proposal)
+        },
+        Err(err) => err!(err.status_code(), err),
+    }
+}
+
+// Approve a proposal, activating its version
+pub async fn post_approve_proposal(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    Path(id): Path<u64>,
+    method: Method,
+    uri: Uri,
+) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    let user: String = match verified_subject(&checker, policy_auth_token.value()).await {
+        Ok(user) => user,
+        Err(err) => return err.into_parts(),
+    };
+
+    let request: Vec<u8> = describe(&method, &uri);
+    let (checker, audit, events, token, approver, request): (&Checker, &AuditLog, &EventBus, &str, &str, &[u8]) =
+        (&checker, &state.audit, &state.events, policy_auth_token.value(), &user, &request);
+    let activate = |version: i64| async move {
+        let result = checker.activate_policy(token, version).await;
+        audit.record_by(approver, &checker.name, AuditAction::ApproveActivation, Some(version), Some(&result), request).await;
+        events.changed(&checker.name, PolicyEventKind::Activated, Some(version), Some(token), &result).await;
+        result
+    };
+    match state.proposals.approve(id, &checker.name, user.clone(), activate).await {
+        Ok(proposal) => to_response(&proposal),
+        Err(err) => err!(err.status_code(), err),
+    }
+}
+
+// Reject or withdraw a proposal
+pub async fn delete_proposal(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    Path(id): Path<u64>,
+    method: Method,
+    uri: Uri,
+) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    let user: String = match verified_subject(&checker, policy_auth_token.value()).await {
+        Ok(user) => user,
+        Err(err) => return err.into_parts(),
+    };
+
+    match state.proposals.reject(id, &checker.name, user.clone()).await {
+        Ok(proposal) => {
+            state.audit.record_by(&user, &checker.name, AuditAction::RejectActivation, Some(proposal.version), None, &describe(&method, &uri)).await;
+            to_response(&proposal)
+        },
+        Err(err) => err!(err.status_code(), err),
+    }
+}
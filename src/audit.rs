@@ -0,0 +1,531 @@
+//  AUDIT.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 11:15:33
+//  Last edited:
+//    19 Oct 2026, 11:15:33
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements an append-only audit log of all management actions that
+//!   went through the backend.
+//!
+//!   The log is stored as JSON Lines. Every entry carries the hash of the
+//!   entry before it, and its own hash covers that, so removing or altering
+//!   an entry breaks the chain from that point onwards.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::PathBuf;
+
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::{Method, StatusCode, Uri};
+use axum::response::{IntoResponse as _, Response};
+use axum_extra::extract::cookie::PrivateCookieJar;
+use chrono::{DateTime, Utc};
+use error_trace::ErrorTrace as _;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::sync::Mutex;
+
+use crate::auth::{jwt_subject, verified_manager, AppState};
+use crate::checker::{CheckerError, CheckerResponse, SelectedChecker, DEFAULT_CHECKER};
+
+
+/***** CONSTANTS *****/
+/// The hash that the first entry in the log refers to as its predecessor.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+
+
+
+
+/***** ERRORS *****/
+/// Defines errors originating from the audit log.
+#[derive(Debug)]
+pub enum AuditError {
+    /// Failed to read the log file.
+    Read { path: PathBuf, err: std::io::Error },
+    /// The hash chain of the existing log file is broken.
+    Corrupt { path: PathBuf, line: u64, reason: String },
+    /// Failed to serialize an entry.
+    Serialize { err: serde_json::Error },
+    /// Failed to append to the log file.
+    Write { path: PathBuf, err: std::io::Error },
+}
+impl Display for AuditError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use AuditError::*;
+        match self {
+            Read { path, .. } => write!(f, "Failed to read audit log '{}'", path.display()),
+            Corrupt { path, line, reason } => write!(
+                f,
+                "Audit log '{}' is corrupted or tampered with at line {line} ({reason}); inspect it and move it aside to start a new log",
+                path.display()
+            ),
+            Serialize { .. } => write!(f, "Failed to serialize audit log entry"),
+            Write { path, .. } => write!(f, "Failed to append to audit log '{}'", path.display()),
+        }
+    }
+}
+impl Error for AuditError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use AuditError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            Corrupt { .. } => None,
+            Serialize { err } => Some(err),
+            Write { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// The management actions that are audited.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A policy was uploaded.
+    AddPolicy,
+    /// A policy was activated directly.
+    ActivatePolicy,
+    /// The active policy was deactivated.
+    DeactivatePolicy,
+    /// An activation was proposed.
+    ProposeActivation,
+    /// A proposed activation was approved (and thus executed).
+    ApproveActivation,
+    /// A proposed activation was rejected or withdrawn.
+    RejectActivation,
+    /// An activation was scheduled.
+    ScheduleActivation,
+    /// A scheduled activation was cancelled.
+    CancelSchedule,
+    /// A scheduled activation was executed.
+    ScheduledActivation,
+    /// A scheduled activation was rolled back because its health checks failed.
+    ScheduledRollback,
+}
+
+/// The hashed part of an [`AuditEntry`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditRecord {
+    /// The position of this entry in the log, starting at 0.
+    pub seq: u64,
+    /// When the action took place.
+    pub timestamp: DateTime<Utc>,
+    /// The subject of the JWT of the user performing the action, if known.
+    pub subject: Option<String>,
+    /// The action performed.
+    pub action: AuditAction,
+    /// The name of the checker acted upon, if it was not the default one.
+    ///
+    /// Omitted otherwise, so entries written before there were multiple checkers keep their hashes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checker: Option<String>,
+    /// The policy version acted upon, if known.
+    pub version: Option<i64>,
+    /// The status code with which the checker responded, or [`None`] if it wasn't reached (or wasn't involved).
+    pub status: Option<u16>,
+    /// The SHA-256 hash of the request that caused the action.
+    pub request_hash: String,
+    /// The hash of the previous entry in the log.
+    pub prev_hash: String,
+}
+impl AuditRecord {
+    /// Computes the hash of this record, which is the hash of its canonical serialization.
+    ///
+    /// # Errors
+    /// This function errors if the record could not be serialized.
+    fn hash(&self) -> Result<String, AuditError> {
+        let raw: Vec<u8> = serde_json::to_vec(self).map_err(|err| AuditError::Serialize { err })?;
+        Ok(hash(&raw))
+    }
+}
+
+/// A single entry in the audit log.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// The contents of the entry.
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    /// The hash of `record`.
+    pub hash:   String,
+}
+
+/// The result of verifying the hash chain of the audit log.
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditVerification {
+    /// Whether the chain is intact.
+    pub valid:     bool,
+    /// The number of entries in the log that were verified to be intact.
+    pub entries:   u64,
+    /// The line (starting at 1) at which the chain is broken, if it is.
+    pub broken_at: Option<u64>,
+    /// Why the chain is broken, if it is.
+    pub reason:    Option<String>,
+}
+
+
+
+/// The filters with which the audit log can be queried.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    /// Only return entries of this action.
+    pub action:  Option<AuditAction>,
+    /// Only return entries by this subject.
+    pub subject: Option<String>,
+    /// Only return entries about this checker.
+    pub checker: Option<String>,
+    /// Only return entries about this policy version.
+    pub version: Option<i64>,
+    /// Only return entries at or after this time.
+    pub since:   Option<DateTime<Utc>>,
+    /// Only return entries at or before this time.
+    pub until:   Option<DateTime<Utc>>,
+    /// Return at most this many entries (the newest ones).
+    pub limit:   Option<usize>,
+}
+impl AuditQuery {
+    /// Checks whether the given record passes the filters.
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.action.map_or(true, |action| record.action == action)
+            && self.subject.as_ref().map_or(true, |subject| record.subject.as_ref() == Some(subject))
+            && self.checker.as_ref().map_or(true, |checker| record.checker.as_deref().unwrap_or(DEFAULT_CHECKER) == checker)
+            && self.version.map_or(true, |version| record.version == Some(version))
+            && self.since.map_or(true, |since| record.timestamp >= since)
+            && self.until.map_or(true, |until| record.timestamp <= until)
+    }
+}
+
+
+
+/// The end of the hash chain, needed to append new entries.
+#[derive(Debug)]
+struct ChainTail {
+    /// The sequence number of the next entry.
+    seq:  u64,
+    /// The hash of the last entry.
+    hash: String,
+}
+
+/// Implements the append-only audit log.
+#[derive(Debug)]
+pub struct AuditLog {
+    /// The JSON Lines file to which entries are appended.
+    path: PathBuf,
+    /// The end of the hash chain. Also serializes writes to the file.
+    tail: Mutex<ChainTail>,
+}
+impl AuditLog {
+    /// Constructor for the AuditLog that picks up where an existing log file left off.
+    ///
+    /// Refuses to continue a log whose hash chain is broken, since entries appended to it could never be verified again.
+    ///
+    /// # Arguments
+    /// - `path`: The JSON Lines file to which entries are appended.
+    ///
+    /// # Returns
+    /// A new AuditLog.
+    ///
+    /// # Errors
+    /// This function errors if the log file exists but could not be read, or if its hash chain is broken.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, AuditError> {
+        let path: PathBuf = path.into();
+        let raw: String = match std::fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(AuditError::Read { path, err }),
+        };
+
+        // Verify the chain while we find its end
+        let verification: AuditVerification = verify(&raw);
+        if !verification.valid {
+            return Err(AuditError::Corrupt {
+                path,
+                line: verification.broken_at.unwrap_or_default(),
+                reason: verification.reason.unwrap_or_default(),
+            });
+        }
+        let last: Option<AuditEntry> = raw.lines().rev().find_map(|line| serde_json::from_str(line).ok());
+        let tail: ChainTail = match last {
+            Some(entry) => ChainTail { seq: entry.record.seq + 1, hash: entry.hash },
+            None => ChainTail { seq: 0, hash: GENESIS_HASH.into() },
+        };
+        debug!("Opened audit log '{}' ({} entries)", path.display(), verification.entries);
+        Ok(Self { path, tail: Mutex::new(tail) })
+    }
+
+    /// Appends a new entry to the log.
+    ///
+    /// # Arguments
+    /// - `subject`: The subject of the JWT of the user performing the action, if known.
+    /// - `checker`: The name of the checker acted upon.
+    /// - `action`: The action performed.
+    /// - `version`: The policy version acted upon, if known.
+    /// - `status`: The status code with which the checker responded, if it did.
+    /// - `request`: The raw request that caused the action.
+    ///
+    /// # Errors
+    /// This function errors if we failed to write the entry.
+    pub async fn append(
+        &self,
+        subject: Option<String>,
+        checker: &str,
+        action: AuditAction,
+        version: Option<i64>,
+        status: Option<u16>,
+        request: &[u8],
+    ) -> Result<AuditEntry, AuditError> {
+        let mut tail = self.tail.lock().await;
+        let record: AuditRecord = AuditRecord {
+            seq: tail.seq,
+            timestamp: Utc::now(),
+            subject,
+            checker: if checker == DEFAULT_CHECKER { None } else { Some(checker.into()) },
+            action,
+            version,
+            status,
+            request_hash: hash(request),
+            prev_hash: tail.hash.clone(),
+        };
+        let entry: AuditEntry = AuditEntry { hash: record.hash()?, record };
+
+        // Write it
+        let mut line: String = serde_json::to_string(&entry).map_err(|err| AuditError::Serialize { err })?;
+        line.push('\n');
+        let mut handle = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|err| AuditError::Write { path: self.path.clone(), err })?;
+        handle.write_all(line.as_bytes()).map_err(|err| AuditError::Write { path: self.path.clone(), err })?;
+        handle.sync_data().map_err(|err| AuditError::Write { path: self.path.clone(), err })?;
+
+        // Move the chain along
+        tail.seq += 1;
+        tail.hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// Records an action, logging (instead of returning) any failure to do so.
+    ///
+    /// Failing to audit is not a reason to fail the action itself, as it has already been sent to the checker by the time we get here.
+    ///
+    /// The subject is only taken from the token if the checker accepted it, as anyone can make up a token that claims to be somebody. Use
+    /// [`AuditLog::record_by()`] if the identity of the user was verified otherwise.
+    ///
+    /// Only the hash of the request is stored. It is the hash of the body exactly as the client sent it, or, for requests without one, of
+    /// [`describe()`] (i.e., `<METHOD> <PATH>[?<QUERY>]`). Actions the backend performs later on behalf of a request (e.g., a scheduled
+    /// activation) record the request that caused them, and synchronizing checkers records the synchronization request for every checker.
+    ///
+    /// # Arguments
+    /// - `token`: The JWT of the user performing the action, if known.
+    /// - `checker`: The name of the checker acted upon.
+    /// - `action`: The action performed.
+    /// - `version`: The policy version acted upon, if known.
+    /// - `res`: The result of the checker call that performed the action, if any.
+    /// - `request`: The raw request that caused the action, as described above.
+    pub async fn record(
+        &self,
+        token: Option<&str>,
+        checker: &str,
+        action: AuditAction,
+        version: Option<i64>,
+        res: Option<&Result<CheckerResponse, CheckerError>>,
+        request: &[u8],
+    ) {
+        // The checker authenticates before anything else, so any other response than these means it accepted the token
+        let accepted: bool = match res {
+            Some(Ok(_)) => true,
+            Some(Err(err)) => err.upstream_status().is_some_and(|status| status != 401 && status != 403),
+            None => false,
+        };
+        let subject: Option<String> = if accepted { token.and_then(jwt_subject) } else { None };
+        self.record_subject(subject, checker, action, version, res, request).await
+    }
+
+    /// Records an action by a user whose identity was verified, logging (instead of returning) any failure to do so.
+    ///
+    /// # Arguments
+    /// - `subject`: The verified identity of the user performing the action (e.g., from [`verified_subject()`](crate::auth::verified_subject)).
+    /// - `checker`: The name of the checker acted upon.
+    /// - `action`: The action performed.
+    /// - `version`: The policy version acted upon, if known.
+    /// - `res`: The result of the checker call that performed the action, if any.
+    /// - `request`: The raw request that caused the action, as described for [`AuditLog::record()`].
+    pub async fn record_by(
+        &self,
+        subject: &str,
+        checker: &str,
+        action: AuditAction,
+        version: Option<i64>,
+        res: Option<&Result<CheckerResponse, CheckerError>>,
+        request: &[u8],
+    ) {
+        self.record_subject(Some(subject.into()), checker, action, version, res, request).await
+    }
+
+    /// Implements [`AuditLog::record()`] and [`AuditLog::record_by()`].
+    async fn record_subject(
+        &self,
+        subject: Option<String>,
+        checker: &str,
+        action: AuditAction,
+        version: Option<i64>,
+        res: Option<&Result<CheckerResponse, CheckerError>>,
+        request: &[u8],
+    ) {
+        let status: Option<u16> = res.and_then(|res| match res {
+            Ok(res) => Some(res.status),
+            Err(err) => err.upstream_status(),
+        });
+        if let Err(err) = self.append(subject, checker, action, version, status, request).await {
+            error!("{}", err.trace());
+        }
+    }
+
+    /// Reads all entries matching the given query.
+    ///
+    /// # Errors
+    /// This function errors if we failed to read the log.
+    pub async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditError> {
+        let raw: String = self.read().await?;
+        let mut entries: Vec<AuditEntry> =
+            raw.lines().filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok()).filter(|entry| query.matches(&entry.record)).collect();
+        if let Some(limit) = query.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        Ok(entries)
+    }
+
+    /// Reads the raw log, e.g., to export it.
+    ///
+    /// # Errors
+    /// This function errors if we failed to read the log.
+    pub async fn read(&self) -> Result<String, AuditError> {
+        // Lock to not read half-written lines
+        let _tail = self.tail.lock().await;
+        match std::fs::read_to_string(&self.path) {
+            Ok(raw) => Ok(raw),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(err) => Err(AuditError::Read { path: self.path.clone(), err }),
+        }
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Computes the hex-encoded SHA-256 hash of the given bytes.
+pub fn hash(raw: &[u8]) -> String { hex::encode(Sha256::digest(raw)) }
+
+/// Describes a request without a body for the audit log, as `<METHOD> <PATH>[?<QUERY>]`.
+///
+/// # Arguments
+/// - `method`: The method of the request.
+/// - `uri`: The URI of the request.
+///
+/// # Returns
+/// The raw description, to give to [`AuditLog::record()`].
+pub fn describe(method: &Method, uri: &Uri) -> Vec<u8> { format!("{method} {}", uri.path_and_query().map_or(uri.path(), |pq| pq.as_str())).into_bytes() }
+
+/// Verifies the hash chain of a raw audit log.
+///
+/// # Arguments
+/// - `raw`: The contents of the log file.
+///
+/// # Returns
+/// An [`AuditVerification`] describing whether (and where) the chain is broken.
+pub fn verify(raw: &str) -> AuditVerification {
+    let mut prev: String = GENESIS_HASH.into();
+    let mut entries: u64 = 0;
+    for (i, line) in raw.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let broken = |reason: String| AuditVerification { valid: false, entries, broken_at: Some(i as u64 + 1), reason: Some(reason) };
+
+        let entry: AuditEntry = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(err) => return broken(format!("entry is not valid: {err}")),
+        };
+        if entry.record.seq != entries {
+            return broken(format!("expected sequence number {entries}, got {}", entry.record.seq));
+        }
+        if entry.record.prev_hash != prev {
+            return broken("entry does not refer to the previous entry".into());
+        }
+        match entry.record.hash() {
+            Ok(hash) if hash == entry.hash => {},
+            Ok(_) => return broken("entry does not match its hash".into()),
+            Err(err) => return broken(format!("entry cannot be hashed: {err}")),
+        }
+        prev = entry.hash;
+        entries += 1;
+    }
+    AuditVerification { valid: true, entries, broken_at: None, reason: None }
+}
+
+
+
+// Query the audit log
+pub async fn get_audit(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    Query(query): Query<AuditQuery>,
+) -> (StatusCode, String) {
+    if let Err(err) = verified_manager(&checker, &jar).await {
+        return err.into_parts();
+    }
+
+    let entries: Vec<AuditEntry> = match state.audit.query(&query).await {
+        Ok(entries) => entries,
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    match serde_json::to_string(&entries) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+// Export the raw audit log
+pub async fn get_audit_export(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar) -> Response {
+    if let Err(err) = verified_manager(&checker, &jar).await {
+        return err.into_parts().into_response();
+    }
+
+    match state.audit.read().await {
+        Ok(raw) => (
+            StatusCode::OK,
+            [(CONTENT_TYPE, "application/x-ndjson"), (CONTENT_DISPOSITION, "attachment; filename=\"audit.jsonl\"")],
+            raw,
+        )
+            .into_response(),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+// Verify the hash chain of the audit log
+pub async fn get_audit_verify(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, String) {
+    if let Err(err) = verified_manager(&checker, &jar).await {
+        return err.into_parts();
+    }
+
+    let raw: String = match state.audit.read().await {
+        Ok(raw) => raw,
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    match serde_json::to_string(&verify(&raw)) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
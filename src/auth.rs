@@ -1,46 +1,408 @@
-use std::fs::{read_to_string, File};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs::OpenOptions;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use axum::extract::FromRef;
-use axum::http::StatusCode;
+use axum::extract::{FromRef, Request, State};
+use axum::http::header::{COOKIE, SET_COOKIE};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
 use axum::Json;
-use axum_extra::extract::cookie::{Cookie, Key};
+use axum_extra::extract::cookie::{Cookie, CookieJar, Key};
 use axum_extra::extract::PrivateCookieJar;
 use base64::engine::general_purpose;
 use base64::Engine as _;
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use specifications::address::Address;
 
-pub fn get_key(p: &str) -> Key {
-    match read_to_string::<&str>(p.into()) {
-        Ok(contents) => {
-            let key_bytes = general_purpose::STANDARD
-                .decode(&contents)
-                .expect("Invalid key bytes");
+use crate::approval::ProposalStore;
+use crate::audit::AuditLog;
+use crate::checker::{Checker, CheckerError, Checkers, SelectedChecker, DEFAULT_CHECKER};
+use crate::config::ConvConfig;
+use crate::errors::ApiError;
+use crate::events::EventBus;
+use crate::health::HealthMonitor;
+use crate::history::HistoryStore;
+use crate::rules::WorkflowRules;
+use crate::schedule::ScheduleStore;
 
-            Key::from(&key_bytes)
+
+/***** CONSTANTS *****/
+/// The names of the (encrypted) cookies in which we store the user's tokens.
+pub const AUTH_COOKIES: [&str; 2] = ["reasoner_policy_auth", "reasoner_deliberation_auth"];
+
+
+
+
+
+/***** ERRORS *****/
+/// Defines errors originating from loading, generating or rotating cookie keys.
+#[derive(Debug)]
+pub enum KeyError {
+    /// Failed to read the key file.
+    Read { path: PathBuf, err: std::io::Error },
+    /// A line in the key file was not valid base64.
+    Decode { path: PathBuf, line: usize, err: base64::DecodeError },
+    /// A line in the key file did not encode a valid key.
+    Invalid { path: PathBuf, line: usize, err: cookie::KeyError },
+    /// The key file exists but contains no keys.
+    Empty { path: PathBuf },
+    /// Failed to write the key file.
+    Write { path: PathBuf, err: std::io::Error },
+}
+impl Display for KeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use KeyError::*;
+        match self {
+            Read { path, .. } => write!(f, "Failed to read key file '{}'", path.display()),
+            Decode { path, line, .. } => write!(f, "Line {} in key file '{}' is not valid base64", line, path.display()),
+            Invalid { path, line, .. } => write!(f, "Line {} in key file '{}' is not a valid key", line, path.display()),
+            Empty { path } => write!(f, "Key file '{}' contains no keys (remove it to generate a new one)", path.display()),
+            Write { path, .. } => write!(f, "Failed to write key file '{}'", path.display()),
+        }
+    }
+}
+impl Error for KeyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use KeyError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            Decode { err, .. } => Some(err),
+            Invalid { err, .. } => Some(err),
+            Empty { .. } => None,
+            Write { err, .. } => Some(err),
         }
-        Err(_) => {
-            let key = Key::generate();
+    }
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Writes the given keys to the given file, readable only by the current user.
+///
+/// # Arguments
+/// - `path`: The path of the key file to write.
+/// - `keys`: The keys to write, oldest first.
+///
+/// # Errors
+/// This function errors if we failed to write the file.
+fn write_keys(path: &Path, keys: &[Key]) -> Result<(), KeyError> {
+    let mut opts: OpenOptions = OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt as _;
+        opts.mode(0o600);
+    }
+    let mut handle = opts.open(path).map_err(|err| KeyError::Write { path: path.into(), err })?;
+
+    // The mode only applies to new files, so make sure existing ones are restricted too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        handle.set_permissions(std::fs::Permissions::from_mode(0o600)).map_err(|err| KeyError::Write { path: path.into(), err })?;
+    }
+
+    for key in keys {
+        writeln!(handle, "{}", general_purpose::STANDARD.encode(key.master())).map_err(|err| KeyError::Write { path: path.into(), err })?;
+    }
+    handle.flush().map_err(|err| KeyError::Write { path: path.into(), err })
+}
+
 
-            let b = key.master();
-            let b64key = general_purpose::STANDARD.encode(&b);
 
-            let mut output = File::create(p).expect("Could not create file to store key");
-            write!(output, "{}", b64key).expect("Could not write key to file");
-            // output.write(b64key.into());
-            output.flush().expect("Could not flush key to file");
-            key
+
+
+/***** AUXILLARY *****/
+/// The keys used to encrypt the auth cookies.
+///
+/// New cookies are always encrypted with the newest key, but cookies encrypted with older keys are still accepted (and re-encrypted with the
+/// newest one) until those keys are rotated out.
+#[derive(Clone, Debug)]
+pub struct KeyRing {
+    /// The newest key, used to encrypt new cookies.
+    current:  Key,
+    /// The older keys, oldest first, only used to decrypt existing cookies.
+    previous: Vec<Key>,
+}
+impl KeyRing {
+    /// Builds a KeyRing from the given keys, oldest first.
+    ///
+    /// # Arguments
+    /// - `keys`: The keys, the last of which becomes the current one.
+    ///
+    /// # Returns
+    /// A new KeyRing, or [`None`] if there were no keys.
+    fn from_keys(mut keys: Vec<Key>) -> Option<Self> {
+        let current: Key = keys.pop()?;
+        Some(Self { current, previous: keys })
+    }
+
+
+    /// Loads the keys from the given key file, generating a new one if the file does not exist.
+    ///
+    /// The key file contains one base64-encoded key per line, oldest first.
+    ///
+    /// # Arguments
+    /// - `path`: The path of the key file.
+    ///
+    /// # Returns
+    /// A new KeyRing.
+    ///
+    /// # Errors
+    /// This function errors if the key file could not be read or written, or if it contains invalid keys.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KeyError> {
+        let path: &Path = path.as_ref();
+
+        // Read the file, or generate a new one
+        let contents: String = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("Key file '{}' does not exist; generating a new key", path.display());
+                let current: Key = Key::generate();
+                write_keys(path, std::slice::from_ref(&current))?;
+                return Ok(Self { current, previous: vec![] });
+            },
+            Err(err) => return Err(KeyError::Read { path: path.into(), err }),
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            if let Ok(meta) = std::fs::metadata(path) {
+                if meta.permissions().mode() & 0o077 != 0 {
+                    warn!("Key file '{}' is accessible by other users; consider restricting it with 'chmod 600'", path.display());
+                }
+            }
+        }
+
+        // Parse the keys in it
+        let mut keys: Vec<Key> = vec![];
+        for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let bytes: Vec<u8> = general_purpose::STANDARD.decode(line.trim()).map_err(|err| KeyError::Decode { path: path.into(), line: i + 1, err })?;
+            keys.push(Key::try_from(bytes.as_slice()).map_err(|err| KeyError::Invalid { path: path.into(), line: i + 1, err })?);
         }
+        debug!("Loaded {} key(s) from key file '{}'", keys.len(), path.display());
+        Self::from_keys(keys).ok_or_else(|| KeyError::Empty { path: path.into() })
     }
+
+    /// Adds a new key to the given key file, dropping the oldest keys so at most `keep` remain.
+    ///
+    /// Cookies encrypted with dropped keys are no longer accepted, so users holding them will have to log in again.
+    ///
+    /// # Arguments
+    /// - `path`: The path of the key file.
+    /// - `keep`: The number of keys to keep, including the new one. Values below 1 are treated as 1.
+    ///
+    /// # Returns
+    /// The KeyRing as it is after rotation.
+    ///
+    /// # Errors
+    /// This function errors if the key file could not be read or written, or if it contains invalid keys.
+    pub fn rotate(path: impl AsRef<Path>, keep: usize) -> Result<Self, KeyError> {
+        let path: &Path = path.as_ref();
+        let Self { current, mut previous } = Self::load(path)?;
+        previous.push(current);
+        let drop: usize = (previous.len() + 1).saturating_sub(keep.max(1));
+        previous.drain(..drop);
+        let rotated: Self = Self { current: Key::generate(), previous };
+        write_keys(path, &rotated.keys().cloned().collect::<Vec<Key>>())?;
+        info!("Rotated key file '{}' (now holds {} key(s), dropped {})", path.display(), rotated.previous.len() + 1, drop);
+        Ok(rotated)
+    }
+
+    /// Returns the newest key, which is used to encrypt new cookies.
+    #[inline]
+    pub fn current(&self) -> &Key { &self.current }
+
+    /// Returns the older keys, which are only used to decrypt existing cookies, newest first.
+    #[inline]
+    pub fn previous(&self) -> impl Iterator<Item = &Key> { self.previous.iter().rev() }
+
+    /// Returns all keys, oldest first, as they are stored in the key file.
+    #[inline]
+    fn keys(&self) -> impl Iterator<Item = &Key> { self.previous.iter().chain(std::iter::once(&self.current)) }
 }
 
-#[derive(Deserialize)]
+
+
+
+
+/***** LIBRARY *****/
+/// Middleware that re-encrypts auth cookies that were encrypted with an older key with the newest one.
+///
+/// Cookies that no key can decrypt are left alone; the handlers will simply not see them.
+pub async fn migrate_cookies(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    if state.keys.previous().next().is_none() {
+        return next.run(req).await;
+    }
+
+    // Find the auth cookies that only an older key can decrypt
+    let jar: CookieJar = CookieJar::from_headers(req.headers());
+    let mut migrated: Vec<Cookie<'static>> = vec![];
+    for cookie in jar.iter().filter(|c| is_auth_cookie(c.name())) {
+        let name: &str = cookie.name();
+        let mut raw: cookie::CookieJar = cookie::CookieJar::new();
+        raw.add_original(cookie.clone());
+        if raw.private(state.keys.current()).get(name).is_some() {
+            continue;
+        }
+        let Some(decrypted) = state.keys.previous().find_map(|key| raw.private(key).get(name)) else { continue };
+
+        // Re-encrypt it with the current key
+        let mut out: cookie::CookieJar = cookie::CookieJar::new();
+        out.private_mut(state.keys.current()).add(decrypted);
+        if let Some(encrypted) = out.get(name) {
+            debug!("Migrating cookie '{name}' to the current key");
+            migrated.push(encrypted.clone());
+        }
+    }
+    if migrated.is_empty() {
+        return next.run(req).await;
+    }
+
+    // Replace them in the request so the handlers can read them...
+    let cookies: String = jar
+        .iter()
+        .map(|c| migrated.iter().find(|m| m.name() == c.name()).unwrap_or(c))
+        .map(|c| format!("{}={}", c.name(), c.value()))
+        .collect::<Vec<String>>()
+        .join("; ");
+    req.headers_mut().remove(COOKIE);
+    if let Ok(value) = HeaderValue::from_str(&cookies) {
+        req.headers_mut().insert(COOKIE, value);
+    }
+
+    // ...and tell the browser to store the new versions
+    let mut res: Response = next.run(req).await;
+    for mut cookie in migrated {
+        cookie.set_path("/api");
+        cookie.set_secure(true);
+        cookie.set_http_only(true);
+        if let Ok(value) = HeaderValue::from_str(&cookie.encoded().to_string()) {
+            res.headers_mut().append(SET_COOKIE, value);
+        }
+    }
+    res
+}
+
+/// Checks whether a cookie with the given name carries a token, for any checker.
+///
+/// # Arguments
+/// - `name`: The name of the cookie.
+///
+/// # Returns
+/// Whether the cookie is one of the [`AUTH_COOKIES`], possibly for a particular checker.
+pub fn is_auth_cookie(name: &str) -> bool {
+    AUTH_COOKIES.iter().any(|base| name == *base || name.strip_prefix(base).map_or(false, |rest| rest.starts_with('.')))
+}
+
+/// Checks whether the user has given a token of the given type for any checker.
+///
+/// Used by routes that are about the backend itself rather than about a particular checker.
+///
+/// # Arguments
+/// - `jar`: The [`PrivateCookieJar`] with the user's cookies.
+/// - `state`: The [`AppState`] with the checkers.
+/// - `t`: The type of token to look for.
+///
+/// # Returns
+/// Whether such a token exists.
+pub fn has_any_token(jar: &PrivateCookieJar, state: &AppState, t: AuthDataType) -> bool {
+    state.checkers.iter().any(|checker| jar.get(&t.cookie(&checker.name)).is_some())
+}
+
+/// Extracts the identity of the user to which the given JWT was issued.
+///
+/// Note that the token is NOT validated here; see [`verified_subject`] for that. The identity is read from the `sub`-claim, or, if that is missing, from
+/// the `username`-claim used by the policy reasoner's own JWT tool.
+///
+/// # Arguments
+/// - `token`: The raw JWT to read the identity from.
+///
+/// # Returns
+/// The identity of the user, or [`None`] if the token is malformed or carries no identity.
+pub fn jwt_subject(token: &str) -> Option<String> {
+    let payload: &str = token.split('.').nth(1)?;
+    let payload: Vec<u8> = general_purpose::URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    ["sub", "username"].into_iter().find_map(|claim| claims.get(claim).and_then(|value| value.as_str()).map(String::from))
+}
+
+/// Establishes the identity of the user to which the given JWT was issued, having the checker verify the token first.
+///
+/// Use this instead of [`jwt_subject`] whenever the identity decides what the user may do (e.g., who may approve a proposal), since anyone can
+/// forge a token that merely claims to be somebody.
+///
+/// # Arguments
+/// - `checker`: The [`Checker`] that issued (or at least accepts) the token.
+/// - `token`: The raw JWT to verify and read the identity from.
+///
+/// # Returns
+/// The identity of the user.
+///
+/// # Errors
+/// This function errors if the checker rejects the token, if it cannot be reached or if the token carries no identity.
+pub async fn verified_subject(checker: &Checker, token: &str) -> Result<String, ApiError> {
+    match checker.get_active_version(token).await {
+        Ok(_) => (),
+        Err(CheckerError::Status { code: 401 | 403, .. }) => {
+            let message: String = format!("Checker '{}' did not accept the given token", checker.name);
+            return Err(ApiError::new(StatusCode::UNAUTHORIZED, message).with_code("invalid_token"));
+        },
+        Err(err) => return Err(err.api_error().context("Failed to verify the given token")),
+    }
+    jwt_subject(token).ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "Cannot determine the user from the given token"))
+}
+
+/// Establishes that the user is a manager of the given checker, i.e., that they have a management token that the checker actually accepts.
+///
+/// Use this to guard routes that reveal what other users did (e.g., the audit log), for which merely having a token in a cookie is not enough.
+///
+/// # Arguments
+/// - `checker`: The [`Checker`] selected by the request.
+/// - `jar`: The [`PrivateCookieJar`] with the user's cookies.
+///
+/// # Returns
+/// The verified identity of the user.
+///
+/// # Errors
+/// This function errors if the user has no management token for the checker, or if the checker does not accept it.
+pub async fn verified_manager(checker: &Checker, jar: &PrivateCookieJar) -> Result<String, ApiError> {
+    match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(token) => verified_subject(checker, token.value()).await,
+        None => Err(ApiError::new(StatusCode::UNAUTHORIZED, "Unauthorized")),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AuthDataType {
     Policy,
     Deliberation,
 }
+impl AuthDataType {
+    /// Returns the name of the cookie in which tokens of this type are stored for the given checker.
+    ///
+    /// The [`DEFAULT_CHECKER`] uses the plain names in [`AUTH_COOKIES`], so sessions from before there were multiple checkers remain valid.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker the token is for.
+    ///
+    /// # Returns
+    /// The name of the cookie.
+    pub fn cookie(&self, checker: &str) -> String {
+        let base: &str = match self {
+            Self::Policy => AUTH_COOKIES[0],
+            Self::Deliberation => AUTH_COOKIES[1],
+        };
+        if checker == DEFAULT_CHECKER { base.into() } else { format!("{base}.{checker}") }
+    }
+}
 
 #[derive(Serialize)]
 pub struct AuthDataViewModel {
@@ -66,59 +428,138 @@ pub struct AuthDataPostModel {
 // our application state
 #[derive(Clone)]
 pub struct AppState {
-    /// The checker address to connect to.
-    pub checker_address: Address,
-    // that holds the key used to sign cookies
-    pub key: Key,
+    /// The checkers to connect to.
+    pub checkers: Arc<Checkers>,
+    /// The keys used to encrypt cookies.
+    pub keys: Arc<KeyRing>,
+    /// The pending (and past) proposals to activate a policy.
+    pub proposals: Arc<ProposalStore>,
+    /// The scheduled policy activations.
+    pub schedules: Arc<ScheduleStore>,
+    /// The log of all management actions performed through us.
+    pub audit: Arc<AuditLog>,
+    /// The history of deliberation requests sent through us.
+    pub history: Arc<HistoryStore>,
+    /// What is known about the health of the checkers.
+    pub health: Arc<HealthMonitor>,
+    /// Pushes changes to the checkers' policies to the clients.
+    pub events: Arc<EventBus>,
+    /// The rules that workflows are checked against before asking the checker.
+    pub workflow_rules: Arc<WorkflowRules>,
+    /// How the conversions of `/api/conv` are performed.
+    pub conv: Arc<ConvConfig>,
+    /// If `true`, policies can only be activated through an approved proposal.
+    pub four_eyes: bool,
 }
 
 // this impl tells `SignedCookieJar` how to access the key from our state
 impl FromRef<AppState> for Key {
     fn from_ref(state: &AppState) -> Self {
-        state.key.clone()
+        state.keys.current().clone()
     }
 }
 
 // #[debug_handler]
 pub async fn post_authenticate(
+    checker: SelectedChecker,
     jar: PrivateCookieJar,
     Json(auth_data): Json<AuthDataPostModel>,
 ) -> (PrivateCookieJar, StatusCode) {
-    match auth_data.t {
-        AuthDataType::Policy => {
-            let mut cookie = Cookie::new("reasoner_policy_auth", auth_data.token);
-            cookie.set_secure(true);
-            cookie.set_http_only(true);
-            // cookie.set_max_age(Some(Duration::new(60 * 10, 0)));
-            (jar.add(cookie), StatusCode::OK)
-        }
-        AuthDataType::Deliberation => {
-            let mut cookie = Cookie::new("reasoner_deliberation_auth", auth_data.token);
-            cookie.set_secure(true);
-            cookie.set_http_only(true);
-            // cookie.set_max_age(Some(Duration::new(60 * 10, 0)));
-            (jar.add(cookie), StatusCode::OK)
-        }
-    }
+    let mut cookie = Cookie::new(auth_data.t.cookie(&checker.name), auth_data.token);
+    cookie.set_secure(true);
+    cookie.set_http_only(true);
+    // cookie.set_max_age(Some(Duration::new(60 * 10, 0)));
+    (jar.add(cookie), StatusCode::OK)
 }
 
-pub async fn get_authenticate(jar: PrivateCookieJar) -> (StatusCode, Json<AuthDataViewModel>) {
+pub async fn get_authenticate(checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, Json<AuthDataViewModel>) {
     let mut auth_data = AuthDataViewModel::new();
 
-    if let Some(data) = jar.get("reasoner_policy_auth") {
+    if let Some(data) = jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         auth_data.policy = data.value().into();
     }
-    if let Some(data) = jar.get("reasoner_deliberation_auth") {
+    if let Some(data) = jar.get(&AuthDataType::Deliberation.cookie(&checker.name)) {
         auth_data.deliberation = data.value().into();
     }
 
     (StatusCode::OK, Json(auth_data))
 }
 
-pub async fn logout(jar: PrivateCookieJar) -> (PrivateCookieJar, StatusCode) {
-    let jar = jar
-        .remove("reasoner_policy_auth")
-        .remove("reasoner_deliberation_auth");
+pub async fn logout(State(state): State<AppState>, mut jar: PrivateCookieJar) -> (PrivateCookieJar, StatusCode) {
+    // Log out of every checker at once
+    for checker in state.checkers.iter() {
+        for t in [AuthDataType::Policy, AuthDataType::Deliberation] {
+            jar = jar.remove(Cookie::from(t.cookie(&checker.name)));
+        }
+    }
 
     (jar, StatusCode::OK)
 }
+
+
+
+
+
+/***** TESTS *****/
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Returns the raw bytes of the given keys, for comparison.
+    fn masters<'k>(keys: impl Iterator<Item = &'k Key>) -> Vec<Vec<u8>> { keys.map(|k| k.master().to_vec()).collect() }
+
+    #[test]
+    fn test_rotate() {
+        // The number of keys to keep, and the number of keys the file holds after each of three rotations
+        let cases: [(usize, [usize; 3]); 4] = [(0, [1, 1, 1]), (1, [1, 1, 1]), (2, [2, 2, 2]), (3, [2, 3, 3])];
+        for (keep, counts) in cases {
+            let dir: TempDir = TempDir::new().unwrap();
+            let path: PathBuf = dir.path().join("key");
+            let mut ring: KeyRing = KeyRing::load(&path).unwrap();
+            for count in counts {
+                let old: Vec<u8> = ring.current().master().to_vec();
+                ring = KeyRing::rotate(&path, keep).unwrap();
+
+                // The new key is current, the old one (if kept) is the newest previous one, and the file holds exactly what the ring does
+                assert_ne!(ring.current().master(), old.as_slice(), "keep {keep}");
+                assert_eq!(ring.keys().count(), count, "keep {keep}");
+                assert_eq!(ring.previous().next().map(|k| k.master().to_vec()), (count > 1).then_some(old), "keep {keep}");
+                assert_eq!(masters(KeyRing::load(&path).unwrap().keys()), masters(ring.keys()), "keep {keep}");
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rotate_restricts_mode() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let dir: TempDir = TempDir::new().unwrap();
+        let path: PathBuf = dir.path().join("key");
+        KeyRing::load(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // Existing files that are too permissive are restricted when they are rewritten
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        KeyRing::rotate(&path, 2).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let dir: TempDir = TempDir::new().unwrap();
+        let path: PathBuf = dir.path().join("key");
+        let cases: [(&str, fn(&KeyError) -> bool); 3] = [
+            ("\n  \n", |err| matches!(err, KeyError::Empty { .. })),
+            ("not base64!\n", |err| matches!(err, KeyError::Decode { line: 1, .. })),
+            ("\naGVsbG8=\n", |err| matches!(err, KeyError::Invalid { line: 2, .. })),
+        ];
+        for (contents, expected) in cases {
+            std::fs::write(&path, contents).unwrap();
+            let err: KeyError = KeyRing::load(&path).unwrap_err();
+            assert!(expected(&err), "{contents:?} gave {err:?}");
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//  BATCH.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 13:20:48
+//  Last edited:
+//    19 Oct 2026, 13:20:48
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements asking the checker about every step of a workflow at once,
+//!   returning a matrix of verdicts.
+//
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_exe::pc::ProgramCounter;
+use brane_tsk::errors::StringError;
+use futures::stream::{self, StreamExt as _};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::auth::{AppState, AuthDataType};
+use crate::bstowir::{AnalyzedWir, TaskCallInfo};
+use crate::checker::{Checker, CheckerError, CheckerResponse, DeliberationKind, SelectedChecker};
+use crate::history;
+use crate::verdict::Verdict;
+
+
+/***** CONSTANTS *****/
+/// The number of requests sent to the checker at the same time if the client does not say otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// The maximum number of requests sent to the checker at the same time, whatever the client asks for.
+pub const MAX_CONCURRENCY: usize = 32;
+/// The use-case asked about if the client does not say otherwise. Matches the one used by the Deliberation page.
+pub const DEFAULT_USE_CASE: &str = "central";
+/// Why the questions about a task call that is never called are not asked.
+pub const NOT_CALLED: &str = "Not asked, as the task is never called";
+
+
+
+
+
+/***** AUXILLARY *****/
+/// The options of a batch deliberation.
+#[derive(Debug, Deserialize)]
+pub struct BatchQuery {
+    /// The use-case to ask the questions in.
+    pub use_case: Option<String>,
+    /// The user to ask the questions as. If omitted, the workflow's own user is used.
+    pub user:     Option<String>,
+    /// The maximum number of requests sent to the checker at the same time. Capped at [`MAX_CONCURRENCY`].
+    pub limit:    Option<usize>,
+}
+
+/// The outcome of a single question in the batch.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchCell {
+    /// The verdict of the checker, if it gave one.
+    pub verdict:  Option<String>,
+    /// The status code of the checker's response, if it responded.
+    pub status:   Option<u16>,
+    /// The body of the checker's response, or the reason there is none.
+    pub response: String,
+}
+impl From<Result<CheckerResponse, CheckerError>> for BatchCell {
+    fn from(value: Result<CheckerResponse, CheckerError>) -> Self {
+        match value {
+            Ok(res) => Self { verdict: Verdict::parse(&res.body).ok().map(|v| v.as_str().into()), status: Some(res.status), response: res.body },
+            Err(err) => Self { verdict: None, status: err.upstream_status(), response: err.to_string() },
+        }
+    }
+}
+
+/// The outcome of asking whether a particular dataset may be accessed.
+#[derive(Clone, Debug, Serialize)]
+pub struct DataCell {
+    /// The dataset asked about.
+    pub dataset: String,
+    /// The outcome.
+    #[serde(flatten)]
+    pub cell:    BatchCell,
+}
+
+/// The outcomes of all questions about a single task call.
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskRow {
+    /// The location of the call in the workflow.
+    pub pg:       ProgramCounter,
+    /// The name of the task called.
+    pub name:     String,
+    /// Whether the task may be executed.
+    pub execute:  BatchCell,
+    /// Whether each input dataset of the task may be accessed.
+    pub datasets: Vec<DataCell>,
+}
+impl TaskRow {
+    /// Constructor for a TaskRow that has no answers yet.
+    ///
+    /// # Arguments
+    /// - `task`: The [`TaskCallInfo`] of the call to make a row for.
+    /// - `reason`: Why its questions were not asked, if they won't be.
+    ///
+    /// # Returns
+    /// A new TaskRow.
+    pub fn new(task: &TaskCallInfo, reason: Option<&str>) -> Self {
+        Self {
+            pg: task.pg,
+            name: task.name.clone(),
+            execute: BatchCell { verdict: None, status: None, response: reason.map(String::from).unwrap_or_default() },
+            datasets: vec![],
+        }
+    }
+}
+
+/// The counts of all verdicts in a [`BatchReport`].
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct BatchSummary {
+    /// The number of questions answered with `allow`.
+    pub allowed: usize,
+    /// The number of questions answered with `deny`.
+    pub denied:  usize,
+    /// The number of questions that did not get a verdict.
+    pub errors:  usize,
+}
+impl BatchSummary {
+    /// Counts the given cell.
+    pub fn count(&mut self, cell: &BatchCell) {
+        match cell.verdict.as_deref() {
+            Some("allow") => self.allowed += 1,
+            Some(_) => self.denied += 1,
+            None => self.errors += 1,
+        }
+    }
+}
+
+/// The verdict matrix of a whole workflow.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchReport {
+    /// Whether the workflow as a whole is OK.
+    pub workflow: BatchCell,
+    /// The verdicts for every task call in the workflow.
+    pub tasks:    Vec<TaskRow>,
+    /// Whether each result of the workflow may be accessed by the user.
+    pub results:  Vec<DataCell>,
+    /// The counts of all verdicts.
+    pub summary:  BatchSummary,
+}
+
+
+
+/// A single question to ask in a batch.
+#[derive(Clone, Debug)]
+pub enum Question {
+    /// Ask about the whole workflow.
+    Workflow,
+    /// Ask if the task at the given index may be executed.
+    Execute(usize),
+    /// Ask if the given dataset of the task at the given index may be accessed.
+    Access(usize, String),
+    /// Ask if the given workflow result may be accessed.
+    Result(String),
+}
+impl Question {
+    /// Builds the request that asks this question.
+    ///
+    /// # Arguments
+    /// - `tasks`: The task calls of the workflow, which the indices in the question refer to.
+    /// - `workflow`: The workflow as it is sent to the checker (see [`prepare_workflow()`]).
+    /// - `use_case`: The use-case to ask the question in.
+    ///
+    /// # Returns
+    /// The kind of question and the raw request to send.
+    pub fn request(&self, tasks: &[TaskCallInfo], workflow: &Value, use_case: &str) -> (DeliberationKind, String) {
+        let mut req: Map<String, Value> = Map::new();
+        req.insert("use_case".into(), Value::String(use_case.into()));
+        req.insert("workflow".into(), workflow.clone());
+        let kind: DeliberationKind = match self {
+            Self::Workflow => DeliberationKind::Workflow,
+            Self::Execute(i) => {
+                req.insert("task_id".into(), serde_json::to_value(tasks[*i].pg).unwrap_or(Value::Null));
+                DeliberationKind::Task
+            },
+            Self::Access(i, dataset) => {
+                req.insert("task_id".into(), serde_json::to_value(tasks[*i].pg).unwrap_or(Value::Null));
+                req.insert("data_id".into(), Value::String(dataset.clone()));
+                DeliberationKind::Data
+            },
+            Self::Result(result) => {
+                req.insert("data_id".into(), Value::String(result.clone()));
+                DeliberationKind::Data
+            },
+        };
+        (kind, Value::Object(req).to_string())
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Prepares a workflow to be sent to the checker.
+///
+/// # Arguments
+/// - `wir`: The [`AnalyzedWir`] with the workflow to send.
+/// - `user`: The user to ask the questions as, or [`None`] to use the workflow's own user.
+///
+/// # Returns
+/// The workflow as JSON.
+///
+/// # Errors
+/// This function errors if the workflow could not be serialized.
+pub fn prepare_workflow(wir: &AnalyzedWir, user: Option<&str>) -> Result<Value, serde_json::Error> {
+    let mut workflow: Value = serde_json::to_value(&wir.workflow)?;
+    if let (Some(user), Some(workflow)) = (user, workflow.as_object_mut()) {
+        workflow.insert("user".into(), Value::String(user.into()));
+    }
+    Ok(workflow)
+}
+
+/// Asks a checker many questions with bounded concurrency, recording each in the history.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the history to record in.
+/// - `checker`: The [`Checker`] to ask.
+/// - `token`: The deliberation token to authenticate with.
+/// - `policy_token`: A management token, if the user has one, used to find out which policy answered.
+/// - `questions`: The questions to ask, together with their kind and raw request (see [`Question::request()`]).
+/// - `limit`: The maximum number of requests sent to the checker at the same time.
+///
+/// # Returns
+/// The answer to every question, in the order they were given.
+pub async fn ask(
+    state: &AppState,
+    checker: &Checker,
+    token: &str,
+    policy_token: Option<&str>,
+    questions: Vec<(Question, DeliberationKind, String)>,
+    limit: usize,
+) -> Vec<(Question, BatchCell)> {
+    stream::iter(questions)
+        .map(|(question, kind, req)| async move {
+            let (res, _) = history::deliberate(state, checker, token, policy_token, kind, req, None).await;
+            (question, BatchCell::from(res))
+        })
+        .buffered(limit.max(1))
+        .collect()
+        .await
+}
+
+
+
+// Ask about every step of a workflow at once
+pub async fn post_batch(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    Query(query): Query<BatchQuery>,
+    Json(wir): Json<AnalyzedWir>,
+) -> (StatusCode, String) {
+    let deliberation_auth_token = match jar.get(&AuthDataType::Deliberation.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    let policy_auth_token = jar.get(&AuthDataType::Policy.cookie(&checker.name));
+
+    // Prepare the parts shared by all requests
+    let workflow: Value = match prepare_workflow(&wir, query.user.as_deref()) {
+        Ok(workflow) => workflow,
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    let use_case: String = query.use_case.unwrap_or_else(|| DEFAULT_USE_CASE.into());
+
+    // Enumerate all questions
+    let mut questions: Vec<Question> = vec![Question::Workflow];
+    for (i, task) in wir.tasks.iter().enumerate().filter(|(_, t)| t.reachable) {
+        questions.push(Question::Execute(i));
+        questions.extend(task.datasets.iter().map(|d| Question::Access(i, d.clone())));
+    }
+    questions.extend(wir.results.iter().map(|r| Question::Result(r.clone())));
+    debug!("Asking {} question(s) about workflow '{}'", questions.len(), wir.workflow.id);
+
+    // Ask them with bounded concurrency
+    let questions: Vec<(Question, DeliberationKind, String)> = questions
+        .into_iter()
+        .map(|question| {
+            let (kind, req) = question.request(&wir.tasks, &workflow, &use_case);
+            (question, kind, req)
+        })
+        .collect();
+    let limit: usize = query.limit.unwrap_or(DEFAULT_CONCURRENCY).clamp(1, MAX_CONCURRENCY);
+    let answers: Vec<(Question, BatchCell)> =
+        ask(&state, &checker, deliberation_auth_token.value(), policy_auth_token.as_ref().map(|c| c.value()), questions, limit).await;
+
+    // Build the matrix
+    let mut report: Option<BatchCell> = None;
+    let mut rows: Vec<TaskRow> =
+        wir.tasks.iter().map(|task| TaskRow::new(task, if task.reachable { None } else { Some(NOT_CALLED) })).collect();
+    let mut results: Vec<DataCell> = vec![];
+    let mut summary: BatchSummary = BatchSummary::default();
+    for (question, cell) in answers {
+        summary.count(&cell);
+        match question {
+            Question::Workflow => report = Some(cell),
+            Question::Execute(i) => rows[i].execute = cell,
+            Question::Access(i, dataset) => rows[i].datasets.push(DataCell { dataset, cell }),
+            Question::Result(dataset) => results.push(DataCell { dataset, cell }),
+        }
+    }
+    let report: BatchReport = BatchReport {
+        workflow: report.unwrap_or_else(|| BatchCell { verdict: None, status: None, response: String::new() }),
+        tasks: rows,
+        results,
+        summary,
+    };
+
+    match serde_json::to_string(&report) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
@@ -0,0 +1,93 @@
+//  MOCK-REASONER.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 15:21:05
+//  Last edited:
+//    19 Oct 2026, 15:21:05
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Runs the in-memory mock of the policy reasoner, so the GUI can be
+//!   demonstrated without a real one.
+//
+
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use clap::Parser;
+use humanlog::{DebugMode, HumanLogger};
+use log::info;
+use policy_reasoner_client_backend::mock::{MockReasoner, MockRule};
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+
+/***** ARGUMENTS *****/
+/// Toplevel arguments
+#[derive(Debug, Parser)]
+struct Arguments {
+    /// The address to listen on.
+    #[clap(short, long, default_value = "127.0.0.1:3030", help = "The address to listen on.")]
+    address: SocketAddr,
+    /// A JSON file with the rules that decide verdicts.
+    #[clap(short, long, help = "A JSON file with a list of rules that decide verdicts. If omitted, everything is allowed while a policy is active.")]
+    rules:   Option<PathBuf>,
+    /// An eFLINT JSON file with a policy to add and activate on startup.
+    #[clap(short, long, help = "An eFLINT JSON file with a policy to add and activate on startup.")]
+    policy:  Option<PathBuf>,
+}
+
+
+
+
+
+/***** ENTRYPOINT *****/
+#[tokio::main]
+async fn main() {
+    let args = Arguments::parse();
+    if let Err(err) = HumanLogger::terminal(DebugMode::Debug).init() {
+        eprintln!("WARNING: Failed to setup logger: {err} (no logging enabled for this session)");
+    }
+
+    // Load the rules and the initial policy
+    let rules: Vec<MockRule> = match &args.rules {
+        Some(path) => match fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|r| serde_json::from_str(&r).map_err(|err| err.to_string())) {
+            Ok(rules) => rules,
+            Err(err) => {
+                eprintln!("Failed to load rules file '{}': {err}", path.display());
+                std::process::exit(1);
+            },
+        },
+        None => vec![],
+    };
+    let mock = MockReasoner::with_rules(rules);
+    if let Some(path) = &args.policy {
+        match fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|p| serde_json::from_str::<Value>(&p).map_err(|err| err.to_string())) {
+            Ok(policy) => {
+                let version: i64 = mock.add_policy(policy, true).await;
+                info!("Activated policy '{}' as version {version}", path.display());
+            },
+            Err(err) => {
+                eprintln!("Failed to load policy file '{}': {err}", path.display());
+                std::process::exit(1);
+            },
+        }
+    }
+
+    // Serve it
+    let listener: TcpListener = match TcpListener::bind(args.address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed to bind to '{}': {err}", args.address);
+            std::process::exit(1);
+        },
+    };
+    info!("Mock policy reasoner listening on '{}'", args.address);
+    if let Err(err) = mock.serve(listener).await {
+        eprintln!("Mock policy reasoner failed: {err}");
+        std::process::exit(1);
+    }
+}
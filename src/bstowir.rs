@@ -13,36 +13,42 @@
 //!   Intermediate Representation (WIR).
 //
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FResult};
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use axum::http::StatusCode;
-use brane_ast::ast::{ComputeTaskDef, Edge, TaskDef};
+use brane_ast::ast::{ComputeTaskDef, DataName, Edge, EdgeInstr, TaskDef};
 use brane_ast::func_id::FunctionId;
 use brane_ast::locations::Locations;
 use brane_ast::{CompileResult, ParserOptions, SymTable, Workflow};
 use brane_exe::pc::ProgramCounter;
 use brane_shr::formatters::BlockFormatter;
 use enum_debug::EnumDebug;
-use error_trace::trace;
 use log::debug;
 use serde::{Deserialize, Serialize};
 use specifications::address::Address;
 use specifications::data::DataIndex;
 use specifications::package::PackageIndex;
 
+use crate::errors::{panic_message, ApiError};
+
 
 /***** ERRORS *****/
 /// Defines errors relating to fetching [`Package`](PackageIndex)- and [`DataIndex`]es.
 #[derive(Debug)]
 enum IndexError {
+    /// Failed to read a local test directory ([`IndexSource::LocalTest`]) at all.
+    ///
+    /// The `kind`-field indicates if this is a package- or data-index.
+    LocalTestDir { kind: &'static str, path: PathBuf, err: std::io::Error },
     /// Failed to fetch the index from a local test directory ([`IndexSource::LocalTest`]).
     ///
     /// The `kind`-field indicates if this is a package- or data-index.
-    LocalTest { kind: &'static str, path: PathBuf },
+    LocalTest { kind: &'static str, path: PathBuf, reason: String },
     /// Failed to fetch the index from a local client directory ([`IndexSource::LocalClient`]).
     ///
     /// The `kind`-field indicates if this is a package- or data-index.
@@ -56,7 +62,8 @@ impl Display for IndexError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
         use IndexError::*;
         match self {
-            LocalTest { kind, path } => write!(f, "Failed to load {} index from local test directory '{}' (see panic above)", kind, path.display()),
+            LocalTestDir { kind, path, .. } => write!(f, "Failed to read local test directory '{}' for the {} index", path.display(), kind),
+            LocalTest { kind, path, reason } => write!(f, "Failed to load {} index from local test directory '{}': {}", kind, path.display(), reason),
             LocalClient { kind, path, .. } => write!(f, "Failed to load {} index from local client directory '{}'", kind, path.display()),
             Remote { kind, url, .. } => write!(f, "Failed to load {kind} index from remote registry at '{url}'"),
         }
@@ -66,6 +73,7 @@ impl Error for IndexError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         use IndexError::*;
         match self {
+            LocalTestDir { err, .. } => Some(err),
             LocalTest { .. } => None,
             LocalClient { err, .. } => Some(err),
             Remote { err, .. } => Some(err),
@@ -73,6 +81,37 @@ impl Error for IndexError {
     }
 }
 
+/// Defines errors relating to parsing [`IndexSource`]s from strings.
+#[derive(Debug)]
+pub enum IndexSourceParseError {
+    /// The source did not have a `<KIND>:` prefix.
+    MissingKind { raw: String },
+    /// The source had an unknown kind.
+    UnknownKind { kind: String },
+    /// The address of a remote source was invalid.
+    RemoteAddress { raw: String, err: <Address as FromStr>::Err },
+}
+impl Display for IndexSourceParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use IndexSourceParseError::*;
+        match self {
+            MissingKind { raw } => write!(f, "Index source '{raw}' is not of the form '<KIND>:<PATH or ADDRESS>'"),
+            UnknownKind { kind } => write!(f, "Unknown index source kind '{kind}' (expected 'local_test', 'local_client' or 'remote')"),
+            RemoteAddress { raw, .. } => write!(f, "Invalid registry address '{raw}'"),
+        }
+    }
+}
+impl Error for IndexSourceParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use IndexSourceParseError::*;
+        match self {
+            MissingKind { .. } => None,
+            UnknownKind { .. } => None,
+            RemoteAddress { err, .. } => Some(err),
+        }
+    }
+}
+
 /// Defines errors originating in [`analyze_wir_and_trivially_plan()`].
 #[derive(Debug)]
 enum AnalyzeError {
@@ -112,6 +151,92 @@ impl Error for AnalyzeError {}
 
 
 /***** HELPER FUNCTIONS *****/
+/// Finds the shortest chain of calls that reaches every function from the toplevel script.
+///
+/// # Arguments
+/// - `calls`: The calls found in the workflow, as the calling function, the edge of the call and the called function (if we could resolve it).
+/// - `funcs`: The bodies of the functions in the workflow, which tells us which called functions aren't builtins.
+///
+/// # Returns
+/// A map from every reachable function (with [`None`] for the toplevel script) to the calls that reach it, outermost first; or [`None`] if a
+/// reachable function makes a call we could not resolve, in which case any function may be reachable.
+fn call_chains(calls: &[(FunctionId, usize, Option<usize>)], funcs: &HashMap<usize, Vec<Edge>>) -> Option<HashMap<Option<usize>, Vec<ProgramCounter>>> {
+    let mut chains: HashMap<Option<usize>, Vec<ProgramCounter>> = HashMap::from([(None, vec![])]);
+    let mut todo: VecDeque<Option<usize>> = VecDeque::from([None]);
+    while let Some(func) = todo.pop_front() {
+        let chain: Vec<ProgramCounter> = chains[&func].clone();
+        for (caller, pc, callee) in calls {
+            let caller_key: Option<usize> = if caller.is_main() { None } else { Some(caller.id()) };
+            if caller_key != func {
+                continue;
+            }
+            let callee: usize = match callee {
+                Some(callee) => *callee,
+                None => {
+                    debug!("Cannot resolve call at {}; assuming every function is reachable", ProgramCounter::new(*caller, *pc));
+                    return None;
+                },
+            };
+            if !funcs.contains_key(&callee) || chains.contains_key(&Some(callee)) {
+                continue;
+            }
+            let mut callee_chain: Vec<ProgramCounter> = chain.clone();
+            callee_chain.push(ProgramCounter::new(*caller, *pc));
+            chains.insert(Some(callee), callee_chain);
+            todo.push_back(Some(callee));
+        }
+    }
+    Some(chains)
+}
+
+/// Resolves the name of a function in a workflow.
+///
+/// # Arguments
+/// - `table`: The workflow's [`SymTable`] that defines the function.
+/// - `func_id`: The identifier of the function.
+///
+/// # Returns
+/// The name of the function, `<main>` for the toplevel script, or its identifier if it is unknown.
+fn function_name(table: &SymTable, func_id: FunctionId) -> String {
+    if func_id.is_main() {
+        "<main>".into()
+    } else if let Some(def) = table.funcs.get(func_id.id()) {
+        def.name.clone()
+    } else {
+        format!("{}", func_id.id())
+    }
+}
+
+/// Loads an index from a local test directory, without taking the caller down if that fails.
+///
+/// The loaders of `brane_shr` panic instead of returning errors, so they are run on their own (blocking) task.
+///
+/// # Arguments
+/// - `kind`: Whether this is a package- or data-index.
+/// - `path`: The local test directory to load the index from.
+/// - `load`: The loader to run on the `path`.
+///
+/// # Returns
+/// The loaded index.
+///
+/// # Errors
+/// This function errors if the `path` could not be read, or if the loader panicked.
+async fn local_test_index<T: 'static + Send>(kind: &'static str, path: PathBuf, load: impl 'static + Send + FnOnce(&PathBuf) -> T) -> Result<T, IndexError> {
+    let dir: PathBuf = path.clone();
+    let res = tokio::task::spawn_blocking(move || {
+        // Catch the most likely reason for the loader to panic ourselves
+        std::fs::read_dir(&dir)?;
+        Ok::<T, std::io::Error>(load(&dir))
+    })
+    .await;
+    match res {
+        Ok(Ok(index)) => Ok(index),
+        Ok(Err(err)) => Err(IndexError::LocalTestDir { kind, path, err }),
+        Err(err) if err.is_panic() => Err(IndexError::LocalTest { kind, path, reason: panic_message(&*err.into_panic()).into() }),
+        Err(err) => Err(IndexError::LocalTest { kind, path, reason: err.to_string() }),
+    }
+}
+
 /// Fetches a [`PackageIndex`] from the location pointed to by the given [`IndexSource`].
 ///
 /// # Arguments
@@ -127,8 +252,7 @@ async fn get_package_index(source: IndexSource) -> Result<PackageIndex, IndexErr
     match source {
         IndexSource::LocalTest(path) => {
             debug!("Fetching package index from local test directory '{}'", path.display());
-            std::panic::catch_unwind(|| brane_shr::utilities::create_package_index_from(&path))
-                .map_err(|_| IndexError::LocalTest { kind: "package", path })
+            local_test_index("package", path, |path| brane_shr::utilities::create_package_index_from(path)).await
         },
         IndexSource::LocalClient(path) => {
             debug!("Fetching package index from local client directory '{}'", path.display());
@@ -157,7 +281,7 @@ async fn get_data_index(source: IndexSource) -> Result<DataIndex, IndexError> {
     match source {
         IndexSource::LocalTest(path) => {
             debug!("Fetching data index from local test directory '{}'", path.display());
-            std::panic::catch_unwind(|| brane_shr::utilities::create_data_index_from(&path)).map_err(|_| IndexError::LocalTest { kind: "data", path })
+            local_test_index("data", path, |path| brane_shr::utilities::create_data_index_from(path)).await
         },
         IndexSource::LocalClient(path) => {
             debug!("Fetching data index from local client directory '{}'", path.display());
@@ -187,6 +311,10 @@ async fn get_data_index(source: IndexSource) -> Result<DataIndex, IndexError> {
 /// - `is_main`: If `true`, then we're operating in the toplevel script where return statements exit the workflow.
 /// - `tasks`: A list of tasks that will be populated with the ones found in this workflow.
 /// - `result`: A list of possible results returned from a workflow.
+/// - `lineage`: A [`Lineage`] graph that will be populated with the data flowing in and out of the tasks found.
+/// - `scope`: The [`Scope`] of the edges we're currently analyzing.
+/// - `calls`: A list of calls that will be populated with the calling function, the edge of the call and the called function for every call,
+///   or [`None`] as called function if we could not resolve it.
 ///
 /// # Errors
 /// This function may fail if we failed to analyze the workflow.
@@ -200,7 +328,12 @@ fn analyze_wir_and_trivially_plan(
     is_main: bool,
     tasks: &mut Vec<TaskCallInfo>,
     results: &mut Vec<String>,
+    lineage: &mut Lineage,
+    scope: Scope,
+    calls: &mut Vec<(FunctionId, usize, Option<usize>)>,
 ) -> Result<(), AnalyzeError> {
+    // The function most recently pushed on the stack, which is the one called by the next call
+    let mut callee: Option<usize> = None;
     loop {
         // Stop on breakpoints
         if let Some(breakpoint) = breakpoint {
@@ -220,7 +353,7 @@ fn analyze_wir_and_trivially_plan(
         // Match on it!
         log::trace!("Analyzing & trivially planning edge {} (Edge::{})", pc, edge.variant());
         match edge {
-            Edge::Node { task, locs, at, input, result: _, metadata: _, next } => {
+            Edge::Node { task, locs, at, input, result, metadata: _, next } => {
                 let pg: ProgramCounter = ProgramCounter::new(func_id, pc);
 
                 // Resolve the task ID in the workflow table
@@ -240,35 +373,44 @@ fn analyze_wir_and_trivially_plan(
                 debug!("Attempting to plan node '{}' in workflow '{}' (possible locations: {:?})", pg, wf_id, locs);
                 match locs {
                     // If there's exactly one location, plan there
-                    Locations::Restricted(locs) if locs.len() == 1 => *at = Some(locs.first().unwrap().clone()),
+                    Locations::Restricted(locs) if locs.len() == 1 => *at = locs.first().cloned(),
 
                     _ => {
                         return Err(AnalyzeError::AmbigiousLocation {
                             id: wf_id.into(),
-                            node_func_id: if func_id.is_main() {
-                                "<main>".into()
-                            } else {
-                                if let Some(def) = table.funcs.get(func_id.id()) { def.name.clone() } else { format!("{}", func_id.id()) }
-                            },
+                            node_func_id: function_name(table, func_id),
                             node_edge_idx: pc,
                             def: def.clone(),
                         });
                     },
                 }
 
-                // Now built the index of it
+                // Now built the index of it (sorting the input, since it comes from a map)
+                let name: String = format!("{}<{}>::{}", def.package, def.version, def.function.name);
+                let mut datasets: Vec<&DataName> = input.keys().collect();
+                datasets.sort_by(|lhs, rhs| lhs.name().cmp(rhs.name()));
+                lineage.add_task(pg, name.clone(), at.clone(), datasets.iter().copied(), result.as_deref());
                 tasks.push(TaskCallInfo {
                     pg,
-                    name: format!("{}<{}>::{}", def.package, def.version, def.function.name),
-                    datasets: input.keys().map(|dataname| dataname.name().into()).collect(),
+                    name,
+                    datasets: datasets.into_iter().map(|dataname| dataname.name().into()).collect(),
+                    at: at.clone(),
+                    result: result.clone(),
+                    function: function_name(table, func_id),
+                    in_loop: scope.in_loop,
+                    in_branch: scope.in_branch,
+                    in_parallel: scope.in_parallel,
+                    reachable: true,
+                    call_chain: vec![],
                 });
 
                 // Then we can move to the next edge
                 pc = *next;
                 continue;
             },
-            Edge::Linear { instrs: _, next } => {
-                // No need to dive into the instructions (basically expressions)
+            Edge::Linear { instrs, next } => {
+                // No need to dive into the instructions (basically expressions), except to see which function is called next
+                callee = instrs.iter().rev().find_map(|i| if let EdgeInstr::Function { def } = i { Some(*def) } else { None });
                 pc = *next;
                 continue;
             },
@@ -280,10 +422,11 @@ fn analyze_wir_and_trivially_plan(
                 let merge: Option<usize> = *merge;
 
                 // Recurse into the true-branch
-                analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, true_next, merge, is_main, tasks, results)?;
+                let inner: Scope = Scope { in_branch: true, ..scope };
+                analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, true_next, merge, is_main, tasks, results, lineage, inner, calls)?;
                 // Recurse into the false-branch (if any)
                 if let Some(false_next) = false_next {
-                    analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, false_next, merge, is_main, tasks, results)?;
+                    analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, false_next, merge, is_main, tasks, results, lineage, inner, calls)?;
                 }
 
                 // If either branch does not fully return, continue
@@ -299,9 +442,10 @@ fn analyze_wir_and_trivially_plan(
                 let merge: usize = *merge;
 
                 // Recurse into all the branches
+                let inner: Scope = Scope { in_parallel: true, ..scope };
                 for b in branches {
                     // NOTE: Switch `is_main` to false, as returns break the branch and not the script
-                    analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, b, Some(merge), false, tasks, results)?;
+                    analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, b, Some(merge), false, tasks, results, lineage, inner, calls)?;
                 }
 
                 // Continue with the rest of the program
@@ -321,9 +465,10 @@ fn analyze_wir_and_trivially_plan(
                 // This one's gnarly, due to the layout of the edges; pay attention to the breakpoints
 
                 // Recurse into the condition
-                analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, cond, Some(body - 1), is_main, tasks, results)?;
+                let inner: Scope = Scope { in_loop: true, ..scope };
+                analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, cond, Some(body - 1), is_main, tasks, results, lineage, inner, calls)?;
                 // Recurse into the body
-                analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, body, Some(cond), is_main, tasks, results)?;
+                analyze_wir_and_trivially_plan(wf_id, table, func_id, edges, body, Some(cond), is_main, tasks, results, lineage, inner, calls)?;
 
                 // Continue with next
                 if let Some(next) = next {
@@ -335,7 +480,8 @@ fn analyze_wir_and_trivially_plan(
             },
 
             Edge::Call { input: _, result: _, next } => {
-                // We ignore calls, as we do function body analysis separately
+                // We do function body analysis separately, but remember the call to find out which functions are reachable
+                calls.push((func_id, pc, callee.take()));
                 pc = *next;
                 continue;
             },
@@ -358,7 +504,8 @@ fn analyze_wir_and_trivially_plan(
 
 /***** AUXILLARY *****/
 /// Alternates between possible sources of a [`Package`](PackageIndex)- or [`DataIndex`].
-#[derive(Clone, Debug, EnumDebug)]
+#[derive(Clone, Debug, Deserialize, EnumDebug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum IndexSource {
     /// The index is sourced from a local test repository of packages- or datasets (as found in `tests/` in this repository, for example).
     LocalTest(PathBuf),
@@ -367,6 +514,19 @@ pub enum IndexSource {
     /// The index is sourced from a `brane-api` registry at the given address.
     Remote(Address),
 }
+impl FromStr for IndexSource {
+    type Err = IndexSourceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value): (&str, &str) = s.split_once(':').ok_or_else(|| IndexSourceParseError::MissingKind { raw: s.into() })?;
+        match kind {
+            "local_test" => Ok(Self::LocalTest(value.into())),
+            "local_client" => Ok(Self::LocalClient(value.into())),
+            "remote" => Ok(Self::Remote(Address::from_str(value).map_err(|err| IndexSourceParseError::RemoteAddress { raw: value.into(), err })?)),
+            kind => Err(IndexSourceParseError::UnknownKind { kind: kind.into() }),
+        }
+    }
+}
 
 
 
@@ -381,6 +541,12 @@ pub struct AnalyzedWir {
     pub tasks:    Vec<TaskCallInfo>,
     /// If there is any, this defines the result to the workflow as a whole.
     pub results:  Vec<String>,
+    /// How data flows between datasets, intermediate results and the tasks in this workflow.
+    #[serde(default)]
+    pub lineage:  Lineage,
+    /// The names of the functions in this workflow that are never called.
+    #[serde(default)]
+    pub unreachable_functions: Vec<String>,
 }
 
 /// A struct describing the info we extract from each task call.
@@ -392,6 +558,171 @@ pub struct TaskCallInfo {
     pub name: String,
     /// A list of datasets that are input to the TaskCall.
     pub datasets: Vec<String>,
+    /// The location where the task is planned.
+    #[serde(default)]
+    pub at: Option<String>,
+    /// The name of the intermediate result produced by the task, if any.
+    #[serde(default)]
+    pub result: Option<String>,
+    /// The name of the function in which the call occurs (`<main>` for the toplevel script).
+    #[serde(default)]
+    pub function: String,
+    /// Whether the call occurs in the condition or body of a loop.
+    ///
+    /// Like [`Self::in_branch`] and [`Self::in_parallel`], this only looks at the constructs within [`Self::function`]; a call in a function
+    /// that is itself called from a loop is not marked. Use [`Self::call_chain`] to find the call sites and their surroundings.
+    #[serde(default)]
+    pub in_loop: bool,
+    /// Whether the call occurs in a branch of an if-statement within [`Self::function`].
+    #[serde(default)]
+    pub in_branch: bool,
+    /// Whether the call occurs in a branch of a parallel block within [`Self::function`].
+    #[serde(default)]
+    pub in_parallel: bool,
+    /// Whether the call can actually be executed, i.e., whether it occurs in the toplevel script or in a function that is (indirectly) called
+    /// from it.
+    #[serde(default = "default_reachable")]
+    pub reachable: bool,
+    /// The calls through which the task call is reached from the toplevel script, outermost first. Empty for calls in the toplevel script.
+    #[serde(default)]
+    pub call_chain: Vec<ProgramCounter>,
+}
+
+/// The default for [`TaskCallInfo::reachable`] when it is omitted.
+fn default_reachable() -> bool { true }
+
+/// Describes the control flow constructs surrounding the edges being analyzed.
+#[derive(Clone, Copy, Debug, Default)]
+struct Scope {
+    /// Whether we're in the condition or body of a loop.
+    in_loop:     bool,
+    /// Whether we're in a branch of an if-statement.
+    in_branch:   bool,
+    /// Whether we're in a branch of a parallel block.
+    in_parallel: bool,
+}
+
+
+
+/// A node in a [`Lineage`] graph.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LineageNode {
+    /// A dataset that exists before the workflow runs.
+    Dataset {
+        /// The name of the dataset.
+        id: String,
+        /// The locations where the dataset is available.
+        locations: Vec<String>,
+    },
+    /// A result produced by a task in the workflow.
+    IntermediateResult {
+        /// The name of the result.
+        id: String,
+        /// The location where the result is produced, i.e., where the task producing it runs.
+        location: Option<String>,
+    },
+    /// A task call in the workflow.
+    Task {
+        /// The location of the call in the workflow.
+        pg:   ProgramCounter,
+        /// The name of the task called.
+        name: String,
+        /// The location where the task is planned.
+        at:   Option<String>,
+    },
+}
+
+/// The kinds of edges in a [`Lineage`] graph.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineageEdgeKind {
+    /// The data is produced by the task.
+    ProducedBy,
+    /// The data is consumed by the task.
+    ConsumedBy,
+}
+
+/// An edge in a [`Lineage`] graph, always between a piece of data and a task.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LineageEdge {
+    /// The relation between the data and the task.
+    pub kind: LineageEdgeKind,
+    /// The name of the dataset or intermediate result.
+    pub data: String,
+    /// The task call.
+    pub task: ProgramCounter,
+}
+
+/// Describes how data flows through a workflow.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Lineage {
+    /// The datasets, intermediate results and tasks in the workflow.
+    pub nodes: Vec<LineageNode>,
+    /// How the data relates to the tasks.
+    pub edges: Vec<LineageEdge>,
+}
+impl Lineage {
+    /// Adds a piece of data to the graph if it isn't there yet.
+    ///
+    /// # Arguments
+    /// - `data`: The [`DataName`] of the dataset or intermediate result.
+    /// - `location`: The location where the data is produced, if known. Only relevant for intermediate results.
+    ///
+    /// # Returns
+    /// The name of the data.
+    fn add_data(&mut self, data: &DataName, location: Option<String>) -> String {
+        let id: &str = data.name();
+        let existing: Option<usize> = self.nodes.iter().position(|n| match n {
+            LineageNode::Dataset { id: other, .. } | LineageNode::IntermediateResult { id: other, .. } => other == id,
+            LineageNode::Task { .. } => false,
+        });
+        match (existing, data) {
+            (Some(i), _) => {
+                if let LineageNode::IntermediateResult { location: known @ None, .. } = &mut self.nodes[i] {
+                    *known = location;
+                }
+            },
+            (None, DataName::Data(_)) => self.nodes.push(LineageNode::Dataset { id: id.into(), locations: vec![] }),
+            (None, DataName::IntermediateResult(_)) => self.nodes.push(LineageNode::IntermediateResult { id: id.into(), location }),
+        }
+        id.into()
+    }
+
+    /// Adds a task call to the graph, together with the data it consumes and produces.
+    ///
+    /// # Arguments
+    /// - `pg`: The location of the call in the workflow.
+    /// - `name`: The name of the task called.
+    /// - `at`: The location where the task is planned.
+    /// - `input`: The data consumed by the task.
+    /// - `result`: The intermediate result produced by the task, if any.
+    fn add_task<'d>(&mut self, pg: ProgramCounter, name: String, at: Option<String>, input: impl IntoIterator<Item = &'d DataName>, result: Option<&str>) {
+        for data in input {
+            let data: String = self.add_data(data, None);
+            self.edges.push(LineageEdge { kind: LineageEdgeKind::ConsumedBy, data, task: pg });
+        }
+        if let Some(result) = result {
+            let data: String = self.add_data(&DataName::IntermediateResult(result.into()), at.clone());
+            self.edges.push(LineageEdge { kind: LineageEdgeKind::ProducedBy, data, task: pg });
+        }
+        self.nodes.push(LineageNode::Task { pg, name, at });
+    }
+
+    /// Fills in where the datasets in the graph are available.
+    ///
+    /// # Arguments
+    /// - `dindex`: The [`DataIndex`] that knows where datasets live.
+    fn locate(&mut self, dindex: &DataIndex) {
+        for node in &mut self.nodes {
+            if let LineageNode::Dataset { id, locations } = node {
+                if let Some(info) = dindex.get(id) {
+                    *locations = info.access.keys().cloned().collect();
+                    locations.sort();
+                }
+            }
+        }
+    }
 }
 
 
@@ -412,16 +743,16 @@ pub struct TaskCallInfo {
 /// A(n already serialized) [`AnalyzedWir`] that encodes not just the compiled workflow, but also a list of all tasks occurring in it (together with their possible input data) and which data is the result of the workflow (if any).
 ///
 /// # Errors
-/// This function may error if we failed to read from the `packages`- or `data`-source, or if the input workflow was malformed.
-pub async fn bs_to_wir(packages: IndexSource, data: IndexSource, body: String) -> Result<String, (StatusCode, String)> {
+/// This function errors with an [`ApiError`] if we failed to read from the `packages`- or `data`-source, or if the input workflow was malformed.
+pub async fn bs_to_wir(packages: IndexSource, data: IndexSource, body: String) -> Result<String, ApiError> {
     // Fetch the indices
     let pindex: PackageIndex = match get_package_index(packages).await {
         Ok(index) => index,
-        Err(err) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{}", trace!(("Failed to collect package index"), err)))),
+        Err(err) => return Err(ApiError::from_error(StatusCode::INTERNAL_SERVER_ERROR, &err).context("Failed to collect package index")),
     };
     let dindex: DataIndex = match get_data_index(data).await {
         Ok(index) => index,
-        Err(err) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{}", trace!(("Failed to collect package index"), err)))),
+        Err(err) => return Err(ApiError::from_error(StatusCode::INTERNAL_SERVER_ERROR, &err).context("Failed to collect data index")),
     };
 
     // Compile the program next
@@ -430,82 +761,116 @@ pub async fn bs_to_wir(packages: IndexSource, data: IndexSource, body: String) -
         // NOTE: We ignore the warnings
         CompileResult::Workflow(wf, _warns) => wf,
         CompileResult::Err(errs) => {
-            // Collect the errors in a nice string
-            let mut msg: Vec<u8> = Vec::new();
+            // Collect the errors as the causes, each nicely printed
+            let mut causes: Vec<String> = Vec::new();
             for err in errs {
-                err.prettywrite(&mut msg, "<input>", &body).unwrap_or_else(|_| {
-                    panic!("BraneScript compiler error prettyprinter failed to write to in-memory buffer; this should never happen!")
-                });
+                let mut msg: Vec<u8> = Vec::new();
+                match err.prettywrite(&mut msg, "<input>", &body) {
+                    Ok(_) => causes.push(String::from_utf8_lossy(&msg).into_owned()),
+                    Err(err) => causes.push(format!("<failed to print compiler error: {err}>")),
+                }
             }
 
-            // Add some generic error
-            msg.extend(b"Failed to compile input workflow (see errors above)\n");
-
             // Return the failure body
-            return Err((
-                StatusCode::BAD_REQUEST,
-                String::from_utf8(msg)
-                    .unwrap_or_else(|_| panic!("BraneScript compiler error prettyprinter did not produce valid UTF-8; this should never happen!")),
-            ));
+            return Err(ApiError { causes, ..ApiError::new(StatusCode::BAD_REQUEST, "Failed to compile input workflow").with_code("compile_error") });
         },
         CompileResult::Eof(err) => {
             // Serialize the error
             let mut msg: Vec<u8> = Vec::new();
-            err.prettywrite(&mut msg, "<input>", &body).unwrap_or_else(|_| {
-                panic!("BraneScript compiler error prettyprinter failed to write to in-memory buffer; this should never happen!")
-            });
-
-            // Add some generic error
-            msg.extend(b"Failed to compile input workflow (see errors above)\n");
+            let causes: Vec<String> = match err.prettywrite(&mut msg, "<input>", &body) {
+                Ok(_) => vec![String::from_utf8_lossy(&msg).into_owned()],
+                Err(err) => vec![format!("<failed to print compiler error: {err}>")],
+            };
 
             // Return the failure body
-            return Err((
-                StatusCode::BAD_REQUEST,
-                String::from_utf8(msg)
-                    .unwrap_or_else(|_| panic!("BraneScript compiler error prettyprinter did not produce valid UTF-8; this should never happen!")),
-            ));
+            return Err(ApiError { causes, ..ApiError::new(StatusCode::BAD_REQUEST, "Failed to compile input workflow").with_code("compile_error") });
         },
 
-        CompileResult::Unresolved(_, _) => unreachable!(),
-        CompileResult::Program(_, _) => unreachable!(),
+        CompileResult::Unresolved(_, _) | CompileResult::Program(_, _) => {
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Compiler did not return a workflow").with_code("compile_error"));
+        },
     };
 
     // Run an analysis to find all nodes and data and the like
-    let (mut tasks, mut results): (Vec<TaskCallInfo>, Vec<String>) = (vec![], vec![]);
+    let (mut tasks, mut results, mut lineage): (Vec<TaskCallInfo>, Vec<String>, Lineage) = (vec![], vec![], Lineage::default());
+    let mut calls: Vec<(FunctionId, usize, Option<usize>)> = vec![];
+    let unreachable_functions: Vec<String>;
     {
         /* Step 1: Extract the lists from behind the Arc's to get them mutable */
         // Extract the main graph
         let mut graph: Arc<Vec<Edge>> = Arc::new(vec![]);
         std::mem::swap(&mut workflow.graph, &mut graph);
-        let mut graph: Vec<Edge> = Arc::into_inner(graph).unwrap();
+        let mut graph: Vec<Edge> = Arc::unwrap_or_clone(graph);
 
         // Extract the function bodies
         let mut funcs: Arc<HashMap<usize, Vec<Edge>>> = Arc::new(HashMap::new());
         std::mem::swap(&mut workflow.funcs, &mut funcs);
-        let mut funcs: HashMap<usize, Vec<Edge>> = Arc::into_inner(funcs).unwrap();
+        let mut funcs: HashMap<usize, Vec<Edge>> = Arc::unwrap_or_clone(funcs);
 
 
 
         /* Step 2: Run the analysis */
         // Run the main function's analysis
-        if let Err(err) =
-            analyze_wir_and_trivially_plan(&workflow.id, &workflow.table, FunctionId::Main, &mut graph, 0, None, true, &mut tasks, &mut results)
-        {
-            return Err((StatusCode::BAD_REQUEST, format!("{}", trace!(("Invalid workflow given"), err))));
+        if let Err(err) = analyze_wir_and_trivially_plan(
+            &workflow.id,
+            &workflow.table,
+            FunctionId::Main,
+            &mut graph,
+            0,
+            None,
+            true,
+            &mut tasks,
+            &mut results,
+            &mut lineage,
+            Scope::default(),
+            &mut calls,
+        ) {
+            return Err(ApiError::from_error(StatusCode::BAD_REQUEST, &err).context("Invalid workflow given"));
         };
 
-        // Analyze/plan all the function bodies
-        for (id, body) in funcs.iter_mut() {
-            if let Err(err) =
-                analyze_wir_and_trivially_plan(&workflow.id, &workflow.table, FunctionId::Func(*id), body, 0, None, false, &mut tasks, &mut results)
-            {
-                return Err((StatusCode::BAD_REQUEST, format!("{}", trace!(("Invalid workflow given"), err))));
+        // Analyze/plan all the function bodies, in order so the tasks and lineage come out the same every time
+        let mut bodies: Vec<(&usize, &mut Vec<Edge>)> = funcs.iter_mut().collect();
+        bodies.sort_by_key(|(id, _)| **id);
+        for (id, body) in bodies {
+            if let Err(err) = analyze_wir_and_trivially_plan(
+                &workflow.id,
+                &workflow.table,
+                FunctionId::Func(*id),
+                body,
+                0,
+                None,
+                false,
+                &mut tasks,
+                &mut results,
+                &mut lineage,
+                Scope::default(),
+                &mut calls,
+            ) {
+                return Err(ApiError::from_error(StatusCode::BAD_REQUEST, &err).context("Invalid workflow given"));
             };
         }
 
 
 
-        /* Step 3: Put the lists back */
+        /* Step 3: Find which functions (and therefore tasks) are actually called */
+        // NOTE: If we cannot tell, every task is left reachable (but without a call chain) rather than wrongly claiming it is never called
+        let mut unreachable: Vec<usize> = vec![];
+        if let Some(chains) = call_chains(&calls, &funcs) {
+            for task in &mut tasks {
+                let func: Option<usize> = if task.pg.func_id.is_main() { None } else { Some(task.pg.func_id.id()) };
+                match chains.get(&func) {
+                    Some(chain) => task.call_chain = chain.clone(),
+                    None => task.reachable = false,
+                }
+            }
+            unreachable = funcs.keys().filter(|id| !chains.contains_key(&Some(**id))).copied().collect();
+            unreachable.sort();
+        }
+        unreachable_functions = unreachable.into_iter().map(|id| function_name(&workflow.table, FunctionId::Func(id))).collect();
+
+
+
+        /* Step 4: Put the lists back */
         // Re-inject the function bodies
         let mut funcs: Arc<HashMap<usize, Vec<Edge>>> = Arc::new(funcs);
         std::mem::swap(&mut funcs, &mut workflow.funcs);
@@ -514,13 +879,79 @@ pub async fn bs_to_wir(packages: IndexSource, data: IndexSource, body: String) -
         let mut graph: Arc<Vec<Edge>> = Arc::new(graph);
         std::mem::swap(&mut graph, &mut workflow.graph);
     }
+    lineage.locate(&dindex);
 
     // Now serialize the resulting body
-    let res: String = match serde_json::to_string_pretty(&AnalyzedWir { workflow, tasks, results }) {
+    let res: String = match serde_json::to_string_pretty(&AnalyzedWir { workflow, tasks, results, lineage, unreachable_functions }) {
         Ok(res) => res,
-        Err(err) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{}", trace!(("Failed to serialize workflow"), err)))),
+        Err(err) => return Err(ApiError::from_error(StatusCode::INTERNAL_SERVER_ERROR, &err).context("Failed to serialize workflow")),
     };
 
     // Alrighty, return everything
     Ok(res)
 }
+
+
+
+
+
+/***** TESTS *****/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the function bodies for [`call_chains()`], which only cares about which functions exist.
+    fn funcs(ids: &[usize]) -> HashMap<usize, Vec<Edge>> { ids.iter().map(|id| (*id, vec![])).collect() }
+
+    #[test]
+    fn test_call_chains() {
+        // `<main>` calls 2, which calls 1; 0 is never called
+        let calls = [(FunctionId::Main, 3, Some(2)), (FunctionId::Func(2), 1, Some(1)), (FunctionId::Func(0), 0, Some(1))];
+        let chains = call_chains(&calls, &funcs(&[0, 1, 2])).unwrap();
+        assert_eq!(chains[&None], vec![]);
+        assert_eq!(chains[&Some(2)], vec![ProgramCounter::new(FunctionId::Main, 3)]);
+        assert_eq!(chains[&Some(1)], vec![ProgramCounter::new(FunctionId::Main, 3), ProgramCounter::new(FunctionId::Func(2), 1)]);
+        assert!(!chains.contains_key(&Some(0)));
+
+        // Calls to builtins are not functions to reach
+        let calls = [(FunctionId::Main, 0, Some(7))];
+        assert_eq!(call_chains(&calls, &funcs(&[0])).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_call_chains_unresolved() {
+        // A call we cannot resolve in a reachable function may reach anything...
+        let calls = [(FunctionId::Main, 3, Some(2)), (FunctionId::Func(2), 1, None)];
+        assert!(call_chains(&calls, &funcs(&[0, 1, 2])).is_none());
+
+        // ...but one in a function that is never called does not matter
+        let calls = [(FunctionId::Main, 3, Some(2)), (FunctionId::Func(0), 1, None)];
+        let chains = call_chains(&calls, &funcs(&[0, 1, 2])).unwrap();
+        assert!(chains.contains_key(&Some(2)) && !chains.contains_key(&Some(0)) && !chains.contains_key(&Some(1)));
+    }
+
+    #[tokio::test]
+    async fn test_call_chain_fixture() {
+        let bs: String = std::fs::read_to_string("./tests/branescript/call_chain.bs").unwrap();
+        let wir: String = bs_to_wir(IndexSource::LocalTest("./tests/packages".into()), IndexSource::LocalTest("./tests/data".into()), bs).await.unwrap();
+        let wir: AnalyzedWir = serde_json::from_str(&wir).unwrap();
+        assert_eq!(wir.unreachable_functions, Vec::<String>::new());
+
+        // Find the calls again, which must all be resolved
+        let Workflow { id, table, graph, funcs, .. } = wir.workflow;
+        let (mut tasks, mut results, mut lineage, mut calls) = (vec![], vec![], Lineage::default(), vec![]);
+        let mut bodies: Vec<(FunctionId, Vec<Edge>, bool)> = vec![(FunctionId::Main, (*graph).clone(), true)];
+        bodies.extend(funcs.iter().map(|(func, body)| (FunctionId::Func(*func), body.clone(), false)));
+        for (func, mut body, is_main) in bodies {
+            let scope: Scope = Scope::default();
+            analyze_wir_and_trivially_plan(&id, &table, func, &mut body, 0, None, is_main, &mut tasks, &mut results, &mut lineage, scope, &mut calls).unwrap();
+        }
+        assert!(calls.iter().all(|(_, _, callee)| callee.is_some()), "{calls:?}");
+
+        // `foo` is reached through `quz`, `baz` and `bar`
+        let chains = call_chains(&calls, &funcs).unwrap();
+        let foo: Option<usize> = funcs.keys().copied().find(|id| function_name(&table, FunctionId::Func(*id)) == "foo");
+        let callers: Vec<String> = chains[&foo].iter().map(|pc| function_name(&table, pc.func_id)).collect();
+        assert_eq!(callers, ["<main>", "quz", "baz", "bar"]);
+    }
+}
@@ -0,0 +1,676 @@
+//  CHECKER.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 09:12:40
+//  Last edited:
+//    19 Oct 2026, 09:12:40
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements calls to the checker that are not simply proxied for the
+//!   client, but that the backend has to make on its own accord (e.g.,
+//!   when an activation proposal gets approved). Also keeps track of the
+//!   checkers that requests can select.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Query, State};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use enum_debug::EnumDebug;
+use log::{debug, warn};
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use specifications::address::Address;
+
+use crate::auth::AppState;
+use crate::checker_tls::{self, CheckerTlsConfig, CheckerTlsError};
+use crate::errors::ApiError;
+use crate::resilience::{CircuitBreaker, Endpoint, ResilienceConfig};
+
+
+/***** CONSTANTS *****/
+/// The name of the checker if only one is configured.
+pub const DEFAULT_CHECKER: &str = "default";
+
+
+
+
+
+/***** ERRORS *****/
+/// Defines errors originating from talking to the checker.
+#[derive(Debug)]
+pub enum CheckerError {
+    /// No checker with the given name is configured.
+    UnknownChecker { name: String },
+    /// A checker has a name that cannot be used in cookies and URLs.
+    IllegalName { name: String },
+    /// Failed to build the HTTP client used to talk to the checker.
+    Client { address: String, err: reqwest::Error },
+    /// Failed to set up TLS for the connection to the checker.
+    Tls { address: String, err: CheckerTlsError },
+    /// The checker could not be reached because of a problem with its certificate (or ours).
+    Certificate { url: String, problem: String, err: reqwest::Error },
+    /// Failed to send the request to the checker.
+    Request { url: String, err: reqwest::Error },
+    /// The checker did not respond in time.
+    Timeout { url: String, after: Duration, err: reqwest::Error },
+    /// The checker failed too often recently, so it wasn't called at all.
+    Unavailable { name: String, retry_after: Duration },
+    /// The checker responded with a non-success status code.
+    Status { url: String, code: u16, body: String },
+    /// Failed to download the body of the checker's response.
+    Body { url: String, err: reqwest::Error },
+    /// Failed to parse the body of the checker's response.
+    Parse { url: String, err: serde_json::Error },
+}
+impl CheckerError {
+    /// Returns the status code that best represents this error to our own clients.
+    ///
+    /// # Returns
+    /// The checker's own status code if it responded with one, [`StatusCode::SERVICE_UNAVAILABLE`] or [`StatusCode::GATEWAY_TIMEOUT`] if it
+    /// is down, or [`StatusCode::INTERNAL_SERVER_ERROR`] otherwise.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Status { code, .. } => StatusCode::from_u16(*code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            Self::UnknownChecker { .. } => StatusCode::NOT_FOUND,
+            Self::Request { .. } | Self::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            Self::IllegalName { .. } | Self::Client { .. } | Self::Tls { .. } | Self::Certificate { .. } | Self::Body { .. } | Self::Parse { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            },
+        }
+    }
+
+    /// Returns whether this error means that the checker is (temporarily) down, as opposed to it refusing the request.
+    ///
+    /// Calls failing with such errors are retried if they only read, and count towards the checker's circuit breaker.
+    pub fn is_outage(&self) -> bool {
+        match self {
+            Self::Request { .. } | Self::Timeout { .. } | Self::Unavailable { .. } => true,
+            Self::Status { code, .. } => matches!(code, 502..=504),
+            Self::UnknownChecker { .. }
+            | Self::IllegalName { .. }
+            | Self::Client { .. }
+            | Self::Tls { .. }
+            | Self::Certificate { .. }
+            | Self::Body { .. }
+            | Self::Parse { .. } => false,
+        }
+    }
+
+    /// Returns the error to send to our own clients for this error.
+    ///
+    /// # Returns
+    /// An [`ApiError`] whose code tells the client whether the checker is down (`checker_unavailable`, `checker_timeout` or
+    /// `checker_circuit_open`), refused the request (`checker_status`) or something else went wrong talking to it (`checker_error`).
+    pub fn api_error(&self) -> ApiError {
+        let code: &'static str = match self {
+            Self::UnknownChecker { .. } => "unknown_checker",
+            Self::Timeout { .. } => "checker_timeout",
+            Self::Unavailable { .. } => "checker_circuit_open",
+            _ if self.is_outage() => "checker_unavailable",
+            Self::Status { .. } => "checker_status",
+            _ => "checker_error",
+        };
+        let mut err: ApiError = ApiError::from_error(self.status_code(), self).with_code(code);
+        err.upstream_status = self.upstream_status();
+        if let Self::Unavailable { retry_after, .. } = self {
+            err.retry_after = Some(retry_after.as_secs().max(1));
+        }
+        err
+    }
+
+    /// Returns the body to send to our own clients for this error.
+    ///
+    /// # Returns
+    /// The [`ApiError`] of this error, serialized.
+    #[inline]
+    pub fn body(&self) -> String { self.api_error().into_parts().1 }
+
+    /// Returns the status code with which the checker responded, if it got that far.
+    pub fn upstream_status(&self) -> Option<u16> {
+        match self {
+            Self::Status { code, .. } => Some(*code),
+            Self::Request { err, .. } | Self::Timeout { err, .. } | Self::Body { err, .. } => err.status().map(|code| code.as_u16()),
+            Self::UnknownChecker { .. }
+            | Self::IllegalName { .. }
+            | Self::Client { .. }
+            | Self::Tls { .. }
+            | Self::Certificate { .. }
+            | Self::Unavailable { .. }
+            | Self::Parse { .. } => None,
+        }
+    }
+}
+impl Display for CheckerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use CheckerError::*;
+        match self {
+            UnknownChecker { name } => write!(f, "Unknown checker '{name}'"),
+            IllegalName { name } => write!(f, "Illegal checker name '{name}' (only letters, digits, '-' and '_' are allowed)"),
+            Client { address, .. } => write!(f, "Failed to build HTTP client for checker at '{address}'"),
+            Tls { address, .. } => write!(f, "Failed to set up TLS for checker at '{address}'"),
+            Certificate { url, problem, .. } => write!(f, "Failed to connect securely to checker at '{url}': {problem}"),
+            Request { url, .. } => write!(f, "Failed to send request to checker at '{url}'"),
+            Timeout { url, after, .. } => write!(f, "Checker at '{url}' did not respond within {}s", after.as_secs_f32()),
+            Unavailable { name, retry_after } => {
+                write!(f, "Checker '{name}' is considered down after failing repeatedly; it will be tried again in {}s", retry_after.as_secs().max(1))
+            },
+            Status { url, code, body } => {
+                write!(f, "Checker at '{url}' responded with status code {code}")?;
+                if !body.is_empty() {
+                    write!(f, ": {body}")?;
+                }
+                Ok(())
+            },
+            Body { url, .. } => write!(f, "Failed to download response body from checker at '{url}'"),
+            Parse { url, .. } => write!(f, "Failed to parse response body from checker at '{url}'"),
+        }
+    }
+}
+impl Error for CheckerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use CheckerError::*;
+        match self {
+            UnknownChecker { .. } => None,
+            IllegalName { .. } => None,
+            Client { err, .. } => Some(err),
+            Tls { err, .. } => Some(err),
+            Certificate { err, .. } => Some(err),
+            Request { err, .. } => Some(err),
+            Timeout { err, .. } => Some(err),
+            Unavailable { .. } => None,
+            Status { .. } => None,
+            Body { err, .. } => Some(err),
+            Parse { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// A successful response of the checker.
+#[derive(Clone, Debug)]
+pub struct CheckerResponse {
+    /// The status code with which the checker responded.
+    pub status: u16,
+    /// The body of the response.
+    pub body:   String,
+}
+
+
+
+/// The body sent to the checker to activate a particular policy version.
+#[derive(Clone, Copy, Debug, Serialize)]
+struct ActivatePolicyModel {
+    /// The version to activate.
+    version: i64,
+}
+
+/// The part of the checker's policies we are interested in when we only need the version.
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct PolicyVersionModel {
+    /// The version of the policy.
+    version: i64,
+}
+
+
+
+/// The kinds of questions that can be asked to the checker's deliberation API.
+#[derive(Clone, Copy, Debug, Deserialize, EnumDebug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliberationKind {
+    /// Asks if a task may be executed.
+    Task,
+    /// Asks if a dataset may be accessed.
+    Data,
+    /// Asks if a workflow as a whole is OK.
+    Workflow,
+}
+impl DeliberationKind {
+    /// Returns the path of the checker's endpoint for this kind of question.
+    pub fn path(&self) -> &'static str {
+        match self {
+            Self::Task => "/v1/deliberation/execute-task",
+            Self::Data => "/v1/deliberation/access-data",
+            Self::Workflow => "/v1/deliberation/execute-workflow",
+        }
+    }
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Sends a request to the checker and returns its body if it succeeded.
+///
+/// # Arguments
+/// - `url`: The URL that the `req`uest is sent to (used for debugging purposes only).
+/// - `req`: The [`RequestBuilder`] that describes the request to send.
+/// - `token`: The token to authenticate ourselves with.
+/// - `timeout`: The maximum time the request may take.
+///
+/// # Returns
+/// The checker's [`CheckerResponse`].
+///
+/// # Errors
+/// This function errors if we failed to reach the checker, if it did not respond in time or if it responded with a non-success status code.
+async fn send(url: String, req: RequestBuilder, token: &str, timeout: Duration) -> Result<CheckerResponse, CheckerError> {
+    debug!("Sending request to checker at '{url}'");
+    let res: Response = match req.header("Authorization", format!("Bearer {token}")).timeout(timeout).send().await {
+        Ok(res) => res,
+        Err(err) if err.is_timeout() => return Err(CheckerError::Timeout { url, after: timeout, err }),
+        Err(err) => match checker_tls::explain(&err) {
+            Some(problem) => return Err(CheckerError::Certificate { url, problem, err }),
+            None => return Err(CheckerError::Request { url, err }),
+        },
+    };
+
+    // Check the status
+    let code: u16 = res.status().as_u16();
+    if code > 399 {
+        let body: String = res.text().await.unwrap_or_default();
+        return Err(CheckerError::Status { url, code, body });
+    }
+    match res.text().await {
+        Ok(body) => Ok(CheckerResponse { status: code, body }),
+        Err(err) if err.is_timeout() => Err(CheckerError::Timeout { url, after: timeout, err }),
+        Err(err) => Err(CheckerError::Body { url, err }),
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// A connection to a checker, shared by all requests sent to it.
+#[derive(Clone, Debug)]
+pub struct Checker {
+    /// The name with which the checker is selected.
+    pub name:    String,
+    /// The address of the checker.
+    pub address: Address,
+    /// The HTTP client used to talk to the checker.
+    client:      reqwest::Client,
+    /// The maximum time a request to the checker may take, unless its endpoint has a timeout of its own.
+    timeout:     Duration,
+    /// How requests to the checker are timed out and retried.
+    resilience:  ResilienceConfig,
+    /// Stops calling the checker when it is down. Shared by all clones.
+    breaker:     Arc<CircuitBreaker>,
+}
+impl Checker {
+    /// Constructor for the Checker.
+    ///
+    /// # Arguments
+    /// - `name`: The name with which the checker is selected.
+    /// - `address`: The address of the checker.
+    /// - `timeout`: The maximum time a single request to the checker may take, unless its endpoint has a timeout of its own.
+    /// - `resilience`: The [`ResilienceConfig`] that determines how requests are timed out and retried, and when the checker is considered down.
+    /// - `tls`: The [`CheckerTlsConfig`] that determines who we trust and who we are when connecting to the checker.
+    ///
+    /// # Returns
+    /// A new Checker that can be cloned cheaply.
+    ///
+    /// # Errors
+    /// This function errors if the name is illegal, if the TLS settings could not be loaded or if the HTTP client could not be built.
+    pub fn new(
+        name: impl Into<String>,
+        address: Address,
+        timeout: Duration,
+        resilience: &ResilienceConfig,
+        tls: &CheckerTlsConfig,
+    ) -> Result<Self, CheckerError> {
+        let name: String = name.into();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(CheckerError::IllegalName { name });
+        }
+
+        let mut builder: reqwest::ClientBuilder = reqwest::Client::builder().timeout(timeout);
+        if !tls.is_default() {
+            let config = tls.client_config().map_err(|err| CheckerError::Tls { address: address.to_string(), err })?;
+            builder = builder.use_preconfigured_tls(config);
+        }
+        let client: reqwest::Client = builder.build().map_err(|err| CheckerError::Client { address: address.to_string(), err })?;
+        let breaker: CircuitBreaker = CircuitBreaker::new(resilience.breaker_threshold, Duration::from_secs(resilience.breaker_cooldown));
+        Ok(Self { name, address, client, timeout, resilience: resilience.clone(), breaker: Arc::new(breaker) })
+    }
+
+    /// Calls an endpoint of the checker, retrying it if it only reads and the checker could not be reached.
+    ///
+    /// # Arguments
+    /// - `endpoint`: The [`Endpoint`] that is called.
+    /// - `url`: The URL of the endpoint.
+    /// - `req`: Builds the request to send with the given client. Called again for every retry.
+    /// - `token`: The token to authenticate ourselves with.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`].
+    ///
+    /// # Errors
+    /// This function errors if the checker is considered down, if we failed to reach it (after retrying), if it did not respond in time
+    /// or if it refused the request.
+    async fn call(
+        &self,
+        endpoint: Endpoint,
+        url: String,
+        req: impl Fn(&reqwest::Client) -> RequestBuilder,
+        token: &str,
+    ) -> Result<CheckerResponse, CheckerError> {
+        if let Err(retry_after) = self.breaker.allow().await {
+            return Err(CheckerError::Unavailable { name: self.name.clone(), retry_after });
+        }
+
+        let timeout: Duration = self.resilience.timeout(endpoint, self.timeout);
+        let retries: u32 = if endpoint.idempotent() { self.resilience.retries } else { 0 };
+        let mut attempt: u32 = 0;
+        loop {
+            match send(url.clone(), req(&self.client), token, timeout).await {
+                Err(err) if err.is_outage() && attempt < retries => {
+                    let backoff: Duration = self.resilience.backoff(attempt);
+                    attempt += 1;
+                    warn!("{err}; retrying in {}ms ({attempt}/{retries})", backoff.as_millis());
+                    tokio::time::sleep(backoff).await;
+                },
+                Err(err) if err.is_outage() => {
+                    if self.breaker.failed().await {
+                        warn!("Considering checker '{}' down for {}s", self.name, self.resilience.breaker_cooldown);
+                    }
+                    return Err(err);
+                },
+                res => {
+                    // Even if the checker refused the request, it is up
+                    self.breaker.succeeded().await;
+                    return res;
+                },
+            }
+        }
+    }
+
+    /// Returns whether the checker is considered down at the moment, i.e., whether requests to it fail without calling it.
+    #[inline]
+    pub async fn is_down(&self) -> bool { self.breaker.is_open().await }
+
+    /// Lists all policy versions on the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`], which carries the (serialized) list of versions.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the request.
+    pub async fn list_policies(&self, token: &str) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/policies", self.address);
+        self.call(Endpoint::ListPolicies, url.clone(), |client| client.get(&url), token).await
+    }
+
+    /// Retrieves the currently active policy from the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`], which carries the (serialized) active policy.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the request.
+    pub async fn get_active_policy(&self, token: &str) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/policies/active", self.address);
+        self.call(Endpoint::ActivePolicy, url.clone(), |client| client.get(&url), token).await
+    }
+
+    /// Retrieves a particular policy version from the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    /// - `version`: The version of the policy to retrieve.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`], which carries the (serialized) policy.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the request.
+    pub async fn get_policy(&self, token: &str, version: i64) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/policies/{version}", self.address);
+        self.call(Endpoint::GetPolicy, url.clone(), |client| client.get(&url), token).await
+    }
+
+    /// Adds a new policy to the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    /// - `policy`: The (serialized) policy to add.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`], which carries the policy as stored by the checker.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the policy.
+    pub async fn add_policy(&self, token: &str, policy: String) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/policies", self.address);
+        self.call(Endpoint::AddPolicy, url.clone(), |client| client.post(&url).header("Content-Type", "application/json").body(policy.clone()), token).await
+    }
+
+    /// Activates the given policy version on the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    /// - `version`: The version of the policy to activate.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`].
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the activation.
+    pub async fn activate_policy(&self, token: &str, version: i64) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/policies/active", self.address);
+        self.call(Endpoint::Activate, url.clone(), |client| client.put(&url).json(&ActivatePolicyModel { version }), token).await
+    }
+
+    /// Retrieves the version of the currently active policy from the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    ///
+    /// # Returns
+    /// The version of the active policy, or [`None`] if no policy is active.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker, if it refused the request or if it returned something that isn't a policy.
+    pub async fn get_active_version(&self, token: &str) -> Result<Option<i64>, CheckerError> {
+        let body: String = match self.get_active_policy(token).await {
+            Ok(res) => res.body,
+            Err(CheckerError::Status { code: 404, .. }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        match serde_json::from_str::<PolicyVersionModel>(&body) {
+            Ok(policy) => Ok(Some(policy.version)),
+            Err(err) => Err(CheckerError::Parse { url: format!("{}/v1/management/policies/active", self.address), err }),
+        }
+    }
+
+    /// Deactivates the currently active policy on the checker.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`].
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the deactivation.
+    pub async fn deactivate_policy(&self, token: &str) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/policies/active", self.address);
+        self.call(Endpoint::Deactivate, url.clone(), |client| client.delete(&url), token).await
+    }
+
+    /// Asks the checker's deliberation API a question.
+    ///
+    /// # Arguments
+    /// - `token`: The deliberation token to authenticate ourselves with.
+    /// - `kind`: The kind of question to ask.
+    /// - `body`: The (serialized) request to send.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`], which carries its verdict.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the request.
+    pub async fn deliberate(&self, token: &str, kind: DeliberationKind, body: String) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}{}", self.address, kind.path());
+        self.call(Endpoint::Deliberation, url.clone(), |client| client.post(&url).header("Content-Type", "application/json").body(body.clone()), token).await
+    }
+
+    /// Retrieves the context of the reasoner connector the checker uses.
+    ///
+    /// # Arguments
+    /// - `token`: The management token to authenticate ourselves with.
+    ///
+    /// # Returns
+    /// The checker's [`CheckerResponse`], which carries the (serialized) context.
+    ///
+    /// # Errors
+    /// This function errors if we failed to reach the checker or if it refused the request.
+    pub async fn get_connector_info(&self, token: &str) -> Result<CheckerResponse, CheckerError> {
+        let url: String = format!("{}/v1/management/reasoner-connector-context", self.address);
+        self.call(Endpoint::ConnectorInfo, url.clone(), |client| client.get(&url), token).await
+    }
+}
+
+
+
+/// All checkers the backend can talk to.
+#[derive(Clone, Debug)]
+pub struct Checkers {
+    /// The checkers, in the order they were configured.
+    checkers: Vec<Checker>,
+    /// The index of the checker used if a request does not select one.
+    default:  usize,
+}
+impl Checkers {
+    /// Constructor for the Checkers.
+    ///
+    /// # Arguments
+    /// - `checkers`: The checkers to choose from. Must not be empty.
+    /// - `default`: The name of the checker used if a request does not select one, or [`None`] to use the first.
+    ///
+    /// # Returns
+    /// A new Checkers.
+    ///
+    /// # Errors
+    /// This function errors if `checkers` is empty or if the default checker is not one of them.
+    pub fn new(checkers: Vec<Checker>, default: Option<&str>) -> Result<Self, CheckerError> {
+        let default: usize = match default {
+            Some(name) => checkers.iter().position(|c| c.name == name).ok_or_else(|| CheckerError::UnknownChecker { name: name.into() })?,
+            None if !checkers.is_empty() => 0,
+            None => return Err(CheckerError::UnknownChecker { name: DEFAULT_CHECKER.into() }),
+        };
+        Ok(Self { checkers, default })
+    }
+
+    /// Finds a checker by name.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the checker, or [`None`] for the default one.
+    ///
+    /// # Returns
+    /// The selected [`Checker`].
+    ///
+    /// # Errors
+    /// This function errors if no checker has the given name.
+    pub fn get(&self, name: Option<&str>) -> Result<&Checker, CheckerError> {
+        match name {
+            Some(name) => self.checkers.iter().find(|c| c.name == name).ok_or_else(|| CheckerError::UnknownChecker { name: name.into() }),
+            None => Ok(self.default()),
+        }
+    }
+
+    /// Returns the checker used if a request does not select one.
+    #[inline]
+    pub fn default(&self) -> &Checker { &self.checkers[self.default] }
+
+    /// Returns all checkers, in the order they were configured.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Checker> { self.checkers.iter() }
+}
+
+
+
+/// The query parameter with which requests select a checker.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CheckerQuery {
+    /// The name of the checker, or [`None`] for the default one.
+    pub checker: Option<String>,
+}
+
+/// Extracts the checker selected by a request with the `?checker=<NAME>` query parameter, falling back to the default one.
+#[derive(Clone, Debug)]
+pub struct SelectedChecker(pub Checker);
+impl Deref for SelectedChecker {
+    type Target = Checker;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target { &self.0 }
+}
+#[async_trait]
+impl FromRequestParts<AppState> for SelectedChecker {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let query: CheckerQuery = match Query::<CheckerQuery>::try_from_uri(&parts.uri) {
+            Ok(Query(query)) => query,
+            Err(err) => return Err(ApiError::new(StatusCode::BAD_REQUEST, err.body_text()).into_parts()),
+        };
+        match state.checkers.get(query.checker.as_deref()) {
+            Ok(checker) => Ok(Self(checker.clone())),
+            Err(err) => Err(checker_err!(err)),
+        }
+    }
+}
+
+
+
+/// Describes a checker to the client.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckerInfo {
+    /// The name with which the checker is selected.
+    pub name:    String,
+    /// The address of the checker.
+    pub address: String,
+    /// Whether the checker is used if a request does not select one.
+    pub default: bool,
+}
+
+// List the checkers that can be selected
+pub async fn get_checkers(State(state): State<AppState>) -> (StatusCode, String) {
+    let default: &str = &state.checkers.default().name;
+    let checkers: Vec<CheckerInfo> =
+        state.checkers.iter().map(|c| CheckerInfo { name: c.name.clone(), address: c.address.to_string(), default: c.name == default }).collect();
+    match serde_json::to_string(&checkers) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+
+
+/// Returns the name of the [`DEFAULT_CHECKER`].
+///
+/// Used as serde default for things persisted before there were multiple checkers.
+pub fn default_checker() -> String { DEFAULT_CHECKER.into() }
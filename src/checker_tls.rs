@@ -0,0 +1,324 @@
+//  CHECKER TLS.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 19:48:23
+//  Last edited:
+//    19 Oct 2026, 19:48:23
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements the TLS settings of the connection to the checker: a
+//!   custom CA, a client identity for mutual TLS and certificate pinning.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use log::debug;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{AlertDescription, Certificate, CertificateError, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use rustls_pemfile::Item;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+
+/***** ERRORS *****/
+/// Defines errors originating from setting up TLS for the checker connection.
+#[derive(Debug)]
+pub enum CheckerTlsError {
+    /// Failed to read a PEM file.
+    Read { what: &'static str, path: PathBuf, err: std::io::Error },
+    /// A PEM file had no certificates in it.
+    NoCertificates { what: &'static str, path: PathBuf },
+    /// A CA bundle had certificates that could not be used as trust anchors.
+    InvalidCa { path: PathBuf, invalid: usize },
+    /// Failed to load the system's trusted certificates.
+    NativeRoots { err: std::io::Error },
+    /// The key file had no private key in it.
+    NoPrivateKey { path: PathBuf },
+    /// Only one of the client certificate and key was given.
+    IncompleteIdentity,
+    /// The client certificate and key were rejected.
+    InvalidIdentity { cert: PathBuf, key: PathBuf, err: rustls::Error },
+    /// A pin was not a SHA-256 hash.
+    InvalidPin { pin: String },
+}
+impl Display for CheckerTlsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use CheckerTlsError::*;
+        match self {
+            Read { what, path, .. } => write!(f, "Failed to read {what} file '{}'", path.display()),
+            NoCertificates { what, path } => write!(f, "{} file '{}' contains no PEM certificates", capitalize(what), path.display()),
+            InvalidCa { path, invalid } => write!(f, "CA file '{}' contains {invalid} certificate(s) that cannot be used as a trust anchor", path.display()),
+            NativeRoots { .. } => write!(f, "Failed to load the system's trusted CA certificates (set a CA file for the checker instead)"),
+            NoPrivateKey { path } => write!(f, "Client key file '{}' contains no PEM private key (PKCS#8, PKCS#1 or SEC1)", path.display()),
+            IncompleteIdentity => write!(f, "Mutual TLS with the checker requires both a client certificate and a client key"),
+            InvalidIdentity { cert, key, .. } => {
+                write!(f, "Client certificate '{}' and key '{}' cannot be used together (does the key belong to the certificate?)", cert.display(), key.display())
+            },
+            InvalidPin { pin } => write!(f, "Certificate pin '{pin}' is not a hex-encoded SHA-256 hash"),
+        }
+    }
+}
+impl Error for CheckerTlsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use CheckerTlsError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            NoCertificates { .. } => None,
+            InvalidCa { .. } => None,
+            NativeRoots { err } => Some(err),
+            NoPrivateKey { .. } => None,
+            IncompleteIdentity => None,
+            InvalidIdentity { err, .. } => Some(err),
+            InvalidPin { .. } => None,
+        }
+    }
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Capitalizes the first letter of the given string.
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Reads all PEM certificates from the given file.
+///
+/// # Arguments
+/// - `what`: What the file is, for error messages.
+/// - `path`: The path to the file.
+///
+/// # Returns
+/// The DER-encoded certificates in the file, in order.
+///
+/// # Errors
+/// This function errors if the file could not be read or contains no certificates.
+fn read_certs(what: &'static str, path: &Path) -> Result<Vec<Vec<u8>>, CheckerTlsError> {
+    let mut reader = BufReader::new(File::open(path).map_err(|err| CheckerTlsError::Read { what, path: path.into(), err })?);
+    let certs: Vec<Vec<u8>> = rustls_pemfile::certs(&mut reader).map_err(|err| CheckerTlsError::Read { what, path: path.into(), err })?;
+    if certs.is_empty() {
+        return Err(CheckerTlsError::NoCertificates { what, path: path.into() });
+    }
+    Ok(certs)
+}
+
+/// Reads the first PEM private key from the given file.
+///
+/// # Arguments
+/// - `path`: The path to the file.
+///
+/// # Returns
+/// The DER-encoded private key.
+///
+/// # Errors
+/// This function errors if the file could not be read or contains no private key.
+fn read_key(path: &Path) -> Result<PrivateKey, CheckerTlsError> {
+    let mut reader = BufReader::new(File::open(path).map_err(|err| CheckerTlsError::Read { what: "client key", path: path.into(), err })?);
+    let items: Vec<Item> = rustls_pemfile::read_all(&mut reader).map_err(|err| CheckerTlsError::Read { what: "client key", path: path.into(), err })?;
+    for item in items {
+        match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => continue,
+        }
+    }
+    Err(CheckerTlsError::NoPrivateKey { path: path.into() })
+}
+
+/// Parses a certificate pin.
+///
+/// # Arguments
+/// - `pin`: The pin, as hex with optional colons (as printed by `openssl x509 -fingerprint -sha256`).
+///
+/// # Returns
+/// The SHA-256 hash of the pinned certificate.
+///
+/// # Errors
+/// This function errors if the pin is not a hex-encoded SHA-256 hash.
+fn parse_pin(pin: &str) -> Result<[u8; 32], CheckerTlsError> {
+    let raw: String = pin.trim().trim_start_matches("sha256:").replace(':', "");
+    let mut hash: [u8; 32] = [0; 32];
+    hex::decode_to_slice(raw, &mut hash).map_err(|_| CheckerTlsError::InvalidPin { pin: pin.into() })?;
+    Ok(hash)
+}
+
+
+
+/// Verifies the checker's certificate as usual, and then also requires it to be one of the pinned certificates.
+struct PinningVerifier {
+    /// The verifier that checks the certificate chain and hostname.
+    inner: WebPkiVerifier,
+    /// The SHA-256 hashes of the certificates that are accepted.
+    pins:  Vec<[u8; 32]>,
+}
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified: ServerCertVerified = self.inner.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        let hash: [u8; 32] = Sha256::digest(&end_entity.0).into();
+        if !self.pins.contains(&hash) {
+            return Err(rustls::Error::InvalidCertificate(CertificateError::Other(Arc::new(PinMismatch { hash }))));
+        }
+        Ok(verified)
+    }
+}
+
+/// The error reported when the checker's certificate is not pinned.
+#[derive(Debug)]
+struct PinMismatch {
+    /// The SHA-256 hash of the certificate the checker presented.
+    hash: [u8; 32],
+}
+impl Display for PinMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult { write!(f, "certificate with SHA-256 hash {} is not pinned", hex::encode(self.hash)) }
+}
+impl Error for PinMismatch {}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Configures TLS for the connection to the checker.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckerTlsConfig {
+    /// A PEM bundle with the CA certificates to trust instead of the system's.
+    pub ca:   Option<PathBuf>,
+    /// A PEM file with the client certificate chain to present to the checker.
+    pub cert: Option<PathBuf>,
+    /// A PEM file with the private key of the client certificate.
+    pub key:  Option<PathBuf>,
+    /// The SHA-256 hashes of the certificates the checker may present. If empty, any certificate signed by a trusted CA is accepted.
+    pub pins: Vec<String>,
+}
+impl CheckerTlsConfig {
+    /// Returns whether anything is configured, i.e., whether the default TLS settings can be used.
+    #[inline]
+    pub fn is_default(&self) -> bool { self == &Self::default() }
+
+    /// Builds the TLS configuration to connect to the checker with.
+    ///
+    /// # Returns
+    /// A [`ClientConfig`] that trusts the configured CA (or the system's), presents the configured client identity and checks the configured pins.
+    ///
+    /// # Errors
+    /// This function errors if any of the files could not be loaded, or if the pins are invalid.
+    pub fn client_config(&self) -> Result<ClientConfig, CheckerTlsError> {
+        // Catch mistakes in the settings before touching any files
+        if self.cert.is_some() != self.key.is_some() {
+            return Err(CheckerTlsError::IncompleteIdentity);
+        }
+        let pins: Vec<[u8; 32]> = self.pins.iter().map(|pin| parse_pin(pin)).collect::<Result<_, _>>()?;
+
+        // Decide who to trust
+        let mut roots: RootCertStore = RootCertStore::empty();
+        match &self.ca {
+            Some(path) => {
+                let certs: Vec<Vec<u8>> = read_certs("CA", path)?;
+                let (valid, invalid): (usize, usize) = roots.add_parsable_certificates(&certs);
+                if invalid > 0 {
+                    return Err(CheckerTlsError::InvalidCa { path: path.clone(), invalid });
+                }
+                debug!("Trusting {valid} CA certificate(s) from '{}' for the checker", path.display());
+            },
+            None => {
+                let certs: Vec<Vec<u8>> =
+                    rustls_native_certs::load_native_certs().map_err(|err| CheckerTlsError::NativeRoots { err })?.into_iter().map(|c| c.0).collect();
+                roots.add_parsable_certificates(&certs);
+            },
+        }
+
+        // Decide how to verify the checker
+        let builder = ClientConfig::builder().with_safe_defaults();
+        let builder = if pins.is_empty() {
+            builder.with_root_certificates(roots)
+        } else {
+            builder.with_custom_certificate_verifier(Arc::new(PinningVerifier { inner: WebPkiVerifier::new(roots, None), pins }))
+        };
+
+        // Decide who we are
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                let chain: Vec<Certificate> = read_certs("client certificate", cert)?.into_iter().map(Certificate).collect();
+                builder
+                    .with_client_auth_cert(chain, read_key(key)?)
+                    .map_err(|err| CheckerTlsError::InvalidIdentity { cert: cert.clone(), key: key.clone(), err })
+            },
+            _ => Ok(builder.with_no_client_auth()),
+        }
+    }
+}
+
+
+
+/// Explains why a TLS connection to the checker failed, if it did.
+///
+/// # Arguments
+/// - `err`: The error to search for a TLS problem.
+///
+/// # Returns
+/// A description of the problem and how to fix it, or [`None`] if the error is not about TLS.
+pub fn explain(err: &(dyn Error + 'static)) -> Option<String> {
+    // Find the rustls error, which may be hidden inside an I/O error
+    let mut next: Option<&(dyn Error + 'static)> = Some(err);
+    let mut tls: Option<&rustls::Error> = None;
+    while let Some(err) = next {
+        if let Some(err) = err.downcast_ref::<rustls::Error>() {
+            tls = Some(err);
+            break;
+        }
+        if let Some(err) = err.downcast_ref::<std::io::Error>().and_then(|err| err.get_ref()).and_then(|err| err.downcast_ref::<rustls::Error>()) {
+            tls = Some(err);
+            break;
+        }
+        next = err.source();
+    }
+
+    let problem: String = match tls? {
+        rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer) => {
+            "the checker's certificate is not signed by a trusted CA; configure the CA bundle of your PKI as the checker's 'ca'".into()
+        },
+        rustls::Error::InvalidCertificate(CertificateError::Expired) => {
+            "the checker's certificate has expired (or this machine's clock is wrong); renew the checker's certificate".into()
+        },
+        rustls::Error::InvalidCertificate(CertificateError::NotValidYet) => {
+            "the checker's certificate is not valid yet (or this machine's clock is wrong)".into()
+        },
+        rustls::Error::InvalidCertificate(CertificateError::NotValidForName) => {
+            "the checker's certificate is not valid for the hostname in the checker's address; connect using a name listed in the certificate".into()
+        },
+        rustls::Error::InvalidCertificate(CertificateError::Revoked) => "the checker's certificate has been revoked".into(),
+        rustls::Error::InvalidCertificate(CertificateError::Other(err)) => {
+            format!("the checker's certificate was rejected: {err}; update the checker's 'pins' if the certificate was renewed on purpose")
+        },
+        rustls::Error::InvalidCertificate(err) => format!("the checker's certificate is invalid ({err:?})"),
+        rustls::Error::AlertReceived(
+            AlertDescription::CertificateRequired | AlertDescription::BadCertificate | AlertDescription::UnknownCA | AlertDescription::CertificateUnknown,
+        ) => "the checker rejected our client certificate (or requires one); configure the checker's 'cert' and 'key' with an identity it trusts".into(),
+        rustls::Error::AlertReceived(AlertDescription::CertificateExpired) => "the checker says our client certificate has expired; renew it".into(),
+        err => format!("the TLS handshake failed ({err})"),
+    };
+    Some(problem)
+}
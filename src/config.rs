@@ -0,0 +1,667 @@
+//  CONFIG.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 18:02:11
+//  Last edited:
+//    19 Oct 2026, 18:02:11
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Defines the configuration of the backend server, which is layered
+//!   from a TOML file, environment variables and command-line flags.
+//
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use specifications::address::Address;
+
+use crate::bstowir::IndexSource;
+use crate::checker::DEFAULT_CHECKER;
+use crate::checker_tls::CheckerTlsConfig;
+use crate::resilience::ResilienceConfig;
+
+
+/***** ERRORS *****/
+/// Defines errors originating from loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Failed to read the configuration file.
+    Read { path: PathBuf, err: std::io::Error },
+    /// Failed to parse the configuration file.
+    Parse { path: PathBuf, err: toml::de::Error },
+    /// Failed to serialize the effective configuration.
+    Serialize { err: toml::ser::Error },
+    /// Only part of the TLS settings were given.
+    IncompleteTls,
+    /// A flag about the single checker was given while named checkers are configured, which it would not affect.
+    CheckerOverride { flag: &'static str },
+}
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use ConfigError::*;
+        match self {
+            Read { path, .. } => write!(f, "Failed to read configuration file '{}'", path.display()),
+            Parse { path, .. } => write!(f, "Failed to parse configuration file '{}' as TOML", path.display()),
+            Serialize { .. } => write!(f, "Failed to serialize configuration as TOML"),
+            IncompleteTls => write!(f, "Serving HTTPS (or redirecting to it) requires both a TLS certificate and key"),
+            CheckerOverride { flag } => {
+                write!(f, "'--{flag}' only applies if no named checkers are configured; set it for each checker in the '[checkers]' table instead")
+            },
+        }
+    }
+}
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use ConfigError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            Parse { err, .. } => Some(err),
+            Serialize { err } => Some(err),
+            IncompleteTls | CheckerOverride { .. } => None,
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// The format in which log messages are written.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Plain text lines, as written by `tracing`.
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+    /// Colourful, human-friendly output for development.
+    Human,
+}
+
+/// The most verbose level at which log messages are written.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+impl From<LogLevel> for tracing::Level {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Trace => Self::TRACE,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Error => Self::ERROR,
+        }
+    }
+}
+
+
+
+/// Configures how the backend serves the client and the API.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The address to listen on.
+    pub bind:            SocketAddr,
+    /// The directory with the built client files.
+    pub static_dir:      PathBuf,
+    /// The origins that may call the API from another site. If empty, no CORS headers are sent.
+    pub cors_origins:    Vec<String>,
+    /// The number of seconds after which a request to the backend is aborted.
+    pub request_timeout: u64,
+    /// If given, the server serves HTTPS instead of HTTP.
+    pub tls:             Option<TlsConfig>,
+}
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 3001),
+            static_dir: "./clientbuild".into(),
+            cors_origins: vec![],
+            request_timeout: 300,
+            tls: None,
+        }
+    }
+}
+
+/// Configures serving HTTPS.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// The PEM file with the server's certificate chain.
+    pub cert:            PathBuf,
+    /// The PEM file with the server's private key.
+    pub key:             PathBuf,
+    /// The number of seconds between checks whether the certificate or key changed on disk.
+    #[serde(default = "default_reload_interval")]
+    pub reload_interval: u64,
+    /// If given, plain HTTP requests to this address are redirected to HTTPS.
+    #[serde(default)]
+    pub redirect_from:   Option<SocketAddr>,
+}
+impl TlsConfig {
+    /// Constructor for the TlsConfig that uses the defaults for everything but the certificate and key.
+    ///
+    /// # Arguments
+    /// - `cert`: The PEM file with the server's certificate chain.
+    /// - `key`: The PEM file with the server's private key.
+    ///
+    /// # Returns
+    /// A new TlsConfig.
+    #[inline]
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self { cert: cert.into(), key: key.into(), reload_interval: default_reload_interval(), redirect_from: None }
+    }
+}
+
+/// Configures the connection to the checker.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckerConfig {
+    /// The address of the checker to connect to.
+    pub address:     Address,
+    /// The number of seconds after which a request to the checker is aborted.
+    pub timeout:     u64,
+    /// How requests to the checker are timed out and retried, and when the checker is considered down.
+    pub resilience:  ResilienceConfig,
+    /// Who we trust and who we are when connecting to the checker.
+    pub tls:         CheckerTlsConfig,
+    /// The management token with which the checker is probed in the background. Without it, only whether it responds is probed.
+    ///
+    /// Never serialized, so it doesn't end up in the output of `--print-config`.
+    #[serde(skip_serializing)]
+    pub probe_token: Option<String>,
+}
+impl Default for CheckerConfig {
+    fn default() -> Self {
+        Self {
+            address: Address::Hostname("http://localhost".into(), 3030),
+            timeout: 30,
+            resilience: ResilienceConfig::default(),
+            tls: CheckerTlsConfig::default(),
+            probe_token: None,
+        }
+    }
+}
+
+/// Configures where the backend keeps its state.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    /// The file with the keys used to encrypt cookies.
+    pub key:            PathBuf,
+    /// The file in which activation proposals are stored.
+    pub proposals:      PathBuf,
+    /// The file in which scheduled activations are stored.
+    pub schedules:      PathBuf,
+    /// The JSON Lines file to which the audit log is appended.
+    pub audit:          PathBuf,
+    /// The JSON Lines file to which the deliberation history is appended.
+    pub history:        PathBuf,
+    /// The YAML file with the rules that workflows are checked against, if any.
+    pub workflow_rules: Option<PathBuf>,
+}
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            key: "./key".into(),
+            proposals: "./proposals.json".into(),
+            schedules: "./schedules.json".into(),
+            audit: "./audit.jsonl".into(),
+            history: "./history.jsonl".into(),
+            workflow_rules: None,
+        }
+    }
+}
+
+/// Configures policy activation.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActivationConfig {
+    /// If `true`, policies can only be activated after a second user approved the activation.
+    pub four_eyes:         bool,
+    /// The number of seconds after which a pending activation proposal expires.
+    pub proposal_ttl:      i64,
+    /// The number of seconds between checks for scheduled activations that are due.
+    pub schedule_interval: u64,
+}
+impl Default for ActivationConfig {
+    fn default() -> Self { Self { four_eyes: false, proposal_ttl: 86400, schedule_interval: 5 } }
+}
+
+/// Configures how the checkers are monitored.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    /// The number of seconds between probes of the checkers.
+    pub probe_interval: u64,
+    /// The number of probes remembered per checker.
+    pub history:        usize,
+}
+impl Default for HealthConfig {
+    fn default() -> Self { Self { probe_interval: 15, history: 100 } }
+}
+
+/// Configures the conversions offered by `/api/conv`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConvConfig {
+    /// The `eflint-to-json` compiler used to convert eFLINT to eFLINT JSON.
+    pub eflint_to_json: PathBuf,
+    /// Where the packages are found when compiling BraneScript.
+    pub packages:       IndexSource,
+    /// Where the datasets are found when compiling BraneScript.
+    pub data:           IndexSource,
+}
+impl Default for ConvConfig {
+    fn default() -> Self {
+        Self {
+            eflint_to_json: "./bin/eflint-to-json".into(),
+            packages: IndexSource::LocalTest("./tests/packages".into()),
+            data: IndexSource::LocalTest("./tests/data".into()),
+        }
+    }
+}
+
+/// Configures logging.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// The format of log messages.
+    pub format: LogFormat,
+    /// The most verbose level that is logged.
+    pub level:  LogLevel,
+}
+impl Default for LogConfig {
+    fn default() -> Self { Self { format: LogFormat::Text, level: LogLevel::Debug } }
+}
+
+
+
+/// Returns the default [`TlsConfig::reload_interval`].
+#[inline]
+fn default_reload_interval() -> u64 { 10 }
+
+
+
+/// Overrides of the configuration given on the command-line or in the environment.
+///
+/// Every field corresponds to a field of the [`Config`], and overrides it if given.
+#[derive(Args, Clone, Debug, Default)]
+pub struct ConfigOverrides {
+    /// The address to listen on.
+    #[clap(long, env = "REASONER_GUI_BIND", help = "The address to listen on. [default: 0.0.0.0:3001]")]
+    pub bind: Option<SocketAddr>,
+    /// The directory with the built client files.
+    #[clap(long, env = "REASONER_GUI_STATIC_DIR", help = "The directory with the built client files. [default: ./clientbuild]")]
+    pub static_dir: Option<PathBuf>,
+    /// The origins that may call the API from another site.
+    #[clap(
+        long,
+        env = "REASONER_GUI_CORS_ORIGINS",
+        value_delimiter = ',',
+        help = "The origins that may call the API from another site (comma-separated). If omitted, no CORS headers are sent."
+    )]
+    pub cors_origins: Option<Vec<String>>,
+    /// The number of seconds after which a request to the backend is aborted.
+    #[clap(long, env = "REASONER_GUI_REQUEST_TIMEOUT", help = "The number of seconds after which a request to the backend is aborted. [default: 300]")]
+    pub request_timeout: Option<u64>,
+    /// The PEM file with the server's certificate chain.
+    #[clap(long, env = "REASONER_GUI_TLS_CERT", help = "The PEM file with the server's certificate chain. If given (with '--tls-key'), HTTPS is served.")]
+    pub tls_cert: Option<PathBuf>,
+    /// The PEM file with the server's private key.
+    #[clap(long, env = "REASONER_GUI_TLS_KEY", help = "The PEM file with the server's private key. If given (with '--tls-cert'), HTTPS is served.")]
+    pub tls_key: Option<PathBuf>,
+    /// If given, plain HTTP requests to this address are redirected to HTTPS.
+    #[clap(long, env = "REASONER_GUI_TLS_REDIRECT_FROM", help = "If given, plain HTTP requests to this address are redirected to HTTPS.")]
+    pub tls_redirect_from: Option<SocketAddr>,
+
+    /// The name of the checker used if a request does not select one.
+    #[clap(long, env = "REASONER_GUI_DEFAULT_CHECKER", help = "The name of the checker used if a request does not select one. [default: the first]")]
+    pub default_checker: Option<String>,
+    /// The address of the checker to connect to.
+    #[clap(short, long, env = "REASONER_GUI_CHECKER_ADDRESS", help = "The address of the checker to connect to/manage. [default: http://localhost:3030]")]
+    pub checker_address: Option<Address>,
+    /// The number of seconds after which a request to the checker is aborted.
+    #[clap(long, env = "REASONER_GUI_CHECKER_TIMEOUT", help = "The number of seconds after which a request to the checker is aborted. [default: 30]")]
+    pub checker_timeout: Option<u64>,
+    /// A PEM bundle with the CA certificates to trust for the checker.
+    #[clap(long, env = "REASONER_GUI_CHECKER_CA", help = "A PEM bundle with the CA certificates to trust for the checker, instead of the system's.")]
+    pub checker_ca: Option<PathBuf>,
+    /// A PEM file with the client certificate chain to present to the checker.
+    #[clap(long, env = "REASONER_GUI_CHECKER_CERT", help = "A PEM file with the client certificate to present to the checker (mutual TLS).")]
+    pub checker_cert: Option<PathBuf>,
+    /// A PEM file with the private key of the client certificate.
+    #[clap(long, env = "REASONER_GUI_CHECKER_KEY", help = "A PEM file with the private key of the client certificate given with '--checker-cert'.")]
+    pub checker_key: Option<PathBuf>,
+    /// The SHA-256 hashes of the certificates the checker may present.
+    #[clap(
+        long,
+        env = "REASONER_GUI_CHECKER_PINS",
+        value_delimiter = ',',
+        help = "The SHA-256 fingerprints of the certificates the checker may present (comma-separated). If omitted, any certificate signed by a \
+                trusted CA is accepted."
+    )]
+    pub checker_pins: Option<Vec<String>>,
+    /// The management token with which the checker is probed in the background.
+    #[clap(
+        long,
+        env = "REASONER_GUI_CHECKER_PROBE_TOKEN",
+        help = "The management token with which the checker is probed in the background. If omitted, only whether the checker responds is probed."
+    )]
+    pub checker_probe_token: Option<String>,
+
+    /// The file with the keys used to encrypt cookies.
+    #[clap(long, env = "REASONER_GUI_KEY_PATH", help = "The file with the keys used to encrypt cookies. Generated if it does not exist. [default: ./key]")]
+    pub key_path: Option<PathBuf>,
+    /// The file in which activation proposals are stored.
+    #[clap(long, env = "REASONER_GUI_PROPOSALS_PATH", help = "The file in which activation proposals are stored. [default: ./proposals.json]")]
+    pub proposals_path: Option<PathBuf>,
+    /// The file in which scheduled activations are stored.
+    #[clap(
+        long,
+        env = "REASONER_GUI_SCHEDULES_PATH",
+        help = "The file in which scheduled policy activations are stored. [default: ./schedules.json]"
+    )]
+    pub schedules_path: Option<PathBuf>,
+    /// The JSON Lines file to which the audit log is appended.
+    #[clap(
+        long,
+        env = "REASONER_GUI_AUDIT_PATH",
+        help = "The JSON Lines file to which the audit log of all management actions is appended. [default: ./audit.jsonl]"
+    )]
+    pub audit_path: Option<PathBuf>,
+    /// The JSON Lines file to which the deliberation history is appended.
+    #[clap(
+        long,
+        env = "REASONER_GUI_HISTORY_PATH",
+        help = "The JSON Lines file to which the history of deliberation requests is appended. [default: ./history.jsonl]"
+    )]
+    pub history_path: Option<PathBuf>,
+    /// The YAML file with the rules that workflows are checked against.
+    #[clap(
+        long,
+        env = "REASONER_GUI_WORKFLOW_RULES",
+        help = "A YAML file with rules that workflows can be checked against before asking the checker. If omitted, there are no rules."
+    )]
+    pub workflow_rules: Option<PathBuf>,
+
+    /// If given, policies can only be activated after a second user approved the activation.
+    #[clap(
+        long,
+        env = "REASONER_GUI_FOUR_EYES",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        help = "If given, policies can only be activated by proposing it and having another user approve it."
+    )]
+    pub four_eyes: Option<bool>,
+    /// The number of seconds after which a pending activation proposal expires.
+    #[clap(long, env = "REASONER_GUI_PROPOSAL_TTL", help = "The number of seconds after which a pending activation proposal expires. [default: 86400]")]
+    pub proposal_ttl: Option<i64>,
+    /// The number of seconds between checks for scheduled activations that are due.
+    #[clap(
+        long,
+        env = "REASONER_GUI_SCHEDULE_INTERVAL",
+        help = "The number of seconds between checks for scheduled policy activations that are due. [default: 5]"
+    )]
+    pub schedule_interval: Option<u64>,
+
+    /// The number of seconds between probes of the checkers.
+    #[clap(long, env = "REASONER_GUI_PROBE_INTERVAL", help = "The number of seconds between probes of the checkers. [default: 15]")]
+    pub probe_interval: Option<u64>,
+
+    /// The `eflint-to-json` compiler used to convert eFLINT to eFLINT JSON.
+    #[clap(long, env = "REASONER_GUI_EFLINT_TO_JSON", help = "The eflint-to-json compiler executable. [default: ./bin/eflint-to-json]")]
+    pub eflint_to_json: Option<PathBuf>,
+    /// Where the packages are found when compiling BraneScript.
+    #[clap(
+        long,
+        env = "REASONER_GUI_PACKAGES",
+        help = "Where packages are found when compiling BraneScript, as 'local_test:<PATH>', 'local_client:<PATH>' or 'remote:<ADDRESS>'. \
+                [default: local_test:./tests/packages]"
+    )]
+    pub packages: Option<IndexSource>,
+    /// Where the datasets are found when compiling BraneScript.
+    #[clap(
+        long,
+        env = "REASONER_GUI_DATA",
+        help = "Where datasets are found when compiling BraneScript, as 'local_test:<PATH>', 'local_client:<PATH>' or 'remote:<ADDRESS>'. \
+                [default: local_test:./tests/data]"
+    )]
+    pub data: Option<IndexSource>,
+
+    /// The format of log messages.
+    #[clap(long, env = "REASONER_GUI_LOG_FORMAT", help = "The format of log messages. [default: text]")]
+    pub log_format: Option<LogFormat>,
+    /// The most verbose level that is logged.
+    #[clap(long, env = "REASONER_GUI_LOG_LEVEL", help = "The most verbose level that is logged. [default: debug]")]
+    pub log_level: Option<LogLevel>,
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// The configuration of the backend server.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The name of the checker used if a request does not select one. Defaults to the first in `checkers`.
+    pub default_checker: Option<String>,
+    /// How the backend serves the client and the API.
+    pub server:          ServerConfig,
+    /// The connection to the checker, if only one is used.
+    pub checker:         CheckerConfig,
+    /// The connections to the checkers by name, if more than one is used. Takes precedence over `checker`.
+    pub checkers:        BTreeMap<String, CheckerConfig>,
+    /// Where the backend keeps its state.
+    pub paths:           PathsConfig,
+    /// How policies are activated.
+    pub activation:      ActivationConfig,
+    /// How the checkers are monitored.
+    pub health:          HealthConfig,
+    /// The conversions offered by `/api/conv`.
+    pub conv:            ConvConfig,
+    /// Logging.
+    pub log:             LogConfig,
+}
+impl Config {
+    /// Loads the configuration from the given TOML file. Anything not in the file gets its default value.
+    ///
+    /// # Arguments
+    /// - `path`: The path to the configuration file.
+    ///
+    /// # Returns
+    /// The loaded Config.
+    ///
+    /// # Errors
+    /// This function errors if the file could not be read or is not a valid configuration file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path: &Path = path.as_ref();
+        let raw: String = fs::read_to_string(path).map_err(|err| ConfigError::Read { path: path.into(), err })?;
+        let config: Self = toml::from_str(&raw).map_err(|err| ConfigError::Parse { path: path.into(), err })?;
+        debug!("Loaded configuration from '{}'", path.display());
+        Ok(config)
+    }
+
+    /// Builds the effective configuration from all layers.
+    ///
+    /// From weakest to strongest, these are: the defaults, the given configuration file, the legacy `CLIENT_FILES_PATH` and `HUMANLOGGER`
+    /// environment variables, and finally the given overrides (which clap already layers as environment variables < flags).
+    ///
+    /// # Arguments
+    /// - `path`: The configuration file to load, if any.
+    /// - `overrides`: The [`ConfigOverrides`] given on the command-line or in the environment.
+    ///
+    /// # Returns
+    /// The effective Config.
+    ///
+    /// # Errors
+    /// This function errors if the configuration file could not be loaded.
+    pub fn resolve(path: Option<&Path>, overrides: ConfigOverrides) -> Result<Self, ConfigError> {
+        let mut config: Self = match path {
+            Some(path) => Self::load(path)?,
+            None => Self::default(),
+        };
+
+        // Support the environment variables we had before there was a configuration
+        if let Ok(static_dir) = std::env::var("CLIENT_FILES_PATH") {
+            config.server.static_dir = static_dir.into();
+        }
+        if std::env::var("HUMANLOGGER").is_ok() {
+            config.log.format = LogFormat::Human;
+        }
+
+        config.apply(overrides)?;
+        Ok(config)
+    }
+
+    /// Overrides the fields of this configuration that are given in the `overrides`.
+    ///
+    /// # Arguments
+    /// - `overrides`: The [`ConfigOverrides`] to apply.
+    ///
+    /// # Errors
+    /// This function errors if the overrides only give part of the TLS settings that are not in the configuration already.
+    pub fn apply(&mut self, overrides: ConfigOverrides) -> Result<(), ConfigError> {
+        let ConfigOverrides {
+            bind,
+            static_dir,
+            cors_origins,
+            request_timeout,
+            tls_cert,
+            tls_key,
+            tls_redirect_from,
+            default_checker,
+            checker_address,
+            checker_timeout,
+            checker_ca,
+            checker_cert,
+            checker_key,
+            checker_pins,
+            checker_probe_token,
+            key_path,
+            proposals_path,
+            schedules_path,
+            audit_path,
+            history_path,
+            workflow_rules,
+            four_eyes,
+            proposal_ttl,
+            schedule_interval,
+            probe_interval,
+            eflint_to_json,
+            packages,
+            data,
+            log_format,
+            log_level,
+        } = overrides;
+        /// Assigns `$value` to `$field` if it's given.
+        macro_rules! set {
+            ($field:expr, $value:expr) => {
+                if let Some(value) = $value {
+                    $field = value;
+                }
+            };
+        }
+
+        // The flags about the single checker would silently do nothing if there are named ones
+        if !self.checkers.is_empty() {
+            let given: [(&'static str, bool); 7] = [
+                ("checker-address", checker_address.is_some()),
+                ("checker-timeout", checker_timeout.is_some()),
+                ("checker-ca", checker_ca.is_some()),
+                ("checker-cert", checker_cert.is_some()),
+                ("checker-key", checker_key.is_some()),
+                ("checker-pins", checker_pins.is_some()),
+                ("checker-probe-token", checker_probe_token.is_some()),
+            ];
+            if let Some((flag, _)) = given.into_iter().find(|(_, given)| *given) {
+                return Err(ConfigError::CheckerOverride { flag });
+            }
+        }
+
+        set!(self.server.bind, bind);
+        set!(self.server.static_dir, static_dir);
+        set!(self.server.cors_origins, cors_origins);
+        set!(self.server.request_timeout, request_timeout);
+        set!(self.default_checker, default_checker.map(Some));
+        set!(self.checker.address, checker_address);
+        set!(self.checker.timeout, checker_timeout);
+        set!(self.checker.tls.ca, checker_ca.map(Some));
+        set!(self.checker.tls.cert, checker_cert.map(Some));
+        set!(self.checker.tls.key, checker_key.map(Some));
+        set!(self.checker.tls.pins, checker_pins);
+        set!(self.checker.probe_token, checker_probe_token.map(Some));
+        set!(self.paths.key, key_path);
+        set!(self.paths.proposals, proposals_path);
+        set!(self.paths.schedules, schedules_path);
+        set!(self.paths.audit, audit_path);
+        set!(self.paths.history, history_path);
+        set!(self.paths.workflow_rules, workflow_rules.map(Some));
+        set!(self.activation.four_eyes, four_eyes);
+        set!(self.activation.proposal_ttl, proposal_ttl);
+        set!(self.activation.schedule_interval, schedule_interval);
+        set!(self.health.probe_interval, probe_interval);
+        set!(self.conv.eflint_to_json, eflint_to_json);
+        set!(self.conv.packages, packages);
+        set!(self.conv.data, data);
+        set!(self.log.format, log_format);
+        set!(self.log.level, log_level);
+
+        // TLS is either configured completely or not at all
+        match (&mut self.server.tls, tls_cert, tls_key) {
+            (Some(tls), cert, key) => {
+                set!(tls.cert, cert);
+                set!(tls.key, key);
+            },
+            (tls @ None, Some(cert), Some(key)) => *tls = Some(TlsConfig::new(cert, key)),
+            (None, None, None) => {},
+            (None, _, _) => return Err(ConfigError::IncompleteTls),
+        }
+        match (&mut self.server.tls, tls_redirect_from) {
+            (Some(tls), redirect_from) => set!(tls.redirect_from, redirect_from.map(Some)),
+            (None, Some(_)) => return Err(ConfigError::IncompleteTls),
+            (None, None) => {},
+        }
+        Ok(())
+    }
+
+    /// Returns the checkers to connect to.
+    ///
+    /// # Returns
+    /// The named checkers in `checkers` if there are any, or else `checker` as the [`DEFAULT_CHECKER`].
+    pub fn checkers(&self) -> Vec<(&str, &CheckerConfig)> {
+        if self.checkers.is_empty() {
+            vec![(DEFAULT_CHECKER, &self.checker)]
+        } else {
+            self.checkers.iter().map(|(name, checker)| (name.as_str(), checker)).collect()
+        }
+    }
+
+    /// Serializes the configuration as TOML, e.g., to show the effective configuration.
+    ///
+    /// # Returns
+    /// The configuration as it would be written in a configuration file.
+    ///
+    /// # Errors
+    /// This function errors if the configuration could not be serialized.
+    pub fn to_toml(&self) -> Result<String, ConfigError> { toml::to_string_pretty(self).map_err(|err| ConfigError::Serialize { err }) }
+}
@@ -1,11 +1,15 @@
-use axum::extract::Query;
+use std::path::Path;
+
+use axum::extract::{Query, State};
 use axum::http::StatusCode;
+use brane_tsk::errors::StringError;
 use eflint_json::v0_1_0_srv::Request;
 use eflint_json::DisplayEFlint;
 use serde::Deserialize;
 
-use crate::bstowir::{bs_to_wir, IndexSource};
-use crate::eflinttojson::eflint_to_json;
+use crate::auth::AppState;
+use crate::bstowir::bs_to_wir;
+use crate::eflinttojson::{eflint_to_json, EFlintToJsonError};
 
 #[derive(Deserialize)]
 pub struct ConvQuery {
@@ -22,47 +26,34 @@ pub enum CodeFormat {
     BraneScript,
 }
 
-pub async fn post_conv(Query(conv): Query<ConvQuery>, body: String) -> (StatusCode, String) {
+pub async fn post_conv(State(state): State<AppState>, Query(conv): Query<ConvQuery>, body: String) -> (StatusCode, String) {
     if conv.from == conv.to {
-        return (
-            StatusCode::BAD_REQUEST,
-            "From and into can't be the same format".into(),
-        );
+        return err!(StatusCode::BAD_REQUEST, StringError("From and into can't be the same format".into()));
     }
 
     if conv.from == CodeFormat::EFlint && conv.to == CodeFormat::EFlintJson {
-        return match to_eflint_json(body).await {
+        return match to_eflint_json(&state.conv.eflint_to_json, body).await {
             Ok(ret) => (StatusCode::OK, ret),
-            Err(err) => return (StatusCode::BAD_REQUEST, err.into()),
+            Err(err @ EFlintToJsonError::Compile { .. }) => return err!(StatusCode::BAD_REQUEST, err),
+            Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
         };
     } else if conv.from == CodeFormat::EFlintJson && conv.to == CodeFormat::EFlint {
         return match to_eflint(body).await {
             Ok(ret) => (StatusCode::OK, ret),
-            Err(err) => return (StatusCode::BAD_REQUEST, err.into()),
+            Err(err) => return err!(StatusCode::BAD_REQUEST, StringError(err)),
         };
     } else if conv.from == CodeFormat::BraneScript && conv.to == CodeFormat::WIR {
-        return match bs_to_wir(
-            IndexSource::LocalTest("./tests/packages".into()),
-            IndexSource::LocalTest("./tests/data".into()),
-            body,
-        )
-        .await
-        {
+        return match bs_to_wir(state.conv.packages.clone(), state.conv.data.clone(), body).await {
             Ok(ret) => (StatusCode::OK, ret),
-            Err((code, msg)) => return (code, msg),
+            Err(err) => return err.into_parts(),
         };
     }
 
-    return (StatusCode::BAD_REQUEST, "Invalid conversion".into());
+    return err!(StatusCode::BAD_REQUEST, StringError("Invalid conversion".into()));
 }
 
-pub async fn to_eflint_json(body: String) -> Result<String, String> {
-    match eflint_to_json(body).await {
-        Ok(req) => Ok(req),
-        Err(err) => {
-            return Err(err.to_string());
-        }
-    }
+pub async fn to_eflint_json(compiler: &Path, body: String) -> Result<String, EFlintToJsonError> {
+    eflint_to_json(compiler, body).await
 }
 pub async fn to_eflint(body: String) -> Result<String, String> {
     let req: Request = match serde_json::from_str::<Request>(&body) {
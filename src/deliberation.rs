@@ -14,168 +14,57 @@
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum_extra::extract::cookie::PrivateCookieJar;
-
-use crate::auth::AppState;
-
-//  Exec task request
-pub async fn post_exec_task(
-    State(state): State<AppState>,
-    jar: PrivateCookieJar,
-    body: String,
-) -> (StatusCode, String) {
-    let deliberation_auth_token = match jar.get("reasoner_deliberation_auth") {
+use brane_tsk::errors::StringError;
+
+use crate::auth::{AppState, AuthDataType};
+use crate::checker::{Checker, DeliberationKind, SelectedChecker};
+use crate::history;
+
+
+/***** HELPER FUNCTIONS *****/
+/// Forwards a question to the checker's deliberation API, recording it in the history.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the history to record in.
+/// - `checker`: The [`Checker`] to ask.
+/// - `jar`: The cookies of the user, holding their tokens.
+/// - `kind`: The kind of question to ask.
+/// - `body`: The raw request to forward.
+///
+/// # Returns
+/// The status code and body to send back to the client.
+async fn deliberate(state: &AppState, checker: &Checker, jar: &PrivateCookieJar, kind: DeliberationKind, body: String) -> (StatusCode, String) {
+    let deliberation_auth_token = match jar.get(&AuthDataType::Deliberation.cookie(&checker.name)) {
         Some(data) => data,
         None => {
-            return (StatusCode::UNAUTHORIZED, "".into());
-        }
-    };
-    let client = reqwest::Client::new();
-    let result = match client
-        .post(format!(
-            "{}/v1/deliberation/execute-task",
-            state.checker_address
-        ))
-        .body(body)
-        .header(
-            "Authorization",
-            format!("Bearer {}", deliberation_auth_token.value()),
-        )
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        }
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
     };
+    let policy_auth_token = jar.get(&AuthDataType::Policy.cookie(&checker.name));
 
-    let status = result.status().as_u16();
-
-    if status > 399 {
-        let body = match result.text().await {
-            Ok(text) => text,
-            Err(_) => "".into(),
-        };
-
-        return (StatusCode::from_u16(status).unwrap(), body);
+    let (result, _) = history::deliberate(state, checker, deliberation_auth_token.value(), policy_auth_token.as_ref().map(|c| c.value()), kind, body, None).await;
+    match result {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
-
-    let body = match result.text().await {
-        Ok(body) => body,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        }
-    };
-
-    (StatusCode::OK, body)
 }
 
-// Access data request
-pub async fn post_access_data(
-    State(state): State<AppState>,
-    jar: PrivateCookieJar,
-    body: String,
-) -> (StatusCode, String) {
-    let deliberation_auth_token = match jar.get("reasoner_deliberation_auth") {
-        Some(data) => data,
-        None => {
-            return (StatusCode::UNAUTHORIZED, "".into());
-        }
-    };
-    println!("Bearer {}", deliberation_auth_token.value());
-    let client = reqwest::Client::new();
-    let result = match client
-        .post(format!(
-            "{}/v1/deliberation/access-data",
-            state.checker_address
-        ))
-        .body(body)
-        .header(
-            "Authorization",
-            format!("Bearer {}", deliberation_auth_token.value()),
-        )
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        }
-    };
 
-    let status = result.status().as_u16();
 
-    if status > 399 {
-        let body = match result.text().await {
-            Ok(text) => text,
-            Err(_) => "".into(),
-        };
 
-        return (StatusCode::from_u16(status).unwrap(), body);
-    }
 
-    let body = match result.text().await {
-        Ok(body) => body,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        }
-    };
+/***** LIBRARY *****/
+//  Exec task request
+pub async fn post_exec_task(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar, body: String) -> (StatusCode, String) {
+    deliberate(&state, &checker, &jar, DeliberationKind::Task, body).await
+}
 
-    (StatusCode::OK, body)
+// Access data request
+pub async fn post_access_data(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar, body: String) -> (StatusCode, String) {
+    deliberate(&state, &checker, &jar, DeliberationKind::Data, body).await
 }
 
 // Validate workflow request
-pub async fn post_validate_workflow(
-    State(state): State<AppState>,
-    jar: PrivateCookieJar,
-    body: String,
-) -> (StatusCode, String) {
-    let deliberation_auth_token = match jar.get("reasoner_deliberation_auth") {
-        Some(data) => data,
-        None => {
-            return (StatusCode::UNAUTHORIZED, "".into());
-        }
-    };
-    let client = reqwest::Client::new();
-    let result = match client
-        .post(format!(
-            "{}/v1/deliberation/execute-workflow",
-            state.checker_address
-        ))
-        .body(body)
-        .header(
-            "Authorization",
-            format!("Bearer {}", deliberation_auth_token.value()),
-        )
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        }
-    };
-
-    let status = result.status().as_u16();
-
-    if status > 399 {
-        let body = match result.text().await {
-            Ok(text) => text,
-            Err(_) => "".into(),
-        };
-
-        return (StatusCode::from_u16(status).unwrap(), body);
-    }
-
-    let body = match result.text().await {
-        Ok(body) => body,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        }
-    };
-
-    (StatusCode::OK, body)
+pub async fn post_validate_workflow(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar, body: String) -> (StatusCode, String) {
+    deliberate(&state, &checker, &jar, DeliberationKind::Workflow, body).await
 }
@@ -1,17 +1,68 @@
 use log::debug;
 
-use std::process::{ExitStatus, Stdio};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt as _};
+use tokio::io::AsyncWriteExt as _;
 use tokio::process::{Child as TChild, ChildStdin as TChildStdin, Command as TCommand};
 
-pub async fn eflint_to_json(input: String) -> Result<String, String> {
-    let bin_path = "./bin/eflint-to-json";
 
-    debug!("Using compiler at: '{}'", bin_path);
+/***** ERRORS *****/
+/// Defines errors originating from running the `eflint-to-json` compiler.
+#[derive(Debug)]
+pub enum EFlintToJsonError {
+    /// Failed to start the compiler.
+    Spawn { path: PathBuf, err: std::io::Error },
+    /// The compiler was started without a handle to its stdin.
+    MissingStdin { path: PathBuf },
+    /// Failed to write the input to the compiler.
+    Write { path: PathBuf, err: std::io::Error },
+    /// Failed to wait for the compiler to finish.
+    Wait { path: PathBuf, err: std::io::Error },
+    /// The compiler rejected the input.
+    Compile { stderr: String },
+    /// The compiler produced output that is not valid UTF-8.
+    Output { err: std::string::FromUtf8Error },
+}
+impl Display for EFlintToJsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use EFlintToJsonError::*;
+        match self {
+            Spawn { path, .. } => write!(f, "Failed to start compiler '{}'", path.display()),
+            MissingStdin { path } => write!(f, "Compiler '{}' was started without stdin", path.display()),
+            Write { path, .. } => write!(f, "Could not write to stdin of compiler '{}'", path.display()),
+            Wait { path, .. } => write!(f, "Failed to wait for compiler '{}' to finish", path.display()),
+            Compile { stderr } => write!(f, "{stderr}"),
+            Output { .. } => write!(f, "Compiler output is not valid UTF-8"),
+        }
+    }
+}
+impl Error for EFlintToJsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use EFlintToJsonError::*;
+        match self {
+            Spawn { err, .. } => Some(err),
+            MissingStdin { .. } => None,
+            Write { err, .. } => Some(err),
+            Wait { err, .. } => Some(err),
+            Compile { .. } => None,
+            Output { err } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+pub async fn eflint_to_json(bin_path: &Path, input: String) -> Result<String, EFlintToJsonError> {
+    debug!("Using compiler at: '{}'", bin_path.display());
 
     // Alrighty well open a handle to the compiler
-    debug!("Spawning compiler '{}'", bin_path);
+    debug!("Spawning compiler '{}'", bin_path.display());
     let mut cmd: TCommand = TCommand::new(bin_path);
     cmd.stdin(Stdio::piped());
     cmd.stdout(Stdio::piped());
@@ -19,50 +70,39 @@ pub async fn eflint_to_json(input: String) -> Result<String, String> {
     let mut handle: TChild = match cmd.spawn() {
         Ok(handle) => handle,
         Err(err) => {
-            return Err(format!("{:?}", err));
+            return Err(EFlintToJsonError::Spawn { path: bin_path.into(), err });
         }
     };
 
     // Feed the input to the compiler, analyzing for `#input(...)` and `#require(...)`
     debug!("Reading input to child process...");
-    let mut stdin: TChildStdin = handle.stdin.take().unwrap();
+    let mut stdin: TChildStdin = match handle.stdin.take() {
+        Some(stdin) => stdin,
+        None => {
+            return Err(EFlintToJsonError::MissingStdin { path: bin_path.into() });
+        }
+    };
     match stdin.write_all(input.as_bytes()).await {
         Ok(_) => (),
         Err(err) => {
-            return Err(format!("Could not write to stdin: {:?}", err));
+            return Err(EFlintToJsonError::Write { path: bin_path.into(), err });
         }
     }
     drop(stdin);
 
-    // Wait until the process is finished
+    // Wait until the process is finished, collecting its output while it runs
     debug!("Waiting for child process to complete...");
-    let status: ExitStatus = match handle.wait().await {
-        Ok(status) => status,
+    let output: Output = match handle.wait_with_output().await {
+        Ok(output) => output,
         Err(err) => {
-            return Err(format!("{:?}", err));
+            return Err(EFlintToJsonError::Wait { path: bin_path.into(), err });
         }
     };
-    if !status.success() {
-        let mut buf: String = String::new();
-        match handle.stderr.take().unwrap().read_to_string(&mut buf).await {
-            Ok(_) => (),
-            Err(err) => {
-                return Err(format!("<failed to read err stream:{:?}>", err));
-            }
-        };
-
-        return Err(format!("{:?}", buf));
+    if !output.status.success() {
+        return Err(EFlintToJsonError::Compile { stderr: String::from_utf8_lossy(&output.stderr).into_owned() });
     }
 
-    // Alrighty, now it's time to stream the output of the child to the output file
-    debug!("Writing child process output to given output...");
-    let mut ret: String = String::new();
-    match handle.stdout.take().unwrap().read_to_string(&mut ret).await {
-        Ok(_) => (),
-        Err(err) => {
-            return Err(format!("<failed to read output stream:{:?}>", err));
-        }
-    };
-
-    Ok(ret)
+    // Alrighty, now it's time to return the output of the child
+    debug!("Returning child process output...");
+    String::from_utf8(output.stdout).map_err(|err| EFlintToJsonError::Output { err })
 }
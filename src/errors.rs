@@ -0,0 +1,263 @@
+//  ERRORS.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 23:07:52
+//  Last edited:
+//    19 Oct 2026, 23:07:52
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Defines the error that every route sends back to the client, so they
+//!   all look the same regardless of what went wrong where.
+//
+
+use std::any::Any;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use axum::body::to_bytes;
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+
+/***** CONSTANTS *****/
+/// The header that carries the ID of a request.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The maximum number of bytes of an error response that is rewritten into an [`ApiError`].
+pub const MAX_ERROR_BODY: usize = 1024 * 1024;
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Generates a new ID for a request that did not bring one.
+///
+/// # Returns
+/// An ID that is unique for as long as the backend runs (and, in practice, across restarts).
+fn new_request_id() -> String {
+    static STARTED: OnceLock<i64> = OnceLock::new();
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    format!("{:x}-{:x}", STARTED.get_or_init(|| Utc::now().timestamp_millis()), NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Decides whether to keep the request ID a client (or proxy in front of us) sent along.
+///
+/// # Arguments
+/// - `id`: The ID that was sent.
+///
+/// # Returns
+/// Whether it is short and tame enough to end up in logs and headers.
+fn valid_request_id(id: &str) -> bool { !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c)) }
+
+/// Extracts the message of a panic.
+///
+/// # Arguments
+/// - `payload`: What the panic was called with.
+///
+/// # Returns
+/// The message of the panic, or a placeholder if it was called with something else than a string.
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match (payload.downcast_ref::<String>(), payload.downcast_ref::<&'static str>()) {
+        (Some(msg), _) => msg.as_str(),
+        (None, Some(msg)) => *msg,
+        (None, None) => "<no message>",
+    }
+}
+
+/// Derives the machine-readable code of an error from its status code.
+///
+/// # Arguments
+/// - `status`: The [`StatusCode`] of the error.
+///
+/// # Returns
+/// The reason phrase of the status code in snake case (e.g., `bad_request`).
+fn default_code(status: StatusCode) -> String {
+    match status.canonical_reason() {
+        Some(reason) => reason.to_lowercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+        None => format!("status_{}", status.as_u16()),
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// The body of every error response of the backend.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiError {
+    /// The status code that is sent along.
+    #[serde(skip)]
+    pub status:          StatusCode,
+    /// What went wrong, for machines (e.g., `unauthorized` or `checker_timeout`).
+    pub code:            String,
+    /// What went wrong, for humans.
+    pub message:         String,
+    /// Why it went wrong, from the immediate cause to the root cause.
+    #[serde(default)]
+    pub causes:          Vec<String>,
+    /// The status code with which the checker responded, if the error came from it.
+    #[serde(default)]
+    pub upstream_status: Option<u16>,
+    /// The number of seconds after which it makes sense to try again, if known.
+    #[serde(default)]
+    pub retry_after:     Option<u64>,
+    /// The ID of the request that failed, to find it back in the logs.
+    #[serde(default)]
+    pub request_id:      Option<String>,
+}
+impl ApiError {
+    /// Constructor for an ApiError without an underlying error.
+    ///
+    /// # Arguments
+    /// - `status`: The [`StatusCode`] to send along.
+    /// - `message`: What went wrong.
+    ///
+    /// # Returns
+    /// A new ApiError with the code derived from the `status`.
+    #[inline]
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, code: default_code(status), message: message.into(), causes: vec![], upstream_status: None, retry_after: None, request_id: None }
+    }
+
+    /// Constructor for an ApiError that reports an error and everything that caused it.
+    ///
+    /// # Arguments
+    /// - `status`: The [`StatusCode`] to send along.
+    /// - `err`: The error to report.
+    ///
+    /// # Returns
+    /// A new ApiError with the `err` as message and its sources as causes.
+    pub fn from_error<E: Error + ?Sized>(status: StatusCode, err: &E) -> Self {
+        let mut causes: Vec<String> = vec![];
+        let mut source: Option<&(dyn Error + 'static)> = err.source();
+        while let Some(err) = source {
+            causes.push(err.to_string());
+            source = err.source();
+        }
+        Self { causes, ..Self::new(status, err.to_string()) }
+    }
+
+    /// Reconstructs an ApiError from the body of an error response.
+    ///
+    /// # Arguments
+    /// - `status`: The [`StatusCode`] of the response.
+    /// - `body`: The body of the response, which is either an ApiError already or a plain message.
+    ///
+    /// # Returns
+    /// The ApiError that the response should have been.
+    pub fn parse(status: StatusCode, body: &str) -> Self {
+        if let Ok(err) = serde_json::from_str::<Self>(body) {
+            return Self { status, ..err };
+        }
+        let message: &str = body.trim();
+        if message.is_empty() { Self::new(status, status.canonical_reason().unwrap_or("Unknown error")) } else { Self::new(status, message) }
+    }
+
+    /// Replaces the machine-readable code of this error.
+    #[inline]
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = code.into();
+        self
+    }
+
+    /// Puts this error in context, making the current message the first cause.
+    ///
+    /// # Arguments
+    /// - `message`: What was being done when this error occurred.
+    #[inline]
+    pub fn context(mut self, message: impl Into<String>) -> Self {
+        self.causes.insert(0, std::mem::replace(&mut self.message, message.into()));
+        self
+    }
+
+    /// Converts this error into what the handlers return.
+    ///
+    /// # Returns
+    /// The status code and the serialized error.
+    #[inline]
+    pub fn into_parts(self) -> (StatusCode, String) {
+        let body: String = serde_json::to_string(&self).unwrap_or_else(|_| self.message.clone());
+        (self.status, body)
+    }
+}
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body): (StatusCode, String) = self.into_parts();
+        (status, [(CONTENT_TYPE, HeaderValue::from_static("application/json"))], body).into_response()
+    }
+}
+
+
+
+/// Responds to a request whose handler panicked, as a last resort to keep that from taking down the connection.
+///
+/// # Arguments
+/// - `payload`: What the panic was called with.
+///
+/// # Returns
+/// An [`ApiError`] that does not reveal the details of the panic to the client.
+pub fn report_panic(payload: Box<dyn Any + Send + 'static>) -> Response {
+    error!("Handler panicked: {}", panic_message(&*payload));
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "The request could not be handled because of a bug in the backend")
+        .with_code("internal_panic")
+        .into_response()
+}
+
+/// Makes every error response an [`ApiError`] that carries the ID of the request, whichever handler (or extractor) produced it.
+///
+/// The ID is taken from the request's `X-Request-ID` header if it has one, and is sent back in that of the response.
+///
+/// # Arguments
+/// - `req`: The request to handle.
+/// - `next`: The rest of the stack that handles it.
+///
+/// # Returns
+/// The response, with error bodies rewritten.
+pub async fn report_errors(req: Request, next: Next) -> Response {
+    let request_id: String = match req.headers().get(&REQUEST_ID_HEADER).and_then(|id| id.to_str().ok()) {
+        Some(id) if valid_request_id(id) => id.into(),
+        _ => new_request_id(),
+    };
+
+    let (mut parts, body) = next.run(req).await.into_parts();
+    if let Ok(id) = HeaderValue::from_str(&request_id) {
+        parts.headers.insert(REQUEST_ID_HEADER, id);
+    }
+    if !parts.status.is_client_error() && !parts.status.is_server_error() {
+        return Response::from_parts(parts, body);
+    }
+
+    // Rewrite the body, keeping everything else
+    let body: String = match to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+        Err(err) => {
+            warn!("Failed to read body of error response to request {request_id}: {err}");
+            String::new()
+        },
+    };
+    let mut err: ApiError = ApiError::parse(parts.status, &body);
+    if err.status.is_server_error() {
+        warn!("Request {request_id} failed with {} ({}): {}", err.status, err.code, err.message);
+    } else {
+        info!("Request {request_id} failed with {} ({}): {}", err.status, err.code, err.message);
+    }
+    err.request_id = Some(request_id);
+    let mut res: Response = err.into_response();
+    parts.headers.remove(CONTENT_TYPE);
+    parts.headers.remove(CONTENT_LENGTH);
+    res.headers_mut().extend(parts.headers);
+    res.extensions_mut().extend(parts.extensions);
+    res
+}
@@ -0,0 +1,236 @@
+//  EVENTS.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 22:41:19
+//  Last edited:
+//    19 Oct 2026, 22:41:19
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements pushing changes to the checkers' policies to all connected
+//!   clients, so they do not keep showing stale state.
+//
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::Mutex;
+
+use crate::auth::{has_any_token, jwt_subject, AppState, AuthDataType};
+use crate::checker::{CheckerError, CheckerQuery, CheckerResponse};
+
+
+/***** CONSTANTS *****/
+/// The number of events kept for clients that are slow to receive them.
+pub const EVENT_BUFFER: usize = 64;
+
+
+
+
+
+/***** AUXILLARY *****/
+/// The kinds of changes to a checker's policies.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyEventKind {
+    /// A new policy version was added.
+    Added,
+    /// A policy version was activated.
+    Activated,
+    /// The active policy was deactivated.
+    Deactivated,
+}
+
+/// How we found out about a change.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventOrigin {
+    /// The change went through this backend.
+    Backend,
+    /// The change was made elsewhere, and detected by probing the checker.
+    Detected,
+}
+
+/// A change to a checker's policies.
+#[derive(Clone, Debug, Serialize)]
+pub struct PolicyEvent {
+    /// The number of this event, counting from 1 since the backend started.
+    pub id:      u64,
+    /// When the change was made (or detected).
+    pub at:      DateTime<Utc>,
+    /// The name of the checker whose policies changed.
+    pub checker: String,
+    /// What changed.
+    pub kind:    PolicyEventKind,
+    /// The policy version added or activated, if any.
+    pub version: Option<i64>,
+    /// How we found out about the change.
+    pub origin:  EventOrigin,
+    /// The user who made the change, if known.
+    pub user:    Option<String>,
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Broadcasts changes to the checkers' policies to everyone listening.
+#[derive(Debug)]
+pub struct EventBus {
+    /// The sending end of the broadcast channel.
+    sender:  Sender<PolicyEvent>,
+    /// The number of the next event.
+    next_id: AtomicU64,
+    /// The active policy version per checker, as far as we know, to detect changes made elsewhere.
+    active:  Mutex<HashMap<String, Option<i64>>>,
+}
+impl Default for EventBus {
+    #[inline]
+    fn default() -> Self { Self::new() }
+}
+impl EventBus {
+    /// Constructor for the EventBus.
+    ///
+    /// # Returns
+    /// A new EventBus that nobody listens to yet.
+    #[inline]
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender, next_id: AtomicU64::new(1), active: Mutex::new(HashMap::new()) }
+    }
+
+    /// Starts listening to events.
+    ///
+    /// # Returns
+    /// A [`Receiver`] that receives all events published from now on.
+    #[inline]
+    pub fn subscribe(&self) -> Receiver<PolicyEvent> { self.sender.subscribe() }
+
+    /// Sends an event to everyone listening.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker whose policies changed.
+    /// - `kind`: What changed.
+    /// - `version`: The policy version added or activated, if any.
+    /// - `origin`: How we found out about the change.
+    /// - `user`: The user who made the change, if known.
+    fn publish(&self, checker: &str, kind: PolicyEventKind, version: Option<i64>, origin: EventOrigin, user: Option<String>) {
+        let event: PolicyEvent = PolicyEvent {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            at: Utc::now(),
+            checker: checker.into(),
+            kind,
+            version,
+            origin,
+            user,
+        };
+        debug!("Publishing event {} ({:?} version {:?} on checker '{}')", event.id, event.kind, event.version, event.checker);
+        // Nobody listening is fine
+        let _ = self.sender.send(event);
+    }
+
+    /// Publishes a change that went through this backend, if it succeeded.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker whose policies changed.
+    /// - `kind`: What changed.
+    /// - `version`: The policy version added or activated, if any.
+    /// - `token`: The JWT of the user who made the change, if known.
+    /// - `res`: The result of the checker call that made the change.
+    pub async fn changed(&self, checker: &str, kind: PolicyEventKind, version: Option<i64>, token: Option<&str>, res: &Result<CheckerResponse, CheckerError>) {
+        if res.is_err() {
+            return;
+        }
+        // Remember the change first, so probing the checker does not report it again
+        match kind {
+            PolicyEventKind::Added => {},
+            PolicyEventKind::Activated => {
+                self.active.lock().await.insert(checker.into(), version);
+            },
+            PolicyEventKind::Deactivated => {
+                self.active.lock().await.insert(checker.into(), None);
+            },
+        }
+        self.publish(checker, kind, version, EventOrigin::Backend, token.and_then(jwt_subject));
+    }
+
+    /// Tells the bus which policy a checker has active, publishing a change if it differs from what we knew.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker that was probed.
+    /// - `active`: The policy version active on the checker, if any.
+    pub async fn observe(&self, checker: &str, active: Option<i64>) {
+        let previous: Option<Option<i64>> = self.active.lock().await.insert(checker.into(), active);
+        match (previous, active) {
+            // The first time, there is nothing to compare with
+            (None, _) => {},
+            (Some(previous), active) if previous == active => {},
+            (Some(_), Some(version)) => {
+                info!("Detected that policy version {version} was activated on checker '{checker}'");
+                self.publish(checker, PolicyEventKind::Activated, Some(version), EventOrigin::Detected, None);
+            },
+            (Some(_), None) => {
+                info!("Detected that the policy on checker '{checker}' was deactivated");
+                self.publish(checker, PolicyEventKind::Deactivated, None, EventOrigin::Detected, None);
+            },
+        }
+    }
+}
+
+
+
+// Stream changes to the checkers' policies
+pub async fn get_events(
+    State(state): State<AppState>,
+    jar: PrivateCookieJar,
+    Query(query): Query<CheckerQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    if !has_any_token(&jar, &state, AuthDataType::Policy) && !has_any_token(&jar, &state, AuthDataType::Deliberation) {
+        return Err(err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into())));
+    }
+    if let Some(name) = &query.checker {
+        if let Err(err) = state.checkers.get(Some(name)) {
+            return Err(checker_err!(err));
+        }
+    }
+
+    let events = stream::unfold((state.events.subscribe(), query.checker), |(mut rx, checker)| async move {
+        loop {
+            let event: Event = match rx.recv().await {
+                Ok(event) if checker.as_ref().map_or(true, |checker| *checker == event.checker) => {
+                    match Event::default().id(event.id.to_string()).event("policy").json_data(&event) {
+                        Ok(sse) => sse,
+                        Err(err) => {
+                            error!("Failed to serialize event {}: {err}", event.id);
+                            continue;
+                        },
+                    }
+                },
+                Ok(_) => continue,
+                // Tell the client it missed something, so it can refresh everything
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Client missed {missed} event(s)");
+                    Event::default().event("lagged").data(missed.to_string())
+                },
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok::<Event, Infallible>(event), (rx, checker)));
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,230 @@
+//  FEDERATED.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 20:12:33
+//  Last edited:
+//    19 Oct 2026, 20:12:33
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements asking every domain's checker about the steps of a
+//!   workflow planned on that domain, like Brane does at run time.
+//
+
+use std::collections::BTreeMap;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
+use futures::future::join_all;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::{has_any_token, AppState, AuthDataType};
+use crate::batch::{ask, prepare_workflow, BatchCell, BatchSummary, DataCell, Question, TaskRow, DEFAULT_CONCURRENCY, DEFAULT_USE_CASE, MAX_CONCURRENCY};
+use crate::bstowir::{bs_to_wir, AnalyzedWir};
+use crate::checker::{Checker, DeliberationKind};
+use crate::conv::CodeFormat;
+
+
+/***** AUXILLARY *****/
+/// The options of a federated deliberation.
+#[derive(Debug, Deserialize)]
+pub struct FederatedQuery {
+    /// The format of the workflow sent; either `wir` (the default) or `branescript`.
+    pub from:     Option<CodeFormat>,
+    /// The use-case to ask the questions in.
+    pub use_case: Option<String>,
+    /// The user to ask the questions as. If omitted, the workflow's own user is used.
+    pub user:     Option<String>,
+    /// The maximum number of requests sent to each checker at the same time. Capped at [`MAX_CONCURRENCY`].
+    pub limit:    Option<usize>,
+}
+
+/// The verdicts of a single domain about the task calls planned on it.
+#[derive(Clone, Debug, Serialize)]
+pub struct DomainReport {
+    /// The location the task calls are planned on.
+    pub location: String,
+    /// The name of the checker that was asked, or [`None`] if there is no checker for this location.
+    pub checker:  Option<String>,
+    /// Why the checker could not be asked, if it couldn't.
+    pub problem:  Option<String>,
+    /// The verdicts for every task call planned on this location.
+    pub tasks:    Vec<TaskRow>,
+    /// The counts of all verdicts.
+    pub summary:  BatchSummary,
+    /// Whether this domain allows everything asked of it.
+    pub ok:       bool,
+}
+
+/// The verdicts of all domains involved in a workflow.
+#[derive(Clone, Debug, Serialize)]
+pub struct FederatedReport {
+    /// Whether every domain allows everything asked of it (and every task call is planned).
+    pub ok:        bool,
+    /// The verdicts per domain, ordered by location.
+    pub domains:   Vec<DomainReport>,
+    /// The task calls that are not planned on any location, and so cannot be asked about.
+    pub unplanned: Vec<TaskRow>,
+    /// The counts of all verdicts over all domains.
+    pub summary:   BatchSummary,
+}
+
+
+
+/// What is asked of every domain.
+#[derive(Clone, Copy, Debug)]
+struct Deliberation<'a> {
+    /// The [`AnalyzedWir`] with the task calls.
+    wir:      &'a AnalyzedWir,
+    /// The workflow as it is sent to the checkers.
+    workflow: &'a Value,
+    /// The use-case to ask the questions in.
+    use_case: &'a str,
+    /// The maximum number of requests sent to each checker at the same time. Capped at [`MAX_CONCURRENCY`].
+    limit:    usize,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Asks a single domain's checker about the task calls planned on it.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the history to record in.
+/// - `jar`: The cookies of the user, holding their tokens.
+/// - `checker`: The [`Checker`] of the domain, or [`None`] if there is none.
+/// - `location`: The location of the domain.
+/// - `indices`: The indices of the (reachable) task calls planned on this domain.
+/// - `delib`: What to ask.
+///
+/// # Returns
+/// The [`DomainReport`] of this domain.
+async fn ask_domain(
+    state: &AppState,
+    jar: &PrivateCookieJar,
+    checker: Option<&Checker>,
+    location: String,
+    indices: Vec<usize>,
+    delib: Deliberation<'_>,
+) -> DomainReport {
+    let Deliberation { wir, workflow, use_case, limit } = delib;
+    let mut rows: Vec<TaskRow> = indices.iter().map(|i| TaskRow::new(&wir.tasks[*i], None)).collect();
+    let unasked = |rows: Vec<TaskRow>, checker: Option<String>, problem: String| {
+        let rows: Vec<TaskRow> = rows
+            .into_iter()
+            .map(|mut row| {
+                row.execute.response = problem.clone();
+                row
+            })
+            .collect();
+        DomainReport { location: location.clone(), checker, problem: Some(problem), tasks: rows, summary: BatchSummary::default(), ok: false }
+    };
+
+    // Find out who to ask, and with what
+    let checker: &Checker = match checker {
+        Some(checker) => checker,
+        None => return unasked(rows, None, format!("There is no checker for location '{location}'")),
+    };
+    let token: String = match jar.get(&AuthDataType::Deliberation.cookie(&checker.name)) {
+        Some(token) => token.value().into(),
+        None => return unasked(rows, Some(checker.name.clone()), format!("Not logged in to checker '{}'", checker.name)),
+    };
+    let policy_token: Option<String> = jar.get(&AuthDataType::Policy.cookie(&checker.name)).map(|c| c.value().into());
+
+    // Ask it
+    let mut questions: Vec<(Question, DeliberationKind, String)> = vec![];
+    for i in &indices {
+        let task_questions = std::iter::once(Question::Execute(*i)).chain(wir.tasks[*i].datasets.iter().map(|d| Question::Access(*i, d.clone())));
+        for question in task_questions {
+            let (kind, req) = question.request(&wir.tasks, workflow, use_case);
+            questions.push((question, kind, req));
+        }
+    }
+    debug!("Asking checker '{}' {} question(s) about location '{}'", checker.name, questions.len(), location);
+    let answers: Vec<(Question, BatchCell)> = ask(state, checker, &token, policy_token.as_deref(), questions, limit).await;
+
+    // Fill in the rows
+    let row = |i: usize| indices.iter().position(|j| *j == i).unwrap_or_default();
+    let mut summary: BatchSummary = BatchSummary::default();
+    for (question, cell) in answers {
+        summary.count(&cell);
+        match question {
+            Question::Execute(i) => rows[row(i)].execute = cell,
+            Question::Access(i, dataset) => rows[row(i)].datasets.push(DataCell { dataset, cell }),
+            Question::Workflow | Question::Result(_) => {},
+        }
+    }
+    DomainReport { location, checker: Some(checker.name.clone()), problem: None, tasks: rows, summary, ok: summary.denied == 0 && summary.errors == 0 }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+// Ask every domain about the task calls planned on it
+pub async fn post_federated(State(state): State<AppState>, jar: PrivateCookieJar, Query(query): Query<FederatedQuery>, body: String) -> (StatusCode, String) {
+    if !has_any_token(&jar, &state, AuthDataType::Deliberation) {
+        return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+    }
+
+    // Get the workflow in shape
+    let body: String = match query.from {
+        None | Some(CodeFormat::WIR) => body,
+        Some(CodeFormat::BraneScript) => match bs_to_wir(state.conv.packages.clone(), state.conv.data.clone(), body).await {
+            Ok(wir) => wir,
+            Err(err) => return err.into_parts(),
+        },
+        Some(from) => return err!(StatusCode::BAD_REQUEST, StringError(format!("Cannot deliberate about workflows given as {from:?}"))),
+    };
+    let wir: AnalyzedWir = match serde_json::from_str(&body) {
+        Ok(wir) => wir,
+        Err(err) => return err!(StatusCode::BAD_REQUEST, err),
+    };
+    let workflow: Value = match prepare_workflow(&wir, query.user.as_deref()) {
+        Ok(workflow) => workflow,
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    let use_case: String = query.use_case.unwrap_or_else(|| DEFAULT_USE_CASE.into());
+    let limit: usize = query.limit.unwrap_or(DEFAULT_CONCURRENCY).clamp(1, MAX_CONCURRENCY);
+    let delib: Deliberation = Deliberation { wir: &wir, workflow: &workflow, use_case: &use_case, limit };
+
+    // Group the task calls by where they are planned
+    let mut locations: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut unplanned: Vec<TaskRow> = vec![];
+    for (i, task) in wir.tasks.iter().enumerate() {
+        match (&task.at, task.reachable) {
+            (_, false) => continue,
+            (Some(at), true) => locations.entry(at.clone()).or_default().push(i),
+            (None, true) => unplanned.push(TaskRow::new(task, Some("Not asked, as the task is not planned on any location"))),
+        }
+    }
+    debug!("Asking {} domain(s) about workflow '{}'", locations.len(), wir.workflow.id);
+
+    // Ask every domain at the same time
+    let domains: Vec<DomainReport> = join_all(locations.into_iter().map(|(location, indices)| {
+        let checker: Option<&Checker> = state.checkers.get(Some(&location)).ok();
+        ask_domain(&state, &jar, checker, location, indices, delib)
+    }))
+    .await;
+    let mut summary: BatchSummary = BatchSummary::default();
+    for domain in &domains {
+        summary.allowed += domain.summary.allowed;
+        summary.denied += domain.summary.denied;
+        summary.errors += domain.summary.errors;
+    }
+    let report: FederatedReport = FederatedReport { ok: unplanned.is_empty() && domains.iter().all(|d| d.ok), domains, unplanned, summary };
+
+    match serde_json::to_string(&report) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
@@ -0,0 +1,266 @@
+//  HEALTH.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 21:58:04
+//  Last edited:
+//    19 Oct 2026, 21:58:04
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements probing the checkers in the background, so the backend
+//!   (and the client) know whether they are up without having to wait for
+//!   a user's request to fail.
+//
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::auth::AppState;
+use crate::checker::{Checker, CheckerError, CheckerQuery, CheckerResponse};
+
+
+/***** AUXILLARY *****/
+/// The part of the reasoner connector context we are interested in.
+#[derive(Clone, Debug, Deserialize)]
+struct ContextModel {
+    /// The hash of the reasoner connector's context.
+    hash: String,
+}
+
+
+
+/// The result of probing a checker once.
+#[derive(Clone, Debug, Serialize)]
+pub struct Probe {
+    /// When the checker was probed.
+    pub at:             DateTime<Utc>,
+    /// Whether the checker responded at all.
+    pub reachable:      bool,
+    /// The number of milliseconds it took the checker to respond, if it did.
+    pub latency_ms:     Option<u64>,
+    /// The hash of the reasoner connector's context, if we are allowed to see it.
+    pub context_hash:   Option<String>,
+    /// The version of the active policy, if any and if we are allowed to see it.
+    pub active_version: Option<i64>,
+    /// Whether we could see which policy is active, i.e., whether `active_version` means anything.
+    pub policy_checked: bool,
+    /// What went wrong, if anything.
+    pub error:          Option<String>,
+}
+
+/// What is known about the health of a checker.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckerStatus {
+    /// The name of the checker.
+    pub checker:      String,
+    /// Whether requests to the checker currently fail without calling it, because it failed too often.
+    pub circuit_open: bool,
+    /// Since when the checker is reachable (or not), as far as we know.
+    pub since:        Option<DateTime<Utc>>,
+    /// The most recent probe, if the checker was probed already.
+    pub latest:       Option<Probe>,
+    /// The earlier probes, most recent first.
+    pub history:      Vec<Probe>,
+}
+
+/// The health of the backend itself.
+#[derive(Clone, Debug, Serialize)]
+pub struct Health {
+    /// Whether the backend is running, which it always is if it can respond.
+    pub live:       bool,
+    /// Whether the backend can serve requests, i.e., whether at least one checker was reachable when last probed.
+    pub ready:      bool,
+    /// When the backend was started.
+    pub started_at: DateTime<Utc>,
+    /// Whether each checker was reachable when last probed ([`None`] if it wasn't probed yet).
+    pub checkers:   HashMap<String, Option<bool>>,
+}
+
+/// The options of a health check.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct HealthQuery {
+    /// If `true`, respond with `503 Service Unavailable` if the backend is not ready (e.g., for readiness probes of an orchestrator).
+    #[serde(default)]
+    pub ready: bool,
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Probes the checkers and remembers the results.
+#[derive(Debug)]
+pub struct HealthMonitor {
+    /// The management tokens with which the checkers are probed, by checker name. Checkers without one are only checked for reachability.
+    tokens:     HashMap<String, String>,
+    /// The maximum number of probes remembered per checker.
+    capacity:   usize,
+    /// When the backend was started.
+    started_at: DateTime<Utc>,
+    /// The probes so far per checker, most recent first.
+    probes:     Mutex<HashMap<String, VecDeque<Probe>>>,
+}
+impl HealthMonitor {
+    /// Constructor for the HealthMonitor.
+    ///
+    /// # Arguments
+    /// - `tokens`: The management tokens with which the checkers are probed, by checker name.
+    /// - `capacity`: The maximum number of probes remembered per checker.
+    ///
+    /// # Returns
+    /// A new HealthMonitor that has not probed anything yet.
+    #[inline]
+    pub fn new(tokens: HashMap<String, String>, capacity: usize) -> Self {
+        Self { tokens, capacity: capacity.max(1), started_at: Utc::now(), probes: Mutex::new(HashMap::new()) }
+    }
+
+    /// Probes a checker: whether it responds, with which reasoner connector and which active policy.
+    ///
+    /// # Arguments
+    /// - `checker`: The [`Checker`] to probe.
+    ///
+    /// # Returns
+    /// The [`Probe`] with the results.
+    pub async fn probe(&self, checker: &Checker) -> Probe {
+        let at: DateTime<Utc> = Utc::now();
+        let start: Instant = Instant::now();
+        let token: Option<&str> = self.tokens.get(&checker.name).map(String::as_str);
+
+        // Without a token, the checker refusing us still tells us that it is up
+        let res: Result<CheckerResponse, CheckerError> = checker.get_connector_info(token.unwrap_or_default()).await;
+        let (reachable, context_hash, mut error): (bool, Option<String>, Option<String>) = match res {
+            Ok(res) => match serde_json::from_str::<ContextModel>(&res.body) {
+                Ok(context) => (true, Some(context.hash), None),
+                Err(err) => (true, None, Some(format!("Failed to parse reasoner connector context: {err}"))),
+            },
+            Err(err @ CheckerError::Status { .. }) if !err.is_outage() => (true, None, token.map(|_| err.to_string())),
+            Err(err) => (false, None, Some(err.to_string())),
+        };
+        let latency_ms: Option<u64> = reachable.then(|| u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX));
+
+        // Find out which policy is active, if we may
+        let (mut active_version, mut policy_checked): (Option<i64>, bool) = (None, false);
+        if let (true, Some(token)) = (reachable, token) {
+            match checker.get_active_version(token).await {
+                Ok(version) => (active_version, policy_checked) = (version, true),
+                Err(err) => error = error.or_else(|| Some(err.to_string())),
+            }
+        }
+
+        Probe { at, reachable, latency_ms, context_hash, active_version, policy_checked, error }
+    }
+
+    /// Remembers the result of probing a checker.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker that was probed.
+    /// - `probe`: The [`Probe`] to remember.
+    pub async fn record(&self, checker: &str, probe: Probe) {
+        let mut probes = self.probes.lock().await;
+        let history: &mut VecDeque<Probe> = probes.entry(checker.into()).or_default();
+        match history.front() {
+            Some(prev) if prev.reachable && !probe.reachable => {
+                warn!("Checker '{checker}' is unreachable: {}", probe.error.as_deref().unwrap_or("unknown error"))
+            },
+            Some(prev) if !prev.reachable && probe.reachable => info!("Checker '{checker}' is reachable again"),
+            None if !probe.reachable => warn!("Checker '{checker}' is unreachable: {}", probe.error.as_deref().unwrap_or("unknown error")),
+            _ => debug!("Probed checker '{checker}' (reachable: {})", probe.reachable),
+        }
+        history.push_front(probe);
+        history.truncate(self.capacity);
+    }
+
+    /// Returns what is known about the health of a checker.
+    ///
+    /// # Arguments
+    /// - `checker`: The [`Checker`] to report on.
+    ///
+    /// # Returns
+    /// The [`CheckerStatus`] of the checker.
+    pub async fn status(&self, checker: &Checker) -> CheckerStatus {
+        let mut history: Vec<Probe> = self.probes.lock().await.get(&checker.name).map(|h| h.iter().cloned().collect()).unwrap_or_default();
+        let latest: Option<Probe> = if history.is_empty() { None } else { Some(history.remove(0)) };
+        let since: Option<DateTime<Utc>> =
+            latest.as_ref().map(|latest| history.iter().take_while(|p| p.reachable == latest.reachable).last().map(|p| p.at).unwrap_or(latest.at));
+        CheckerStatus { checker: checker.name.clone(), circuit_open: checker.is_down().await, since, latest, history }
+    }
+
+    /// Returns the health of the backend itself.
+    ///
+    /// # Arguments
+    /// - `checkers`: The names of all checkers.
+    ///
+    /// # Returns
+    /// The [`Health`] of the backend.
+    pub async fn health<'a>(&self, checkers: impl IntoIterator<Item = &'a str>) -> Health {
+        let probes = self.probes.lock().await;
+        let checkers: HashMap<String, Option<bool>> =
+            checkers.into_iter().map(|name| (name.to_string(), probes.get(name).and_then(|h| h.front()).map(|p| p.reachable))).collect();
+        Health { live: true, ready: checkers.values().any(|up| *up == Some(true)), started_at: self.started_at, checkers }
+    }
+}
+
+
+
+/// Probes all checkers in the background, every so often.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the checkers to probe, the monitor to report to and the events to publish changes to.
+/// - `interval`: How often to probe the checkers. Anything shorter than a second is taken as a second.
+pub async fn run_probe(state: AppState, interval: Duration) {
+    let interval: Duration = interval.max(Duration::from_secs(1));
+    debug!("Starting checker probe (interval: {}s)", interval.as_secs_f32());
+    let state: &AppState = &state;
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        join_all(state.checkers.iter().map(|checker| async move {
+            let probe: Probe = state.health.probe(checker).await;
+            // Changes to the active policy made elsewhere are only noticed here
+            if probe.policy_checked {
+                state.events.observe(&checker.name, probe.active_version).await;
+            }
+            state.health.record(&checker.name, probe).await;
+        }))
+        .await;
+    }
+}
+
+
+
+// Report whether the backend is alive and ready
+pub async fn get_health(State(state): State<AppState>, Query(query): Query<HealthQuery>) -> (StatusCode, String) {
+    let health: Health = state.health.health(state.checkers.iter().map(|c| c.name.as_str())).await;
+    let code: StatusCode = if query.ready && !health.ready { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+    match serde_json::to_string(&health) {
+        Ok(body) => (code, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+// Report the health of the checker(s)
+pub async fn get_checker_status(State(state): State<AppState>, Query(query): Query<CheckerQuery>) -> (StatusCode, String) {
+    let checkers: Vec<&Checker> = match query.checker.as_deref() {
+        Some(name) => match state.checkers.get(Some(name)) {
+            Ok(checker) => vec![checker],
+            Err(err) => return checker_err!(err),
+        },
+        None => state.checkers.iter().collect(),
+    };
+    let statuses: Vec<CheckerStatus> = join_all(checkers.into_iter().map(|checker| state.health.status(checker))).await;
+    match serde_json::to_string(&statuses) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
@@ -0,0 +1,444 @@
+//  HISTORY.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 12:04:19
+//  Last edited:
+//    19 Oct 2026, 12:04:19
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements the history of deliberation requests sent through the
+//!   backend, and replaying them against the current or another policy.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
+use chrono::{DateTime, Utc};
+use error_trace::ErrorTrace as _;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+
+use crate::auth::{jwt_subject, verified_manager, AppState, AuthDataType};
+use crate::checker::{Checker, CheckerError, CheckerResponse, DeliberationKind, SelectedChecker, DEFAULT_CHECKER};
+use crate::verdict::Verdict;
+
+
+/***** ERRORS *****/
+/// Defines errors originating from the deliberation history.
+#[derive(Debug)]
+pub enum HistoryError {
+    /// Failed to read the history file.
+    Read { path: PathBuf, err: std::io::Error },
+    /// Failed to serialize a record.
+    Serialize { err: serde_json::Error },
+    /// Failed to append to the history file.
+    Write { path: PathBuf, err: std::io::Error },
+}
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use HistoryError::*;
+        match self {
+            Read { path, .. } => write!(f, "Failed to read deliberation history '{}'", path.display()),
+            Serialize { .. } => write!(f, "Failed to serialize deliberation history record"),
+            Write { path, .. } => write!(f, "Failed to append to deliberation history '{}'", path.display()),
+        }
+    }
+}
+impl Error for HistoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use HistoryError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            Serialize { err } => Some(err),
+            Write { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// A deliberation request as it was sent to the checker, together with its outcome.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeliberationRecord {
+    /// The identifier of this record.
+    pub id: u64,
+    /// When the request was sent.
+    pub timestamp: DateTime<Utc>,
+    /// The subject of the JWT of the user sending the request, if known.
+    pub subject: Option<String>,
+    /// The name of the checker that was asked. Missing for records from before there were multiple checkers.
+    #[serde(default)]
+    pub checker: Option<String>,
+    /// The kind of question asked.
+    pub kind: DeliberationKind,
+    /// The workflow asked about.
+    pub workflow: Value,
+    /// The task asked about, if any.
+    pub task_id: Option<Value>,
+    /// The dataset asked about, if any.
+    pub data_id: Option<String>,
+    /// The use-case the question was asked in, if any.
+    pub use_case: Option<String>,
+    /// The status code of the checker's response, if it responded.
+    pub status: Option<u16>,
+    /// The verdict of the checker, if it gave one.
+    pub verdict: Option<String>,
+    /// The body of the checker's response (or the reason there is none).
+    pub response: String,
+    /// The version of the policy that was active at the time, if known.
+    ///
+    /// This is only known if the user also provided a management token.
+    pub policy_version: Option<i64>,
+    /// How long the checker took to respond, in milliseconds.
+    pub latency_ms: u64,
+    /// If this request was a replay, the record that was replayed.
+    pub replay_of: Option<u64>,
+}
+impl DeliberationRecord {
+    /// Rebuilds the request that was sent to the checker.
+    pub fn request(&self) -> Value {
+        let mut req: Map<String, Value> = Map::new();
+        if let Some(use_case) = &self.use_case {
+            req.insert("use_case".into(), Value::String(use_case.clone()));
+        }
+        req.insert("workflow".into(), self.workflow.clone());
+        if let Some(task_id) = &self.task_id {
+            req.insert("task_id".into(), task_id.clone());
+        }
+        if let Some(data_id) = &self.data_id {
+            req.insert("data_id".into(), Value::String(data_id.clone()));
+        }
+        Value::Object(req)
+    }
+}
+
+/// The result of replaying a [`DeliberationRecord`].
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayResult {
+    /// The record that was replayed.
+    pub original: DeliberationRecord,
+    /// The record of the replay.
+    pub replay:   DeliberationRecord,
+    /// Whether the verdict changed.
+    pub changed:  bool,
+}
+
+
+
+/// The filters with which the history can be queried.
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// Only return records of this kind.
+    pub kind:    Option<DeliberationKind>,
+    /// Only return records of questions asked to this checker.
+    pub checker: Option<String>,
+    /// Return at most this many records (the newest ones).
+    pub limit:   Option<usize>,
+}
+
+/// The options for replaying a record.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReplayQuery {
+    /// The policy version to replay against, which must be the active one. If omitted, whatever policy is active is used.
+    pub version: Option<i64>,
+    /// The selected checker, which must be the one that answered the record. If omitted, that checker is used.
+    pub checker: Option<String>,
+}
+
+
+
+/// Implements the (append-only) deliberation history.
+#[derive(Debug)]
+pub struct HistoryStore {
+    /// The JSON Lines file to which records are appended.
+    path:    PathBuf,
+    /// The identifier of the next record. Also serializes writes to the file.
+    next_id: Mutex<u64>,
+}
+impl HistoryStore {
+    /// Constructor for the HistoryStore that picks up where an existing history file left off.
+    ///
+    /// # Arguments
+    /// - `path`: The JSON Lines file to which records are appended.
+    ///
+    /// # Returns
+    /// A new HistoryStore.
+    ///
+    /// # Errors
+    /// This function errors if the history file exists but could not be read.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, HistoryError> {
+        let path: PathBuf = path.into();
+        let raw: String = match std::fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(HistoryError::Read { path, err }),
+        };
+        let next_id: u64 = raw.lines().rev().find_map(|line| serde_json::from_str::<DeliberationRecord>(line).ok()).map_or(0, |r| r.id + 1);
+        debug!("Opened deliberation history '{}' (next record: {})", path.display(), next_id);
+        Ok(Self { path, next_id: Mutex::new(next_id) })
+    }
+
+    /// Appends a record to the history, assigning it its identifier.
+    ///
+    /// # Errors
+    /// This function errors if we failed to write the record.
+    pub async fn append(&self, mut record: DeliberationRecord) -> Result<DeliberationRecord, HistoryError> {
+        let mut next_id = self.next_id.lock().await;
+        record.id = *next_id;
+
+        let mut line: String = serde_json::to_string(&record).map_err(|err| HistoryError::Serialize { err })?;
+        line.push('\n');
+        let mut handle = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|err| HistoryError::Write { path: self.path.clone(), err })?;
+        handle.write_all(line.as_bytes()).map_err(|err| HistoryError::Write { path: self.path.clone(), err })?;
+
+        *next_id += 1;
+        Ok(record)
+    }
+
+    /// Reads all records matching the given query.
+    ///
+    /// # Errors
+    /// This function errors if we failed to read the history.
+    pub async fn query(&self, query: &HistoryQuery) -> Result<Vec<DeliberationRecord>, HistoryError> {
+        let mut records: Vec<DeliberationRecord> = self
+            .read()
+            .await?
+            .filter(|r| query.kind.map_or(true, |kind| r.kind == kind))
+            .filter(|r| query.checker.as_ref().map_or(true, |checker| r.checker.as_deref().unwrap_or(DEFAULT_CHECKER) == checker))
+            .collect();
+        if let Some(limit) = query.limit {
+            records.drain(..records.len().saturating_sub(limit));
+        }
+        Ok(records)
+    }
+
+    /// Finds the record with the given identifier.
+    ///
+    /// # Errors
+    /// This function errors if we failed to read the history.
+    pub async fn get(&self, id: u64) -> Result<Option<DeliberationRecord>, HistoryError> { Ok(self.read().await?.find(|r| r.id == id)) }
+
+    /// Reads all records in the history.
+    async fn read(&self) -> Result<impl Iterator<Item = DeliberationRecord>, HistoryError> {
+        // Lock to not read half-written lines
+        let _next_id = self.next_id.lock().await;
+        let raw: String = match std::fs::read_to_string(&self.path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(HistoryError::Read { path: self.path.clone(), err }),
+        };
+        Ok(raw.lines().filter_map(|line| serde_json::from_str(line).ok()).collect::<Vec<DeliberationRecord>>().into_iter())
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Asks the checker's deliberation API a question and records it in the history.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the history to record in.
+/// - `checker`: The [`Checker`] to ask.
+/// - `token`: The deliberation token to authenticate with.
+/// - `policy_token`: A management token, if the user has one, used to find out which policy answered the question.
+/// - `kind`: The kind of question to ask.
+/// - `request`: The raw request to send.
+/// - `replay_of`: The record that this request replays, if any.
+///
+/// # Returns
+/// The checker's answer and the record of it. The latter is [`None`] if it could not be written.
+pub async fn deliberate(
+    state: &AppState,
+    checker: &Checker,
+    token: &str,
+    policy_token: Option<&str>,
+    kind: DeliberationKind,
+    request: String,
+    replay_of: Option<u64>,
+) -> (Result<CheckerResponse, CheckerError>, Option<DeliberationRecord>) {
+    let parsed: Value = serde_json::from_str(&request).unwrap_or(Value::Null);
+
+    // Ask the question and which policy is answering it at the same time
+    let ask = async {
+        let start: Instant = Instant::now();
+        let res = checker.deliberate(token, kind, request).await;
+        (res, start.elapsed())
+    };
+    let version = async {
+        match policy_token {
+            Some(policy_token) => checker.get_active_version(policy_token).await.unwrap_or_else(|err| {
+                warn!("Failed to retrieve active policy version for deliberation history: {err}");
+                None
+            }),
+            None => None,
+        }
+    };
+    let ((result, latency), policy_version) = tokio::join!(ask, version);
+
+    // Record it
+    let record: DeliberationRecord = DeliberationRecord {
+        id: 0,
+        timestamp: Utc::now(),
+        subject: jwt_subject(token),
+        checker: Some(checker.name.clone()),
+        kind,
+        workflow: parsed.get("workflow").cloned().unwrap_or(Value::Null),
+        task_id: parsed.get("task_id").cloned(),
+        data_id: parsed.get("data_id").and_then(|v| v.as_str()).map(String::from),
+        use_case: parsed.get("use_case").and_then(|v| v.as_str()).map(String::from),
+        status: match &result {
+            Ok(res) => Some(res.status),
+            Err(err) => err.upstream_status(),
+        },
+        verdict: result.as_ref().ok().and_then(|res| Verdict::parse(&res.body).ok()).map(|v| v.as_str().into()),
+        response: match &result {
+            Ok(res) => res.body.clone(),
+            Err(err) => err.to_string(),
+        },
+        policy_version,
+        latency_ms: latency.as_millis() as u64,
+        replay_of,
+    };
+    match state.history.append(record).await {
+        Ok(record) => (result, Some(record)),
+        Err(err) => {
+            error!("{}", err.trace());
+            (result, None)
+        },
+    }
+}
+
+
+
+// List the history (which holds everybody's questions, so only managers of the selected checker may see it)
+pub async fn get_history(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    Query(query): Query<HistoryQuery>,
+) -> (StatusCode, String) {
+    if let Err(err) = verified_manager(&checker, &jar).await {
+        return err.into_parts();
+    }
+
+    let records: Vec<DeliberationRecord> = match state.history.query(&query).await {
+        Ok(records) => records,
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    match serde_json::to_string(&records) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+// Get a single record
+pub async fn get_history_record(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar, Path(id): Path<u64>) -> (StatusCode, String) {
+    if let Err(err) = verified_manager(&checker, &jar).await {
+        return err.into_parts();
+    }
+
+    let record: DeliberationRecord = match state.history.get(id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return err!(StatusCode::NOT_FOUND, StringError(format!("Unknown deliberation record {id}"))),
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    match serde_json::to_string(&record) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+/// Replays a record against the checker that answered it, reporting whether the verdict changed.
+///
+/// Selecting another checker with `?checker=` is refused, since comparing the verdicts of different checkers tells nothing.
+///
+/// The checker's deliberation API always answers with the policy that is active at that moment; it cannot be asked to use another version.
+/// Replaying never switches the active policy either, since others may rely on it. So `?version=` only makes sure the replay is made against
+/// that version, and is refused with `409 Conflict` if another version is active.
+pub async fn post_replay(State(state): State<AppState>, jar: PrivateCookieJar, Path(id): Path<u64>, Query(query): Query<ReplayQuery>) -> (StatusCode, String) {
+    let original: DeliberationRecord = match state.history.get(id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return err!(StatusCode::NOT_FOUND, StringError(format!("Unknown deliberation record {id}"))),
+        Err(err) => return err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+
+    // Find the checker that answered it
+    let name: &str = original.checker.as_deref().unwrap_or(DEFAULT_CHECKER);
+    if query.checker.as_deref().is_some_and(|selected| selected != name) {
+        return err!(StatusCode::BAD_REQUEST, StringError(format!("Record {id} was answered by checker '{name}' and can only be replayed against it")));
+    }
+    let checker: &Checker = match state.checkers.get(Some(name)) {
+        Ok(checker) => checker,
+        Err(err) => return checker_err!(err),
+    };
+    let deliberation_auth_token = match jar.get(&AuthDataType::Deliberation.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    let policy_auth_token = jar.get(&AuthDataType::Policy.cookie(&checker.name));
+
+    // Make sure the version asked for is the one that will answer
+    if let Some(version) = query.version {
+        let policy_auth_token = match &policy_auth_token {
+            Some(token) => token,
+            None => return err!(StatusCode::UNAUTHORIZED, StringError("Replaying against a specific policy version requires a management token".into())),
+        };
+        match checker.get_active_version(policy_auth_token.value()).await {
+            Ok(Some(active)) if active == version => (),
+            Ok(active) => {
+                let active: String = active.map(|v| format!("version {v} is")).unwrap_or_else(|| "no policy is".into());
+                return err!(
+                    StatusCode::CONFLICT,
+                    StringError(format!("Cannot replay against policy version {version} because {active} active; activate it first"))
+                );
+            },
+            Err(err) => return checker_err!(err),
+        }
+    }
+
+    // Replay it
+    let (result, replay): (Result<CheckerResponse, CheckerError>, Option<DeliberationRecord>) = deliberate(
+        &state,
+        checker,
+        deliberation_auth_token.value(),
+        policy_auth_token.as_ref().map(|c| c.value()),
+        original.kind,
+        original.request().to_string(),
+        Some(id),
+    )
+    .await;
+
+    // Report the difference
+    let replay: DeliberationRecord = match (result, replay) {
+        // Do not show the original to users that the checker does not know
+        (Err(err @ CheckerError::Status { code: 401 | 403, .. }), _) => return checker_err!(err),
+        (_, Some(replay)) => replay,
+        (Err(err), None) => return checker_err!(err),
+        (Ok(_), None) => return err!(StatusCode::INTERNAL_SERVER_ERROR, StringError("Failed to record replay".into())),
+    };
+    let changed: bool = original.verdict != replay.verdict;
+    match serde_json::to_string(&ReplayResult { original, replay, changed }) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
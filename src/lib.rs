@@ -1,7 +1,64 @@
+/***** HELPER MACROS *****/
+/// Reports errors to stderr before sending them back to the user as an [`ApiError`](crate::errors::ApiError)
+macro_rules! err {
+    ($code:expr, $err:expr) => {{
+        use ::error_trace::ErrorTrace as _;
+
+        // Trace the error
+        let code = $code;
+        let err = $err;
+        ::log::error!("[{}] {}", code.to_string(), err.trace());
+
+        // Return the error and its causes to the user
+        $crate::errors::ApiError::from_error(code, &err).into_parts()
+    }};
+}
+
+/// Reports errors from talking to the checker to stderr before sending back to user, telling them if the checker is down
+macro_rules! checker_err {
+    ($err:expr) => {{
+        use ::error_trace::ErrorTrace as _;
+
+        // Trace the error
+        let err: $crate::checker::CheckerError = $err;
+        let code = err.status_code();
+        ::log::error!("[{}] {}", code.to_string(), err.trace());
+
+        // Return the error and its causes to the user
+        err.api_error().into_parts()
+    }};
+}
+
+
+
+
+
+/***** MODULES *****/
+pub mod app;
+pub mod approval;
+pub mod audit;
 pub mod auth;
+pub mod batch;
 pub mod bstowir;
+pub mod checker;
+pub mod checker_tls;
+pub mod config;
 pub mod conv;
 pub mod deliberation;
 pub mod eflinttojson;
+pub mod errors;
+pub mod events;
+pub mod federated;
+pub mod health;
+pub mod history;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod policy;
 pub mod reasoner_conn;
+pub mod resilience;
+pub mod rules;
+pub mod schedule;
+pub mod store;
+pub mod sync;
+pub mod tls;
+pub mod verdict;
@@ -1,30 +1,56 @@
-use std::env;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::routing::{delete, get, post};
-use axum::Router;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use error_trace::ErrorTrace as _;
 use humanlog::{DebugMode, HumanLogger};
-use log::debug;
-use policy_reasoner_client_backend::auth::{get_authenticate, get_key, logout, post_authenticate, AppState};
-use policy_reasoner_client_backend::conv::post_conv;
-use policy_reasoner_client_backend::deliberation::{post_access_data, post_exec_task, post_validate_workflow};
-use policy_reasoner_client_backend::policy::{
-    delete_deactivate_policy, get_active_policy, get_policies, get_policy, post_activate_policy, post_add_policy,
-};
-use policy_reasoner_client_backend::reasoner_conn::get_reasoner_connector_info;
-use specifications::address::Address;
-use tower_http::services::{ServeDir, ServeFile};
-use tower_http::trace::{DefaultOnResponse, TraceLayer};
-use tower_http::LatencyUnit;
-use tracing::Level;
+use log::{debug, error, info};
+use policy_reasoner_client_backend::app::build_router;
+use policy_reasoner_client_backend::approval::ProposalStore;
+use policy_reasoner_client_backend::audit::AuditLog;
+use policy_reasoner_client_backend::auth::{AppState, KeyRing};
+use policy_reasoner_client_backend::checker::{Checker, CheckerError, Checkers};
+use policy_reasoner_client_backend::config::{Config, ConfigOverrides, LogFormat, LogLevel};
+use policy_reasoner_client_backend::events::EventBus;
+use policy_reasoner_client_backend::health::{run_probe, HealthMonitor};
+use policy_reasoner_client_backend::history::HistoryStore;
+use policy_reasoner_client_backend::rules::WorkflowRules;
+use policy_reasoner_client_backend::schedule::{run_scheduler, ScheduleStore};
+use policy_reasoner_client_backend::tls;
+use tokio::net::TcpListener;
 
 /***** ARGUMENTS *****/
 /// Toplevel arguments
 #[derive(Debug, Parser)]
 struct Arguments {
-    /// The address of the checker to connect to.
-    #[clap(short, long, default_value = "http://localhost:3030", help = "The address of the checker to connect to/manage.")]
-    checker_address: Address,
+    /// Any administrative command to run instead of the server.
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// The TOML file with the configuration of the server.
+    #[clap(long, env = "REASONER_GUI_CONFIG", help = "A TOML file with the configuration of the server. Flags and environment variables override it.")]
+    config:       Option<PathBuf>,
+    /// If given, prints the effective configuration instead of running the server.
+    #[clap(long, help = "If given, prints the effective configuration (as TOML) instead of running the server.")]
+    print_config: bool,
+    /// Overrides of the configuration file.
+    #[clap(flatten)]
+    overrides:    ConfigOverrides,
+}
+
+/// Administrative commands
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Adds a new key to the key file. New cookies are encrypted with it, while cookies encrypted with older keys remain valid until those
+    /// keys are rotated out. Restart the server to pick up the new key.
+    #[clap(name = "rotate-key")]
+    RotateKey {
+        /// The number of keys to keep, including the new one.
+        #[clap(long, default_value = "2", help = "The number of keys to keep, including the new one.")]
+        keep: usize,
+    },
 }
 
 #[tokio::main]
@@ -32,43 +58,189 @@ async fn main() {
     // Parse arguments
     let args = Arguments::parse();
 
+    // Resolve the configuration
+    let config: Config = match Config::resolve(args.config.as_deref(), args.overrides) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+    if args.print_config {
+        match config.to_toml() {
+            Ok(config) => print!("{config}"),
+            Err(err) => {
+                eprintln!("{}", err.trace());
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
     // Initialize logging
-    if std::env::var("HUMANLOGGER").is_ok() {
-        if let Err(err) = HumanLogger::terminal(DebugMode::Debug).init() {
-            eprintln!("WARNING: Failed to setup logger: {err} (no logging enabled for this session)");
+    match config.log.format {
+        LogFormat::Human => {
+            let mode: DebugMode = match config.log.level {
+                LogLevel::Trace => DebugMode::Full,
+                LogLevel::Debug => DebugMode::Debug,
+                LogLevel::Info | LogLevel::Warn | LogLevel::Error => DebugMode::HumanFriendly,
+            };
+            if let Err(err) = HumanLogger::terminal(mode).init() {
+                eprintln!("WARNING: Failed to setup logger: {err} (no logging enabled for this session)");
+            }
+        },
+        LogFormat::Json => tracing_subscriber::fmt().json().with_max_level(tracing::Level::from(config.log.level)).init(),
+        LogFormat::Text => tracing_subscriber::fmt().with_max_level(tracing::Level::from(config.log.level)).init(),
+    }
+
+    // Run any administrative command instead of the server
+    if let Some(Command::RotateKey { keep }) = args.command {
+        if let Err(err) = KeyRing::rotate(&config.paths.key, keep) {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        }
+        println!("Rotated key file '{}'; restart the server to start using the new key", config.paths.key.display());
+        return;
+    }
+
+    let keys = match KeyRing::load(&config.paths.key) {
+        Ok(keys) => keys,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+
+    let proposals = match ProposalStore::new(&config.paths.proposals, chrono::Duration::seconds(config.activation.proposal_ttl)) {
+        Ok(proposals) => proposals,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+
+    let schedules = match ScheduleStore::new(&config.paths.schedules) {
+        Ok(schedules) => schedules,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+
+    let audit = match AuditLog::new(&config.paths.audit) {
+        Ok(audit) => audit,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+
+    let history = match HistoryStore::new(&config.paths.history) {
+        Ok(history) => history,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+
+    let workflow_rules = match &config.paths.workflow_rules {
+        Some(path) => match WorkflowRules::load(path) {
+            Ok(rules) => rules,
+            Err(err) => {
+                eprintln!("{}", err.trace());
+                std::process::exit(1);
+            },
+        },
+        None => WorkflowRules::default(),
+    };
+
+    let checkers: Result<Vec<Checker>, CheckerError> = config
+        .checkers()
+        .into_iter()
+        .map(|(name, checker)| Checker::new(name, checker.address.clone(), Duration::from_secs(checker.timeout), &checker.resilience, &checker.tls))
+        .collect();
+    let checkers = match checkers.and_then(|checkers| Checkers::new(checkers, config.default_checker.as_deref())) {
+        Ok(checkers) => checkers,
+        Err(err) => {
+            eprintln!("{}", err.trace());
+            std::process::exit(1);
+        },
+    };
+
+    let probe_tokens: HashMap<String, String> =
+        config.checkers().into_iter().filter_map(|(name, checker)| checker.probe_token.clone().map(|token| (name.to_string(), token))).collect();
+    let health = HealthMonitor::new(probe_tokens, config.health.history);
+
+    let state = AppState {
+        checkers: Arc::new(checkers),
+        keys: Arc::new(keys),
+        proposals: Arc::new(proposals),
+        schedules: Arc::new(schedules),
+        audit: Arc::new(audit),
+        history: Arc::new(history),
+        health: Arc::new(health),
+        events: Arc::new(EventBus::new()),
+        workflow_rules: Arc::new(workflow_rules),
+        conv: Arc::new(config.conv.clone()),
+        four_eyes: config.activation.four_eyes,
+    };
+
+    // Start executing scheduled activations in the background
+    tokio::spawn(run_scheduler(state.clone(), Duration::from_secs(config.activation.schedule_interval)));
+    // Keep an eye on the checkers, too
+    tokio::spawn(run_probe(state.clone(), Duration::from_secs(config.health.probe_interval)));
+
+    debug!("Using client files stored at: {}", config.server.static_dir.display());
+    let app = build_router(state, &config.server);
+
+    // Serve HTTPS if we have a certificate...
+    if let Some(tls) = &config.server.tls {
+        let rustls = match tls::load(tls).await {
+            Ok(rustls) => rustls,
+            Err(err) => {
+                eprintln!("{}", err.trace());
+                std::process::exit(1);
+            },
+        };
+        tokio::spawn(tls::watch(rustls.clone(), tls.clone()));
+
+        // Redirect plain HTTP, if asked
+        if let Some(redirect_from) = tls.redirect_from {
+            let listener: TcpListener = match TcpListener::bind(redirect_from).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    eprintln!("Failed to listen on '{redirect_from}': {}", err.trace());
+                    std::process::exit(1);
+                },
+            };
+            info!("Redirecting HTTP on '{redirect_from}' to HTTPS");
+            let redirect = tls::redirect_router(config.server.bind.port());
+            tokio::spawn(async move {
+                if let Err(err) = axum::serve(listener, redirect).await {
+                    error!("Failed to serve HTTP redirects: {}", err.trace());
+                }
+            });
         }
-    } else {
-        // initialize tracing
-        tracing_subscriber::fmt().with_max_level(tracing::Level::DEBUG).init();
+
+        info!("Listening on '{}' (HTTPS)", config.server.bind);
+        if let Err(err) = axum_server::bind_rustls(config.server.bind, rustls).serve(app.into_make_service()).await {
+            eprintln!("Failed to serve: {}", err.trace());
+            std::process::exit(1);
+        }
+        return;
     }
 
-    let key = get_key("./key");
-
-    let state = AppState { checker_address: args.checker_address, key };
-
-    let static_base_path = env::var("CLIENT_FILES_PATH").unwrap_or_else(|_| "./clientbuild".into());
-    debug!("Using client files stored at: {static_base_path}");
-
-    let app = Router::new()
-        .nest_service("/", ServeDir::new(&static_base_path).not_found_service(ServeFile::new(format!("{}/{}", &static_base_path, "index.html"))))
-        .route("/api/conv", post(post_conv))
-        .route("/api/authenticate", post(post_authenticate))
-        .route("/api/authenticate", get(get_authenticate))
-        .route("/api/authenticate", delete(logout))
-        .route("/api/policies", get(get_policies))
-        .route("/api/policies", post(post_add_policy))
-        .route("/api/policies/active", get(get_active_policy))
-        .route("/api/policies/active", post(post_activate_policy))
-        .route("/api/policies/active", delete(delete_deactivate_policy))
-        .route("/api/policies/:version", get(get_policy))
-        .route("/api/reasoner-connector-info", get(get_reasoner_connector_info))
-        .route("/api/deliberation/task", post(post_exec_task))
-        .route("/api/deliberation/data", post(post_access_data))
-        .route("/api/deliberation/workflow", post(post_validate_workflow))
-        .layer(TraceLayer::new_for_http().on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis)))
-        .with_state(state);
-
-    // run our app with hyper, listening globally on port 3001
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3001").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // ...or plain HTTP otherwise
+    let listener: TcpListener = match TcpListener::bind(config.server.bind).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed to listen on '{}': {}", config.server.bind, err.trace());
+            std::process::exit(1);
+        },
+    };
+    info!("Listening on '{}'", config.server.bind);
+    if let Err(err) = axum::serve(listener, app).await {
+        eprintln!("Failed to serve: {}", err.trace());
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,415 @@
+//  MOCK.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 14:48:37
+//  Last edited:
+//    19 Oct 2026, 14:48:37
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements an in-memory mock of the policy reasoner (the "checker"),
+//!   for testing the backend and for demonstrating the GUI offline.
+//
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+use crate::checker::DeliberationKind;
+use crate::verdict::Verdict;
+
+
+/***** CONSTANTS *****/
+/// The reasoner connector context the mock reports.
+pub const MOCK_CONTEXT_TYPE: &str = "eflint-json";
+/// The version of the reasoner connector the mock reports.
+pub const MOCK_CONTEXT_VERSION: &str = "0.1.0";
+
+
+
+
+
+/***** AUXILLARY *****/
+/// A verdict that the mock should give.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MockVerdict {
+    /// Allow the request.
+    Allow,
+    /// Deny the request.
+    Deny,
+}
+
+/// A rule that decides the verdict of the mock for matching requests.
+///
+/// Every field that is given must match for the rule to apply. Rules are tried in order, and the first one that matches wins.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MockRule {
+    /// The kind of question the rule applies to.
+    #[serde(default)]
+    pub kind:     Option<DeliberationKind>,
+    /// The use-case the rule applies to.
+    #[serde(default)]
+    pub use_case: Option<String>,
+    /// The task (as a `[<function>, <edge>]` program counter) the rule applies to.
+    #[serde(default)]
+    pub task_id:  Option<Value>,
+    /// The dataset the rule applies to.
+    #[serde(default)]
+    pub data_id:  Option<String>,
+    /// The verdict to give.
+    pub verdict:  MockVerdict,
+    /// The reasons to give if the verdict is [`MockVerdict::Deny`].
+    #[serde(default)]
+    pub reasons:  Vec<String>,
+}
+impl MockRule {
+    /// Returns whether this rule applies to the given request.
+    ///
+    /// # Arguments
+    /// - `kind`: The kind of question asked.
+    /// - `req`: The body of the request.
+    fn matches(&self, kind: DeliberationKind, req: &Value) -> bool {
+        self.kind.map(|k| k == kind).unwrap_or(true)
+            && self.use_case.as_ref().map(|u| req.get("use_case").and_then(Value::as_str) == Some(u.as_str())).unwrap_or(true)
+            && self.task_id.as_ref().map(|t| req.get("task_id") == Some(t)).unwrap_or(true)
+            && self.data_id.as_ref().map(|d| req.get("data_id").and_then(Value::as_str) == Some(d.as_str())).unwrap_or(true)
+    }
+}
+
+/// A request that the mock received, kept so tests can inspect it.
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// The method of the request.
+    pub method: &'static str,
+    /// The path of the request.
+    pub path:   String,
+    /// The bearer token with which the request was authenticated.
+    pub token:  String,
+    /// The body of the request, if it had any.
+    pub body:   Option<Value>,
+}
+
+/// The state of the mock.
+#[derive(Debug, Default)]
+struct MockState {
+    /// The policies added so far, by version.
+    policies: BTreeMap<i64, Value>,
+    /// The active policy version, if any.
+    active:   Option<i64>,
+    /// The verdicts to give before the rules are considered, in order.
+    script:   VecDeque<(MockVerdict, Vec<String>)>,
+    /// The rules that decide the verdicts once the script is exhausted.
+    rules:    Vec<MockRule>,
+    /// The number of verdicts given so far.
+    verdicts: u64,
+    /// The requests received so far.
+    requests: Vec<MockRequest>,
+    /// The only tokens that are accepted, if restricted.
+    accepted: Option<HashSet<String>>,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Extracts the bearer token from a request, rejecting it if there is none.
+///
+/// Like the real checker, the mock requires a token; but unless restricted with [`MockReasoner::accept_only()`], any token will do.
+///
+/// # Arguments
+/// - `headers`: The headers of the request.
+///
+/// # Returns
+/// The token.
+///
+/// # Errors
+/// This function errors with [`StatusCode::UNAUTHORIZED`] if there is no bearer token.
+fn token(headers: &HeaderMap) -> Result<String, (StatusCode, String)> {
+    headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing bearer token".into()))
+}
+
+/// Strips the content of a policy, leaving only the metadata that the checker lists.
+fn metadata(policy: &Value) -> Value {
+    let mut policy: Value = policy.clone();
+    if let Some(policy) = policy.as_object_mut() {
+        policy.remove("content");
+    }
+    policy
+}
+
+/// Serializes a response body.
+fn respond(code: StatusCode, body: &Value) -> (StatusCode, String) { (code, body.to_string()) }
+
+
+
+
+
+/***** LIBRARY *****/
+/// An in-memory mock of the policy reasoner.
+///
+/// It implements the management and deliberation endpoints used by the backend. Verdicts are first taken from a script (see
+/// [`MockReasoner::script()`]) and then decided by rules (see [`MockReasoner::with_rules()`]); if no rule matches, requests are allowed as
+/// long as a policy is active.
+#[derive(Clone, Debug, Default)]
+pub struct MockReasoner {
+    /// The shared state of the mock.
+    state: Arc<Mutex<MockState>>,
+}
+impl MockReasoner {
+    /// Constructor for a MockReasoner without any policies or rules.
+    ///
+    /// # Returns
+    /// A new MockReasoner.
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Constructor for a MockReasoner that decides verdicts by the given rules.
+    ///
+    /// # Arguments
+    /// - `rules`: The [`MockRule`]s to decide verdicts with, in order.
+    ///
+    /// # Returns
+    /// A new MockReasoner.
+    pub fn with_rules(rules: Vec<MockRule>) -> Self {
+        Self { state: Arc::new(Mutex::new(MockState { rules, ..Default::default() })) }
+    }
+
+    /// Queues verdicts that are given to the next deliberation requests, regardless of the rules.
+    ///
+    /// # Arguments
+    /// - `verdicts`: The verdicts to give, in order, together with the reasons to give for denials.
+    pub async fn script(&self, verdicts: impl IntoIterator<Item = (MockVerdict, Vec<String>)>) {
+        self.state.lock().await.script.extend(verdicts);
+    }
+
+    /// Restricts the tokens the mock accepts to the given ones, like a real checker rejects tokens it did not issue.
+    ///
+    /// # Arguments
+    /// - `tokens`: The tokens to accept. Requests with any other token are refused with [`StatusCode::UNAUTHORIZED`].
+    pub async fn accept_only(&self, tokens: impl IntoIterator<Item = impl Into<String>>) {
+        self.state.lock().await.accepted = Some(tokens.into_iter().map(Into::into).collect());
+    }
+
+    /// Adds a policy directly, as if it were added through the management API.
+    ///
+    /// # Arguments
+    /// - `content`: The eFLINT JSON of the policy.
+    /// - `activate`: Whether to also activate the new policy.
+    ///
+    /// # Returns
+    /// The version of the new policy.
+    pub async fn add_policy(&self, content: Value, activate: bool) -> i64 {
+        let mut state = self.state.lock().await;
+        let version: i64 = Self::insert(&mut state, json!({
+            "description": "",
+            "version_description": "",
+            "content": [{ "reasoner": MOCK_CONTEXT_TYPE, "reasoner_version": MOCK_CONTEXT_VERSION, "content": content }],
+        }));
+        if activate {
+            state.active = Some(version);
+        }
+        version
+    }
+
+    /// Returns the currently active policy version, if any.
+    pub async fn active(&self) -> Option<i64> { self.state.lock().await.active }
+
+    /// Returns all requests the mock received so far.
+    pub async fn requests(&self) -> Vec<MockRequest> { self.state.lock().await.requests.clone() }
+
+    /// Returns a [`Router`] that serves the mock.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/v1/management/policies", get(get_policies).post(post_policy))
+            .route("/v1/management/policies/active", get(get_active).put(put_active).delete(delete_active))
+            .route("/v1/management/policies/:version", get(get_policy))
+            .route("/v1/management/reasoner-connector-context", get(get_context))
+            .route("/v1/deliberation/execute-task", post(post_execute_task))
+            .route("/v1/deliberation/access-data", post(post_access_data))
+            .route("/v1/deliberation/execute-workflow", post(post_execute_workflow))
+            .with_state(self.clone())
+    }
+
+    /// Serves the mock on the given listener until the process ends.
+    ///
+    /// # Arguments
+    /// - `listener`: The [`TcpListener`] to accept connections on.
+    ///
+    /// # Errors
+    /// This function errors if the server failed.
+    pub async fn serve(&self, listener: TcpListener) -> Result<(), std::io::Error> { axum::serve(listener, self.router()).await }
+
+    /// Stores a new policy and returns its version.
+    fn insert(state: &mut MockState, mut policy: Value) -> i64 {
+        let version: i64 = state.policies.keys().next_back().copied().unwrap_or(0) + 1;
+        if let Some(policy) = policy.as_object_mut() {
+            policy.insert("version".into(), json!(version));
+            policy.insert("creator".into(), json!("mock"));
+            policy.insert("created_at".into(), json!(Utc::now()));
+        }
+        state.policies.insert(version, policy);
+        version
+    }
+
+    /// Records a request and checks its token.
+    async fn record(&self, method: &'static str, path: impl Into<String>, headers: &HeaderMap, body: Option<&Value>) -> Result<(), (StatusCode, String)> {
+        let token: String = token(headers)?;
+        let path: String = path.into();
+        debug!("Mock reasoner received {method} {path}");
+        let mut state = self.state.lock().await;
+        if state.accepted.as_ref().is_some_and(|accepted| !accepted.contains(&token)) {
+            return Err((StatusCode::UNAUTHORIZED, "Invalid bearer token".into()));
+        }
+        state.requests.push(MockRequest { method, path, token, body: body.cloned() });
+        Ok(())
+    }
+
+    /// Decides the verdict for a deliberation request.
+    async fn deliberate(&self, kind: DeliberationKind, headers: &HeaderMap, req: Value) -> (StatusCode, String) {
+        if let Err(err) = self.record("POST", kind.path(), headers, Some(&req)).await {
+            return err;
+        }
+
+        let mut state = self.state.lock().await;
+        state.verdicts += 1;
+        let verdict_reference: String = format!("mock-{}", state.verdicts);
+        let (verdict, reasons): (MockVerdict, Vec<String>) = if let Some(scripted) = state.script.pop_front() {
+            scripted
+        } else if state.active.is_none() {
+            (MockVerdict::Deny, vec!["No active policy".into()])
+        } else {
+            match state.rules.iter().find(|r| r.matches(kind, &req)) {
+                Some(rule) => (rule.verdict, rule.reasons.clone()),
+                None => (MockVerdict::Allow, vec![]),
+            }
+        };
+
+        let verdict: Verdict = match verdict {
+            MockVerdict::Allow => Verdict::Allow { verdict_reference, signature: "mock".into() },
+            MockVerdict::Deny => Verdict::Deny { verdict_reference, reasons_for_denial: Some(reasons) },
+        };
+        match serde_json::to_value(&verdict) {
+            Ok(body) => respond(StatusCode::OK, &body),
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+        }
+    }
+}
+
+
+
+// List all policy versions
+async fn get_policies(State(mock): State<MockReasoner>, headers: HeaderMap) -> (StatusCode, String) {
+    if let Err(err) = mock.record("GET", "/v1/management/policies", &headers, None).await {
+        return err;
+    }
+    let state = mock.state.lock().await;
+    respond(StatusCode::OK, &Value::Array(state.policies.values().map(metadata).collect()))
+}
+
+// Add a new policy
+async fn post_policy(State(mock): State<MockReasoner>, headers: HeaderMap, Json(body): Json<Value>) -> (StatusCode, String) {
+    if let Err(err) = mock.record("POST", "/v1/management/policies", &headers, Some(&body)).await {
+        return err;
+    }
+    if !body.get("content").map(Value::is_array).unwrap_or(false) {
+        return (StatusCode::BAD_REQUEST, "Policy has no content".into());
+    }
+    let mut state = mock.state.lock().await;
+    let version: i64 = MockReasoner::insert(&mut state, body);
+    respond(StatusCode::OK, &state.policies[&version])
+}
+
+// Get a specific policy version
+async fn get_policy(State(mock): State<MockReasoner>, headers: HeaderMap, Path(version): Path<i64>) -> (StatusCode, String) {
+    if let Err(err) = mock.record("GET", format!("/v1/management/policies/{version}"), &headers, None).await {
+        return err;
+    }
+    match mock.state.lock().await.policies.get(&version) {
+        Some(policy) => respond(StatusCode::OK, policy),
+        None => (StatusCode::NOT_FOUND, format!("No policy with version {version}")),
+    }
+}
+
+// Get the active policy
+async fn get_active(State(mock): State<MockReasoner>, headers: HeaderMap) -> (StatusCode, String) {
+    if let Err(err) = mock.record("GET", "/v1/management/policies/active", &headers, None).await {
+        return err;
+    }
+    let state = mock.state.lock().await;
+    match state.active.and_then(|v| state.policies.get(&v)) {
+        Some(policy) => respond(StatusCode::OK, policy),
+        None => (StatusCode::NOT_FOUND, "No active policy".into()),
+    }
+}
+
+// Activate a policy
+async fn put_active(State(mock): State<MockReasoner>, headers: HeaderMap, Json(body): Json<Value>) -> (StatusCode, String) {
+    if let Err(err) = mock.record("PUT", "/v1/management/policies/active", &headers, Some(&body)).await {
+        return err;
+    }
+    let version: i64 = match body.get("version").and_then(Value::as_i64) {
+        Some(version) => version,
+        None => return (StatusCode::BAD_REQUEST, "Missing version".into()),
+    };
+    let mut state = mock.state.lock().await;
+    if !state.policies.contains_key(&version) {
+        return (StatusCode::BAD_REQUEST, format!("No policy with version {version}"));
+    }
+    state.active = Some(version);
+    respond(StatusCode::OK, &state.policies[&version])
+}
+
+// Deactivate the active policy
+async fn delete_active(State(mock): State<MockReasoner>, headers: HeaderMap) -> (StatusCode, String) {
+    if let Err(err) = mock.record("DELETE", "/v1/management/policies/active", &headers, None).await {
+        return err;
+    }
+    let mut state = mock.state.lock().await;
+    match state.active.take() {
+        Some(_) => (StatusCode::OK, "".into()),
+        None => (StatusCode::BAD_REQUEST, "No active policy".into()),
+    }
+}
+
+// Get the reasoner connector context
+async fn get_context(State(mock): State<MockReasoner>, headers: HeaderMap) -> (StatusCode, String) {
+    if let Err(err) = mock.record("GET", "/v1/management/reasoner-connector-context", &headers, None).await {
+        return err;
+    }
+    respond(StatusCode::OK, &json!({ "hash": "mock", "context": { "type": MOCK_CONTEXT_TYPE, "version": MOCK_CONTEXT_VERSION } }))
+}
+
+// Ask if a task may be executed
+async fn post_execute_task(State(mock): State<MockReasoner>, headers: HeaderMap, Json(body): Json<Value>) -> (StatusCode, String) {
+    mock.deliberate(DeliberationKind::Task, &headers, body).await
+}
+
+// Ask if a dataset may be accessed
+async fn post_access_data(State(mock): State<MockReasoner>, headers: HeaderMap, Json(body): Json<Value>) -> (StatusCode, String) {
+    mock.deliberate(DeliberationKind::Data, &headers, body).await
+}
+
+// Ask if a workflow is OK
+async fn post_execute_workflow(State(mock): State<MockReasoner>, headers: HeaderMap, Json(body): Json<Value>) -> (StatusCode, String) {
+    mock.deliberate(DeliberationKind::Workflow, &headers, body).await
+}
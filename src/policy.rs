@@ -13,271 +13,163 @@
 
 use std::collections::HashMap;
 
+use axum::body::Bytes;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{Method, StatusCode, Uri};
 use axum::Json;
 use axum_extra::extract::cookie::PrivateCookieJar;
 use brane_tsk::errors::StringError;
 
-use crate::auth::AppState;
-
-
-/***** HELPER MACROS *****/
-/// Reports errors to stderr before sending back to user
-macro_rules! err {
-    ($code:expr, $err:expr) => {{
-        use ::error_trace::ErrorTrace as _;
-
-        // Trace the error
-        let code = $code;
-        let err = $err;
-        ::log::error!("[{}] {}", code.to_string(), err.trace());
-
-        // Return the plain version to the user
-        (code, err.to_string())
-    }};
-}
-
-
-
+use crate::audit::{describe, AuditAction};
+use crate::auth::{AppState, AuthDataType};
+use crate::checker::SelectedChecker;
+use crate::errors::ApiError;
+use crate::events::PolicyEventKind;
 
 
 //  Get all policy versions
-pub async fn get_policies(State(state): State<AppState>, jar: PrivateCookieJar) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+pub async fn get_policies(checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
             return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
 
-    let client = reqwest::Client::new();
-    let result = match client
-        .get(format!("{}/v1/management/policies", state.checker_address))
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    let body = match result.error_for_status() {
-        Ok(result) => result.text().await,
-        Err(err) => {
-            return err!(StatusCode::from_u16(err.status().unwrap().as_u16()).unwrap(), err);
-        },
-    };
-
-    match body {
-        Ok(data) => (StatusCode::OK, data),
-        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    match checker.list_policies(policy_auth_token.value()).await {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
 }
 
 // Get specific version
-pub async fn get_policy(State(state): State<AppState>, jar: PrivateCookieJar, Path(version): Path<i64>) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+pub async fn get_policy(checker: SelectedChecker, jar: PrivateCookieJar, Path(version): Path<i64>) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
             return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
 
-    let client = reqwest::Client::new();
-    let result = match client
-        .get(format!("{}/v1/management/policies/{}", state.checker_address, version))
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    let body = match result.error_for_status() {
-        Ok(result) => result.text().await,
-        Err(err) => {
-            return err!(StatusCode::from_u16(err.status().unwrap().as_u16()).unwrap(), err);
-        },
-    };
-
-    match body {
-        Ok(data) => (StatusCode::OK, data),
-        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    match checker.get_policy(policy_auth_token.value(), version).await {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
 }
 
 // Get active version
-pub async fn get_active_policy(State(state): State<AppState>, jar: PrivateCookieJar) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+pub async fn get_active_policy(checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
             return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
 
-    let client = reqwest::Client::new();
-    let result = match client
-        .get(format!("{}/v1/management/policies/active", state.checker_address))
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    let body = match result.error_for_status() {
-        Ok(result) => result.text().await,
-        Err(err) => {
-            return err!(StatusCode::from_u16(err.status().unwrap().as_u16()).unwrap(), err);
-        },
-    };
-
-    match body {
-        Ok(data) => (StatusCode::OK, data),
-        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    match checker.get_active_policy(policy_auth_token.value()).await {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
 }
 
 // Activate version
 pub async fn post_activate_policy(
     State(state): State<AppState>,
+    checker: SelectedChecker,
     jar: PrivateCookieJar,
-    Json(version): Json<HashMap<String, i64>>,
+    raw: Bytes,
 ) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
             return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
-
-    let client = reqwest::Client::new();
-    let result = match client
-        .put(format!("{}/v1/management/policies/active", state.checker_address))
-        .json(&version)
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
+    // Parse the body ourselves, as the audit log needs it exactly as it was sent
+    let body: HashMap<String, i64> = match Json::from_bytes(&raw) {
+        Ok(Json(body)) => body,
+        Err(err) => return ApiError::new(err.status(), err.body_text()).into_parts(),
     };
-
-    let status = result.status().as_u16();
-
-    if status > 399 {
-        let body = match result.text().await {
-            Ok(text) => text,
-            Err(_) => "".into(),
-        };
-
-        return err!(StatusCode::from_u16(status).unwrap(), StringError(body));
+    if state.four_eyes {
+        return err!(
+            StatusCode::FORBIDDEN,
+            StringError("Policies can only be activated by proposing it at '/api/proposals' and having another user approve it".into())
+        );
+    }
+    let version: i64 = match body.get("version") {
+        Some(version) => *version,
+        None => return err!(StatusCode::BAD_REQUEST, StringError("Missing 'version' field".into())),
+    };
+
+    let result = checker.activate_policy(policy_auth_token.value(), version).await;
+    state
+        .audit
+        .record(
+            Some(policy_auth_token.value()),
+            &checker.name,
+            AuditAction::ActivatePolicy,
+            Some(version),
+            Some(&result),
+            &raw,
+        )
+        .await;
+    state.events.changed(&checker.name, PolicyEventKind::Activated, Some(version), Some(policy_auth_token.value()), &result).await;
+
+    match result {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
-
-    let body = match result.text().await {
-        Ok(body) => body,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    (StatusCode::OK, body)
 }
 
 // Deactivate policy
-pub async fn delete_deactivate_policy(State(state): State<AppState>, jar: PrivateCookieJar) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+pub async fn delete_deactivate_policy(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    method: Method,
+    uri: Uri,
+) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
             return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
-
-    let client = reqwest::Client::new();
-    let result = match client
-        .delete(format!("{}/v1/management/policies/active", state.checker_address))
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    let status = result.status().as_u16();
-
-    if status > 399 {
-        let body = match result.text().await {
-            Ok(text) => text,
-            Err(_) => "".into(),
-        };
-
-        return err!(StatusCode::from_u16(status).unwrap(), StringError(body));
+    if state.four_eyes {
+        return err!(StatusCode::FORBIDDEN, StringError("Policies cannot be deactivated by a single user while the four-eyes principle is enforced".into()));
     }
 
-    let body = match result.text().await {
-        Ok(body) => body,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
+    let result = checker.deactivate_policy(policy_auth_token.value()).await;
+    state.audit.record(Some(policy_auth_token.value()), &checker.name, AuditAction::DeactivatePolicy, None, Some(&result), &describe(&method, &uri)).await;
+    state.events.changed(&checker.name, PolicyEventKind::Deactivated, None, Some(policy_auth_token.value()), &result).await;
 
-    (StatusCode::OK, body)
+    match result {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
+    }
 }
 
-pub async fn post_add_policy(State(state): State<AppState>, jar: PrivateCookieJar, body: String) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+pub async fn post_add_policy(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar, body: String) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
             return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
 
-    let client = reqwest::Client::new();
-    let result = match client
-        .post(format!("{}/v1/management/policies", state.checker_address))
-        .body(body)
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .header("Content-Type", "application/json")
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    let status = result.status().as_u16();
+    let request: Vec<u8> = body.as_bytes().to_vec();
+    let result = checker.add_policy(policy_auth_token.value(), body).await;
+    // The version is only known once the checker assigned one
+    let version: Option<i64> = result
+        .as_ref()
+        .ok()
+        .and_then(|res| serde_json::from_str::<serde_json::Value>(&res.body).ok())
+        .and_then(|policy| policy.get("version").and_then(|v| v.as_i64()));
+    state.audit.record(Some(policy_auth_token.value()), &checker.name, AuditAction::AddPolicy, version, Some(&result), &request).await;
+    state.events.changed(&checker.name, PolicyEventKind::Added, version, Some(policy_auth_token.value()), &result).await;
 
-    if status > 399 {
-        let body = match result.text().await {
-            Ok(text) => text,
-            Err(_) => "".into(),
-        };
-
-        return err!(StatusCode::from_u16(status).unwrap(), StringError(body));
+    match result {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
-
-    let body = match result.text().await {
-        Ok(body) => body,
-        Err(err) => {
-            return err!(StatusCode::INTERNAL_SERVER_ERROR, err);
-        },
-    };
-
-    (StatusCode::OK, body)
 }
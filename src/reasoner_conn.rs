@@ -12,43 +12,24 @@
 //!   
 //
 
-use axum::extract::State;
 use axum::http::StatusCode;
 use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
 
-use crate::auth::AppState;
+use crate::auth::AuthDataType;
+use crate::checker::SelectedChecker;
 
 
-pub async fn get_reasoner_connector_info(State(state): State<AppState>, jar: PrivateCookieJar) -> (StatusCode, String) {
-    let policy_auth_token = match jar.get("reasoner_policy_auth") {
+pub async fn get_reasoner_connector_info(checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
         Some(data) => data,
         None => {
-            return (StatusCode::UNAUTHORIZED, "".into());
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
         },
     };
 
-    let client = reqwest::Client::new();
-    let result = match client
-        .get(format!("{}/v1/management/reasoner-connector-context", state.checker_address))
-        .header("Authorization", format!("Bearer {}", policy_auth_token.value()))
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err));
-        },
-    };
-
-    let body = match result.error_for_status() {
-        Ok(result) => result.text().await,
-        Err(err) => {
-            return (StatusCode::from_u16(err.status().unwrap().as_u16()).unwrap(), err.to_string());
-        },
-    };
-
-    match body {
-        Ok(data) => (StatusCode::OK, data),
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Err: {:?}", err)),
+    match checker.get_connector_info(policy_auth_token.value()).await {
+        Ok(res) => (StatusCode::OK, res.body),
+        Err(err) => checker_err!(err),
     }
 }
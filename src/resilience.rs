@@ -0,0 +1,182 @@
+//  RESILIENCE.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 21:05:17
+//  Last edited:
+//    19 Oct 2026, 21:05:17
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements how calls to the checker are timed out and retried, and
+//!   the circuit breaker that stops calling a checker that is down.
+//
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use enum_debug::EnumDebug;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+
+/***** AUXILLARY *****/
+/// What the [`CircuitBreaker`] remembers about recent calls.
+#[derive(Clone, Copy, Debug, Default)]
+struct BreakerState {
+    /// The number of calls that failed in a row.
+    failures:   u32,
+    /// Until when no calls are let through, if the breaker is open.
+    open_until: Option<Instant>,
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// The endpoints of the checker that the backend calls, which can each be given their own timeout.
+#[derive(Clone, Copy, Debug, Deserialize, EnumDebug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    /// Listing all policy versions.
+    ListPolicies,
+    /// Retrieving a particular policy version.
+    GetPolicy,
+    /// Retrieving the active policy.
+    ActivePolicy,
+    /// Retrieving the reasoner connector's context.
+    ConnectorInfo,
+    /// Adding a new policy version.
+    AddPolicy,
+    /// Activating a policy version.
+    Activate,
+    /// Deactivating the active policy.
+    Deactivate,
+    /// Asking any of the deliberation questions.
+    Deliberation,
+}
+impl Endpoint {
+    /// Returns whether calls to this endpoint only read, and can thus be repeated safely.
+    #[inline]
+    pub fn idempotent(&self) -> bool { matches!(self, Self::ListPolicies | Self::GetPolicy | Self::ActivePolicy | Self::ConnectorInfo) }
+}
+
+
+
+/// Configures how calls to the checker are timed out, retried and cut off when the checker is down.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResilienceConfig {
+    /// The number of seconds after which calls to particular endpoints are aborted, if not the checker's `timeout`.
+    pub timeouts:          BTreeMap<Endpoint, u64>,
+    /// The number of times a call to an endpoint that only reads is retried if the checker could not be reached.
+    pub retries:           u32,
+    /// The number of milliseconds to wait before the first retry. Doubled for every next one.
+    pub retry_backoff:     u64,
+    /// The number of failed calls in a row after which the checker is considered down. `0` never considers it down.
+    pub breaker_threshold: u32,
+    /// The number of seconds a checker that is down is left alone before it is tried again.
+    pub breaker_cooldown:  u64,
+}
+impl Default for ResilienceConfig {
+    fn default() -> Self { Self { timeouts: BTreeMap::new(), retries: 2, retry_backoff: 200, breaker_threshold: 5, breaker_cooldown: 30 } }
+}
+impl ResilienceConfig {
+    /// Returns the timeout of calls to the given endpoint.
+    ///
+    /// # Arguments
+    /// - `endpoint`: The [`Endpoint`] that is called.
+    /// - `default`: The timeout used if the endpoint has none of its own.
+    ///
+    /// # Returns
+    /// The maximum time a call to the endpoint may take.
+    #[inline]
+    pub fn timeout(&self, endpoint: Endpoint, default: Duration) -> Duration {
+        self.timeouts.get(&endpoint).map(|secs| Duration::from_secs(*secs)).unwrap_or(default)
+    }
+
+    /// Returns how long to wait before the given retry.
+    ///
+    /// # Arguments
+    /// - `attempt`: The number of retries done so far.
+    ///
+    /// # Returns
+    /// The time to wait before retrying.
+    #[inline]
+    pub fn backoff(&self, attempt: u32) -> Duration { Duration::from_millis(self.retry_backoff.saturating_mul(1 << attempt.min(16))) }
+}
+
+
+
+/// Stops calling a checker that failed too many times in a row, so requests fail fast instead of waiting for it to time out.
+///
+/// After the cooldown, a single call is let through to see whether the checker is back. If it is, calls are let through again; if
+/// it isn't, the checker is left alone for another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// The number of failed calls in a row after which no calls are let through. `0` always lets calls through.
+    threshold: u32,
+    /// How long no calls are let through.
+    cooldown:  Duration,
+    /// What we remember about recent calls.
+    state:     Mutex<BreakerState>,
+}
+impl CircuitBreaker {
+    /// Constructor for the CircuitBreaker.
+    ///
+    /// # Arguments
+    /// - `threshold`: The number of failed calls in a row after which no calls are let through. `0` always lets calls through.
+    /// - `cooldown`: How long no calls are let through.
+    ///
+    /// # Returns
+    /// A new CircuitBreaker that lets calls through.
+    #[inline]
+    pub fn new(threshold: u32, cooldown: Duration) -> Self { Self { threshold, cooldown, state: Mutex::new(BreakerState::default()) } }
+
+    /// Decides whether a call may be made.
+    ///
+    /// # Returns
+    /// Nothing if the call may be made.
+    ///
+    /// # Errors
+    /// This function errors with the time until the next call is let through if the checker is considered down.
+    pub async fn allow(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().await;
+        let now: Instant = Instant::now();
+        match state.open_until {
+            Some(until) if until > now => Err(until - now),
+            Some(_) => {
+                // Let this call find out if the checker is back, but keep the others out until it has
+                state.open_until = Some(now + self.cooldown);
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Remembers that a call succeeded, which lets all calls through again.
+    pub async fn succeeded(&self) { *self.state.lock().await = BreakerState::default() }
+
+    /// Remembers that a call failed because the checker could not be reached.
+    ///
+    /// # Returns
+    /// Whether no calls are let through anymore because of this failure.
+    pub async fn failed(&self) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+        let mut state = self.state.lock().await;
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether no calls are let through at the moment.
+    pub async fn is_open(&self) -> bool { self.state.lock().await.open_until.map(|until| until > Instant::now()).unwrap_or(false) }
+}
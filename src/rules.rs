@@ -0,0 +1,221 @@
+//  RULES.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 17:12:30
+//  Last edited:
+//    19 Oct 2026, 17:12:30
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements static checks of workflows against simple, locally
+//!   configured rules, as a quick pre-flight before asking the checker.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use brane_exe::pc::ProgramCounter;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AppState;
+use crate::bstowir::{AnalyzedWir, TaskCallInfo};
+
+
+/***** ERRORS *****/
+/// Defines errors originating from loading workflow rules.
+#[derive(Debug)]
+pub enum RulesError {
+    /// Failed to read the rules file.
+    Read { path: PathBuf, err: std::io::Error },
+    /// Failed to parse the rules file.
+    Parse { path: PathBuf, err: serde_yaml::Error },
+}
+impl Display for RulesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use RulesError::*;
+        match self {
+            Read { path, .. } => write!(f, "Failed to read workflow rules file '{}'", path.display()),
+            Parse { path, .. } => write!(f, "Failed to parse workflow rules file '{}' as YAML", path.display()),
+        }
+    }
+}
+impl Error for RulesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use RulesError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            Parse { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// A single constraint on workflows.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum WorkflowRule {
+    /// The given dataset may only be consumed by tasks at the given locations.
+    DatasetLocation { dataset: String, locations: Vec<String> },
+    /// Tasks of the given package may not run at the given location.
+    ForbiddenPackage { package: String, location: String },
+    /// The results of the workflow must be produced (or committed) at one of the given locations.
+    ResultLocation { locations: Vec<String> },
+}
+impl Display for WorkflowRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use WorkflowRule::*;
+        match self {
+            DatasetLocation { dataset, locations } => write!(f, "Dataset '{dataset}' may only be consumed at {}", locations.join(", ")),
+            ForbiddenPackage { package, location } => write!(f, "No task of package '{package}' may run at '{location}'"),
+            ResultLocation { locations } => write!(f, "Workflow results must be produced at {}", locations.join(", ")),
+        }
+    }
+}
+
+/// A rule as written in the rules file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RuleEntry {
+    /// A human-readable name for the rule, reported with its violations.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The rule itself.
+    #[serde(flatten)]
+    pub rule: WorkflowRule,
+}
+
+/// The rules to check workflows against.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WorkflowRules {
+    /// The rules, in the order they are checked.
+    #[serde(default)]
+    pub rules: Vec<RuleEntry>,
+}
+impl WorkflowRules {
+    /// Loads the rules from the given YAML file.
+    ///
+    /// # Arguments
+    /// - `path`: The path to the rules file.
+    ///
+    /// # Returns
+    /// The loaded WorkflowRules.
+    ///
+    /// # Errors
+    /// This function errors if the file could not be read or is not a valid rules file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RulesError> {
+        let path: &Path = path.as_ref();
+        let raw: String = fs::read_to_string(path).map_err(|err| RulesError::Read { path: path.into(), err })?;
+        let rules: Self = serde_yaml::from_str(&raw).map_err(|err| RulesError::Parse { path: path.into(), err })?;
+        debug!("Loaded {} workflow rule(s) from '{}'", rules.rules.len(), path.display());
+        Ok(rules)
+    }
+
+    /// Checks a workflow against the rules.
+    ///
+    /// # Arguments
+    /// - `wir`: The [`AnalyzedWir`] to check.
+    ///
+    /// # Returns
+    /// Every violation found, in the order of the rules.
+    pub fn check(&self, wir: &AnalyzedWir) -> Vec<Violation> {
+        let mut violations: Vec<Violation> = vec![];
+        for (i, entry) in self.rules.iter().enumerate() {
+            for task in wir.tasks.iter().filter(|t| t.reachable) {
+                if let Some(message) = violation(&entry.rule, task, wir) {
+                    violations.push(Violation { rule: i, name: entry.name.clone(), pg: task.pg, message });
+                }
+            }
+        }
+        violations
+    }
+}
+
+
+
+/// A violation of a rule by a task call.
+#[derive(Clone, Debug, Serialize)]
+pub struct Violation {
+    /// The index of the violated rule in the rules file.
+    pub rule:    usize,
+    /// The name of the violated rule, if it has one.
+    pub name:    Option<String>,
+    /// The task call that violates the rule.
+    pub pg:      ProgramCounter,
+    /// Describes what is wrong.
+    pub message: String,
+}
+
+/// The outcome of checking a workflow.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckReport {
+    /// Whether the workflow satisfies all rules.
+    pub ok:         bool,
+    /// The violations found.
+    pub violations: Vec<Violation>,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Checks a single task call against a single rule.
+///
+/// # Arguments
+/// - `rule`: The [`WorkflowRule`] to check.
+/// - `task`: The [`TaskCallInfo`] of the call to check.
+/// - `wir`: The [`AnalyzedWir`] the call is part of.
+///
+/// # Returns
+/// A message describing the violation, or [`None`] if the call satisfies the rule.
+fn violation(rule: &WorkflowRule, task: &TaskCallInfo, wir: &AnalyzedWir) -> Option<String> {
+    let at: &str = task.at.as_deref().unwrap_or("<unplanned>");
+    match rule {
+        WorkflowRule::DatasetLocation { dataset, locations } => {
+            if task.datasets.contains(dataset) && !locations.iter().any(|l| l == at) {
+                return Some(format!("Task '{}' consumes dataset '{dataset}' at '{at}' ({rule})", task.name));
+            }
+        },
+        WorkflowRule::ForbiddenPackage { package, location } => {
+            // Task names are formatted as `<package><<version>>::<function>`
+            if task.name.split('<').next() == Some(package.as_str()) && at == location {
+                return Some(format!("Task '{}' runs at '{at}' ({rule})", task.name));
+            }
+        },
+        WorkflowRule::ResultLocation { locations } => {
+            let produces: bool = task.result.as_ref().map(|r| wir.results.contains(r)).unwrap_or(false);
+            let commits: bool = task.name.ends_with("::commit_result");
+            if (produces || commits) && !locations.iter().any(|l| l == at) {
+                return Some(format!("Task '{}' produces a workflow result at '{at}' ({rule})", task.name));
+            }
+        },
+    }
+    None
+}
+
+
+
+
+
+/***** LIBRARY *****/
+// Check a workflow against the configured rules
+pub async fn post_check_workflow(State(state): State<AppState>, Json(wir): Json<AnalyzedWir>) -> (StatusCode, String) {
+    let violations: Vec<Violation> = state.workflow_rules.check(&wir);
+    debug!("Workflow '{}' violates {} rule(s)", wir.workflow.id, violations.len());
+
+    match serde_json::to_string(&CheckReport { ok: violations.is_empty(), violations }) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
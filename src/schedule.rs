@@ -0,0 +1,638 @@
+//  SCHEDULE.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 10:02:17
+//  Last edited:
+//    19 Oct 2026, 10:02:17
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements scheduled policy activation. A schedule activates a policy
+//!   version at some point in the future, optionally runs a set of
+//!   deliberation test cases against it, and rolls back to the previously
+//!   active version if any of them does not yield the expected verdict.
+//
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::path::PathBuf;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{Method, StatusCode, Uri};
+use axum::Json;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
+use chrono::{DateTime, Utc};
+use enum_debug::EnumDebug;
+use error_trace::ErrorTrace as _;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::audit::{describe, AuditAction};
+use crate::auth::{verified_subject, AppState, AuthDataType};
+use crate::checker::{default_checker, Checker, DeliberationKind, SelectedChecker};
+use crate::errors::ApiError;
+use crate::events::PolicyEventKind;
+use crate::store::{load_json, save_json_private, StoreError};
+use crate::verdict::Verdict;
+
+
+/***** ERRORS *****/
+/// Defines errors originating from handling schedules.
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// There is no schedule with the given identifier.
+    UnknownSchedule { id: u64 },
+    /// The schedule already ran (or was cancelled).
+    NotScheduled { id: u64, status: ScheduleStatus },
+    /// The schedule is about another checker than the one selected.
+    OtherChecker { id: u64, checker: String },
+    /// Failed to persist the schedules.
+    Store { err: StoreError },
+}
+impl ScheduleError {
+    /// Returns the status code that best represents this error to the client.
+    pub fn status_code(&self) -> StatusCode {
+        use ScheduleError::*;
+        match self {
+            UnknownSchedule { .. } => StatusCode::NOT_FOUND,
+            NotScheduled { .. } | OtherChecker { .. } => StatusCode::CONFLICT,
+            Store { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use ScheduleError::*;
+        match self {
+            UnknownSchedule { id } => write!(f, "Unknown schedule {id}"),
+            NotScheduled { id, status } => write!(f, "Schedule {id} is not scheduled anymore (it is {})", status.variant()),
+            OtherChecker { id, checker } => write!(f, "Schedule {id} is about checker '{checker}'; select that checker to cancel it"),
+            Store { .. } => write!(f, "Failed to persist schedules"),
+        }
+    }
+}
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use ScheduleError::*;
+        match self {
+            UnknownSchedule { .. } | NotScheduled { .. } | OtherChecker { .. } => None,
+            Store { err } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// The verdicts a health check can expect.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpectedVerdict {
+    Allow,
+    Deny,
+}
+impl ExpectedVerdict {
+    /// Returns the verdict as the checker writes it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+/// A deliberation test case that must still yield its expected verdict after a scheduled activation.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HealthCheck {
+    /// The kind of question to ask.
+    pub kind:     DeliberationKind,
+    /// The request to send to the checker, exactly as the Deliberation page would.
+    pub request:  serde_json::Value,
+    /// The verdict that the checker must give.
+    pub expected: ExpectedVerdict,
+}
+
+/// The outcome of running a single [`HealthCheck`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HealthCheckOutcome {
+    /// The verdict that was expected.
+    pub expected: ExpectedVerdict,
+    /// The verdict that the checker actually gave, if any.
+    pub actual:   Option<String>,
+    /// Whether the check passed.
+    pub passed:   bool,
+    /// Why the check could not be run, if it couldn't.
+    pub error:    Option<String>,
+}
+
+/// The state a [`Schedule`] can be in.
+#[derive(Clone, Copy, Debug, Deserialize, EnumDebug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    /// The schedule waits for its time to come.
+    Scheduled,
+    /// The schedule is being executed right now.
+    Running,
+    /// The policy was activated and all health checks passed.
+    Activated,
+    /// The policy was activated, but a health check failed and the previous policy was restored.
+    RolledBack,
+    /// Something went wrong while activating the policy or rolling it back; see the message.
+    Failed,
+    /// The schedule was cancelled before it ran.
+    Cancelled,
+}
+
+/// A scheduled activation of a policy version.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Schedule {
+    /// The identifier of this schedule.
+    pub id: u64,
+    /// The name of the checker to activate the policy on.
+    #[serde(default = "default_checker")]
+    pub checker: String,
+    /// The policy version to activate.
+    pub version: i64,
+    /// When to activate the policy.
+    pub at: DateTime<Utc>,
+    /// The user that scheduled the activation, if known.
+    pub created_by: Option<String>,
+    /// The health checks to run after activation.
+    pub checks: Vec<HealthCheck>,
+    /// The current state of the schedule.
+    pub status: ScheduleStatus,
+    /// The version that was active before this schedule ran, if any.
+    pub previous_version: Option<i64>,
+    /// The outcomes of the health checks, once they ran.
+    pub outcomes: Vec<HealthCheckOutcome>,
+    /// Some human-readable explanation of what happened, once the schedule ran.
+    pub message: Option<String>,
+    /// The body of the request that created this schedule, which is what the audit log records for the (de)activations it causes.
+    #[serde(default)]
+    pub request: String,
+}
+
+/// The tokens with which a schedule acts on behalf of the user that created it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ScheduleTokens {
+    /// The management token used to (de)activate policies.
+    policy: String,
+    /// The deliberation token used to run health checks, if there are any.
+    deliberation: Option<String>,
+}
+
+/// The contents of the [`ScheduleStore`] as persisted on disk.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Schedules {
+    /// The identifier of the next schedule.
+    next_id:   u64,
+    /// All schedules made so far.
+    schedules: Vec<Schedule>,
+    /// The tokens of the schedules that have not run yet. Kept separate so they are never sent back to the client.
+    tokens:    HashMap<u64, ScheduleTokens>,
+}
+
+
+
+/// Keeps track of scheduled activations and persists them to disk.
+///
+/// Since the file holds the tokens of the schedules that have not run yet, only the user running the backend may read it.
+#[derive(Debug)]
+pub struct ScheduleStore {
+    /// The file to which schedules are persisted.
+    path: PathBuf,
+    /// The schedules themselves.
+    schedules: Mutex<Schedules>,
+}
+impl ScheduleStore {
+    /// Constructor for the ScheduleStore that loads any existing schedules from disk.
+    ///
+    /// Schedules that were still running when the server went down are marked as failed, as we don't know how far they got.
+    ///
+    /// # Arguments
+    /// - `path`: The file to which schedules are persisted.
+    ///
+    /// # Returns
+    /// A new ScheduleStore.
+    ///
+    /// # Errors
+    /// This function errors if the file exists but could not be loaded.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let path: PathBuf = path.into();
+        let mut schedules: Schedules = load_json(&path)?;
+        for schedule in &mut schedules.schedules {
+            if schedule.status == ScheduleStatus::Running {
+                warn!("Schedule {} was interrupted while running; marking it as failed", schedule.id);
+                schedule.status = ScheduleStatus::Failed;
+                schedule.message = Some("The server stopped while the schedule was running; check the active policy manually".into());
+                schedules.tokens.remove(&schedule.id);
+            }
+        }
+        Ok(Self { path, schedules: Mutex::new(schedules) })
+    }
+
+    /// Returns all schedules made so far about the given checker, newest first.
+    pub async fn list(&self, checker: &str) -> Vec<Schedule> {
+        self.schedules.lock().await.schedules.iter().rev().filter(|s| s.checker == checker).cloned().collect()
+    }
+
+    /// Schedules a new activation.
+    ///
+    /// # Arguments
+    /// - `checker`: The name of the checker to activate the policy on.
+    /// - `body`: The [`SchedulePostModel`] saying what to activate when, and what to check afterwards.
+    /// - `created_by`: The user that scheduled the activation, if known.
+    /// - `policy`: The management token to (de)activate policies with.
+    /// - `deliberation`: The deliberation token to run the health checks with, if any.
+    /// - `request`: The body of the request that created the schedule.
+    ///
+    /// # Returns
+    /// The new [`Schedule`].
+    ///
+    /// # Errors
+    /// This function errors if we failed to persist the schedule.
+    pub async fn schedule(
+        &self,
+        checker: &str,
+        body: SchedulePostModel,
+        created_by: Option<String>,
+        policy: String,
+        deliberation: Option<String>,
+        request: String,
+    ) -> Result<Schedule, ScheduleError> {
+        let mut schedules = self.schedules.lock().await;
+        let schedule: Schedule = Schedule {
+            id: schedules.next_id,
+            checker: checker.into(),
+            version: body.version,
+            at: body.at,
+            created_by,
+            checks: body.checks,
+            status: ScheduleStatus::Scheduled,
+            previous_version: None,
+            outcomes: vec![],
+            message: None,
+            request,
+        };
+        schedules.next_id += 1;
+        schedules.tokens.insert(schedule.id, ScheduleTokens { policy, deliberation });
+        schedules.schedules.push(schedule.clone());
+        save_json_private(&self.path, &*schedules).map_err(|err| ScheduleError::Store { err })?;
+        info!("Scheduled activation of policy version {} at {} (schedule {})", schedule.version, schedule.at, schedule.id);
+        Ok(schedule)
+    }
+
+    /// Cancels a schedule that has not run yet.
+    ///
+    /// # Arguments
+    /// - `id`: The identifier of the schedule to cancel.
+    /// - `checker`: The name of the selected checker. Must be the one the schedule is about.
+    ///
+    /// # Returns
+    /// The cancelled [`Schedule`].
+    ///
+    /// # Errors
+    /// This function errors if the schedule does not exist, is about another checker, already ran or if we failed to persist the result.
+    pub async fn cancel(&self, id: u64, checker: &str) -> Result<Schedule, ScheduleError> {
+        let mut schedules = self.schedules.lock().await;
+        let schedule: &mut Schedule = match schedules.schedules.iter_mut().find(|s| s.id == id) {
+            Some(schedule) => schedule,
+            None => return Err(ScheduleError::UnknownSchedule { id }),
+        };
+        if schedule.checker != checker {
+            return Err(ScheduleError::OtherChecker { id, checker: schedule.checker.clone() });
+        }
+        if schedule.status != ScheduleStatus::Scheduled {
+            return Err(ScheduleError::NotScheduled { id, status: schedule.status });
+        }
+        schedule.status = ScheduleStatus::Cancelled;
+        let schedule: Schedule = schedule.clone();
+        schedules.tokens.remove(&id);
+        save_json_private(&self.path, &*schedules).map_err(|err| ScheduleError::Store { err })?;
+        info!("Cancelled schedule {id}");
+        Ok(schedule)
+    }
+
+    /// Claims all schedules whose time has come by marking them as running.
+    ///
+    /// # Returns
+    /// The claimed schedules, together with the tokens to execute them with.
+    ///
+    /// # Errors
+    /// This function errors if we failed to persist the claims.
+    async fn claim_due(&self, now: DateTime<Utc>) -> Result<Vec<(Schedule, ScheduleTokens)>, ScheduleError> {
+        let mut schedules = self.schedules.lock().await;
+        let Schedules { schedules: list, tokens, .. } = &mut *schedules;
+
+        let mut due: Vec<(Schedule, ScheduleTokens)> = vec![];
+        for schedule in list.iter_mut().filter(|s| s.status == ScheduleStatus::Scheduled && s.at <= now) {
+            schedule.status = ScheduleStatus::Running;
+            match tokens.remove(&schedule.id) {
+                Some(tok) => due.push((schedule.clone(), tok)),
+                None => {
+                    schedule.status = ScheduleStatus::Failed;
+                    schedule.message = Some("No credentials were stored for this schedule".into());
+                },
+            }
+        }
+        if !due.is_empty() {
+            save_json_private(&self.path, &*schedules).map_err(|err| ScheduleError::Store { err })?;
+        }
+        Ok(due)
+    }
+
+    /// Records the result of running a schedule.
+    ///
+    /// # Errors
+    /// This function errors if we failed to persist the result.
+    async fn finish(&self, result: Schedule) -> Result<(), ScheduleError> {
+        let mut schedules = self.schedules.lock().await;
+        if let Some(schedule) = schedules.schedules.iter_mut().find(|s| s.id == result.id) {
+            *schedule = result;
+        }
+        save_json_private(&self.path, &*schedules).map_err(|err| ScheduleError::Store { err })
+    }
+}
+
+
+
+/// The body sent by the client to schedule an activation.
+#[derive(Deserialize, Serialize)]
+pub struct SchedulePostModel {
+    /// The policy version to activate.
+    pub version: i64,
+    /// When to activate it.
+    pub at: DateTime<Utc>,
+    /// The health checks to run after activation.
+    #[serde(default)]
+    pub checks: Vec<HealthCheck>,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Runs a single health check against the currently active policy.
+///
+/// # Arguments
+/// - `checker`: The [`Checker`] to ask.
+/// - `token`: The deliberation token to authenticate ourselves with.
+/// - `check`: The [`HealthCheck`] to run.
+///
+/// # Returns
+/// The [`HealthCheckOutcome`] of the check.
+async fn run_check(checker: &Checker, token: &str, check: &HealthCheck) -> HealthCheckOutcome {
+    let verdict: Result<String, String> = match checker.deliberate(token, check.kind, check.request.to_string()).await {
+        Ok(res) => Verdict::parse(&res.body).map(|v| v.as_str().into()).map_err(|_| format!("Checker response has no verdict: {}", res.body)),
+        Err(err) => Err(err.trace().to_string()),
+    };
+
+    match verdict {
+        Ok(verdict) => HealthCheckOutcome { expected: check.expected, passed: verdict == check.expected.as_str(), actual: Some(verdict), error: None },
+        Err(err) => HealthCheckOutcome { expected: check.expected, actual: None, passed: false, error: Some(err) },
+    }
+}
+
+/// Executes a claimed schedule.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the checkers to activate the policy on and the audit log to record that in.
+/// - `schedule`: The [`Schedule`] to execute. Will be updated with the results.
+/// - `tokens`: The tokens to execute the schedule with.
+async fn execute(state: &AppState, schedule: &mut Schedule, tokens: ScheduleTokens) {
+    let checker: &Checker = match state.checkers.get(Some(&schedule.checker)) {
+        Ok(checker) => checker,
+        Err(err) => {
+            error!("Schedule {}: {}", schedule.id, err.trace());
+            schedule.status = ScheduleStatus::Failed;
+            schedule.message = Some(format!("Cannot activate the policy: {err}"));
+            return;
+        },
+    };
+    info!("Executing schedule {} (activating policy version {})", schedule.id, schedule.version);
+
+    // Remember what to roll back to
+    schedule.previous_version = match checker.get_active_version(&tokens.policy).await {
+        Ok(version) => version,
+        Err(err) => {
+            error!("Schedule {}: {}", schedule.id, err.trace());
+            schedule.status = ScheduleStatus::Failed;
+            schedule.message = Some(format!("Failed to retrieve the active policy before activation: {err}"));
+            return;
+        },
+    };
+
+    // Activate the new version
+    let result = checker.activate_policy(&tokens.policy, schedule.version).await;
+    state
+        .audit
+        .record(
+            Some(&tokens.policy),
+            &checker.name,
+            AuditAction::ScheduledActivation,
+            Some(schedule.version),
+            Some(&result),
+            schedule.request.as_bytes(),
+        )
+        .await;
+    state.events.changed(&checker.name, PolicyEventKind::Activated, Some(schedule.version), Some(&tokens.policy), &result).await;
+    if let Err(err) = result {
+        error!("Schedule {}: {}", schedule.id, err.trace());
+        schedule.status = ScheduleStatus::Failed;
+        schedule.message = Some(format!("Failed to activate policy version {}: {err}", schedule.version));
+        return;
+    }
+
+    // Run the health checks
+    if let Some(token) = &tokens.deliberation {
+        for check in &schedule.checks {
+            schedule.outcomes.push(run_check(checker, token, check).await);
+        }
+    }
+    let failed: usize = schedule.outcomes.iter().filter(|o| !o.passed).count();
+    if failed == 0 {
+        info!("Schedule {}: activated policy version {}", schedule.id, schedule.version);
+        schedule.status = ScheduleStatus::Activated;
+        schedule.message = Some(format!("Activated policy version {}", schedule.version));
+        return;
+    }
+
+    // Roll back to what was there before
+    warn!("Schedule {}: {} out of {} health check(s) failed; rolling back", schedule.id, failed, schedule.outcomes.len());
+    let rollback = match schedule.previous_version {
+        Some(previous) => checker.activate_policy(&tokens.policy, previous).await,
+        None => checker.deactivate_policy(&tokens.policy).await,
+    };
+    state
+        .audit
+        .record(
+            Some(&tokens.policy),
+            &checker.name,
+            AuditAction::ScheduledRollback,
+            schedule.previous_version,
+            Some(&rollback),
+            schedule.request.as_bytes(),
+        )
+        .await;
+    let kind: PolicyEventKind = if schedule.previous_version.is_some() { PolicyEventKind::Activated } else { PolicyEventKind::Deactivated };
+    state.events.changed(&checker.name, kind, schedule.previous_version, Some(&tokens.policy), &rollback).await;
+    match rollback {
+        Ok(_) => {
+            schedule.status = ScheduleStatus::RolledBack;
+            schedule.message = Some(match schedule.previous_version {
+                Some(previous) => format!("{failed} health check(s) failed; re-activated policy version {previous}"),
+                None => format!("{failed} health check(s) failed; deactivated the policy again"),
+            });
+        },
+        Err(err) => {
+            error!("Schedule {}: {}", schedule.id, err.trace());
+            schedule.status = ScheduleStatus::Failed;
+            schedule.message = Some(format!("{failed} health check(s) failed, but rolling back failed too: {err}"));
+        },
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Executes all schedules whose time has come.
+///
+/// This is what [`run_scheduler()`] does every interval.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the schedules and the checkers to act on.
+pub async fn run_due(state: &AppState) {
+    let due: Vec<(Schedule, ScheduleTokens)> = match state.schedules.claim_due(Utc::now()).await {
+        Ok(due) => due,
+        Err(err) => {
+            error!("{}", err.trace());
+            return;
+        },
+    };
+    for (mut schedule, tokens) in due {
+        execute(state, &mut schedule, tokens).await;
+        if let Err(err) = state.schedules.finish(schedule).await {
+            error!("{}", err.trace());
+        }
+    }
+}
+
+/// Runs the scheduler in the background, executing schedules as their time comes.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the schedules and the checkers to act on.
+/// - `interval`: How often to look for schedules that are due. Anything shorter than a second is taken as a second.
+pub async fn run_scheduler(state: AppState, interval: std::time::Duration) {
+    let interval: std::time::Duration = interval.max(std::time::Duration::from_secs(1));
+    debug!("Starting scheduler (interval: {}s)", interval.as_secs_f32());
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        run_due(&state).await;
+    }
+}
+
+
+
+// List schedules
+pub async fn get_schedules(State(state): State<AppState>, checker: SelectedChecker, jar: PrivateCookieJar) -> (StatusCode, String) {
+    if jar.get(&AuthDataType::Policy.cookie(&checker.name)).is_none() {
+        return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+    }
+
+    match serde_json::to_string(&state.schedules.list(&checker.name).await) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+// Schedule an activation
+pub async fn post_schedule(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    raw: Bytes,
+) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    // Parse the body ourselves, as the audit log needs it exactly as it was sent
+    let body: SchedulePostModel = match Json::from_bytes(&raw) {
+        Ok(Json(body)) => body,
+        Err(err) => return ApiError::new(err.status(), err.body_text()).into_parts(),
+    };
+    if state.four_eyes {
+        return err!(
+            StatusCode::FORBIDDEN,
+            StringError("Policies can only be activated by proposing it at '/api/proposals' and having another user approve it".into())
+        );
+    }
+    let deliberation: Option<String> = jar.get(&AuthDataType::Deliberation.cookie(&checker.name)).map(|c| c.value().into());
+    if !body.checks.is_empty() && deliberation.is_none() {
+        return err!(StatusCode::UNAUTHORIZED, StringError("Health checks require a deliberation token".into()));
+    }
+
+    let user: String = match verified_subject(&checker, policy_auth_token.value()).await {
+        Ok(user) => user,
+        Err(err) => return err.into_parts(),
+    };
+
+    let token: String = policy_auth_token.value().into();
+    let request: String = String::from_utf8_lossy(&raw).into_owned();
+    match state.schedules.schedule(&checker.name, body, Some(user.clone()), token, deliberation, request).await {
+        Ok(schedule) => {
+            state.audit.record_by(&user, &checker.name, AuditAction::ScheduleActivation, Some(schedule.version), None, &raw).await;
+            match serde_json::to_string(&schedule) {
+                Ok(body) => (StatusCode::OK, body),
+                Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+            }
+        },
+        Err(err) => err!(err.status_code(), err),
+    }
+}
+
+// Cancel a schedule
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    checker: SelectedChecker,
+    jar: PrivateCookieJar,
+    Path(id): Path<u64>,
+    method: Method,
+    uri: Uri,
+) -> (StatusCode, String) {
+    let policy_auth_token = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(data) => data,
+        None => {
+            return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+        },
+    };
+    let user: String = match verified_subject(&checker, policy_auth_token.value()).await {
+        Ok(user) => user,
+        Err(err) => return err.into_parts(),
+    };
+
+    match state.schedules.cancel(id, &checker.name).await {
+        Ok(schedule) => {
+            state.audit.record_by(&user, &checker.name, AuditAction::CancelSchedule, Some(schedule.version), None, &describe(&method, &uri)).await;
+            match serde_json::to_string(&schedule) {
+                Ok(body) => (StatusCode::OK, body),
+                Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+            }
+        },
+        Err(err) => err!(err.status_code(), err),
+    }
+}
@@ -0,0 +1,142 @@
+//  STORE.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 09:31:02
+//  Last edited:
+//    19 Oct 2026, 09:31:02
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements helpers for persisting the backend's own (small) state to
+//!   JSON files on disk.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::fs::{File, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+
+/***** ERRORS *****/
+/// Defines errors originating from loading or saving persisted state.
+#[derive(Debug)]
+pub enum StoreError {
+    /// Failed to read the file backing a store.
+    Read { path: PathBuf, err: std::io::Error },
+    /// Failed to parse the file backing a store.
+    Parse { path: PathBuf, err: serde_json::Error },
+    /// Failed to serialize the contents of a store.
+    Serialize { path: PathBuf, err: serde_json::Error },
+    /// Failed to write the file backing a store.
+    Write { path: PathBuf, err: std::io::Error },
+}
+impl Display for StoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use StoreError::*;
+        match self {
+            Read { path, .. } => write!(f, "Failed to read store file '{}'", path.display()),
+            Parse { path, .. } => write!(f, "Failed to parse store file '{}'", path.display()),
+            Serialize { path, .. } => write!(f, "Failed to serialize contents of store file '{}'", path.display()),
+            Write { path, .. } => write!(f, "Failed to write store file '{}'", path.display()),
+        }
+    }
+}
+impl Error for StoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use StoreError::*;
+        match self {
+            Read { err, .. } => Some(err),
+            Parse { err, .. } => Some(err),
+            Serialize { err, .. } => Some(err),
+            Write { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Loads some state from the given JSON file.
+///
+/// # Arguments
+/// - `path`: The path of the file to load from.
+///
+/// # Returns
+/// The parsed state, or its [`Default`] if the file does not exist (yet).
+///
+/// # Errors
+/// This function errors if the file exists but could not be read or parsed.
+pub fn load_json<T: Default + DeserializeOwned>(path: &Path) -> Result<T, StoreError> {
+    debug!("Loading store file '{}'", path.display());
+    let raw: String = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            debug!("Store file '{}' does not exist; starting empty", path.display());
+            return Ok(T::default());
+        },
+        Err(err) => return Err(StoreError::Read { path: path.into(), err }),
+    };
+    serde_json::from_str(&raw).map_err(|err| StoreError::Parse { path: path.into(), err })
+}
+
+/// Saves some state to the given JSON file.
+///
+/// The state is first written to a sibling file and then moved over the old one, so a crash halfway never leaves a truncated store behind.
+///
+/// # Arguments
+/// - `path`: The path of the file to save to.
+/// - `state`: The state to save.
+///
+/// # Errors
+/// This function errors if we failed to serialize the state or write it to disk.
+#[inline]
+pub fn save_json<T: Serialize>(path: &Path, state: &T) -> Result<(), StoreError> { save(path, state, false) }
+
+/// Saves some state with secrets in it (e.g., tokens) to the given JSON file, which only the user running the backend may read.
+///
+/// Like [`save_json()`], the file is replaced in one go.
+///
+/// # Arguments
+/// - `path`: The path of the file to save to.
+/// - `state`: The state to save.
+///
+/// # Errors
+/// This function errors if we failed to serialize the state or write it to disk.
+#[inline]
+pub fn save_json_private<T: Serialize>(path: &Path, state: &T) -> Result<(), StoreError> { save(path, state, true) }
+
+/// Implements [`save_json()`] and [`save_json_private()`].
+fn save<T: Serialize>(path: &Path, state: &T, private: bool) -> Result<(), StoreError> {
+    debug!("Saving store file '{}'", path.display());
+    let raw: String = serde_json::to_string_pretty(state).map_err(|err| StoreError::Serialize { path: path.into(), err })?;
+
+    // Write to a temporary file first, then swap it in
+    let mut tmp: PathBuf = path.into();
+    tmp.as_mut_os_string().push(".tmp");
+    let mut opts: OpenOptions = OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt as _;
+        opts.mode(0o600);
+    }
+    let mut handle: File = opts.open(&tmp).map_err(|err| StoreError::Write { path: tmp.clone(), err })?;
+    // The mode only applies to new files, so make sure a leftover one is restricted too
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt as _;
+        handle.set_permissions(std::fs::Permissions::from_mode(0o600)).map_err(|err| StoreError::Write { path: tmp.clone(), err })?;
+    }
+    handle.write_all(raw.as_bytes()).map_err(|err| StoreError::Write { path: tmp.clone(), err })?;
+    drop(handle);
+    std::fs::rename(&tmp, path).map_err(|err| StoreError::Write { path: path.into(), err })
+}
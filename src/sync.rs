@@ -0,0 +1,476 @@
+//  SYNC.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 20:41:09
+//  Last edited:
+//    19 Oct 2026, 20:41:09
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements comparing the policies active on the different checkers,
+//!   and pushing a policy from one checker to the others.
+//
+
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_tsk::errors::StringError;
+use futures::future::join_all;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::audit::AuditAction;
+use crate::auth::{has_any_token, verified_subject, AppState, AuthDataType};
+use crate::checker::{Checker, CheckerError};
+use crate::conv::to_eflint;
+use crate::errors::ApiError;
+use crate::events::PolicyEventKind;
+
+
+/***** AUXILLARY *****/
+/// A single eFLINT phrase that differs between two policies.
+#[derive(Clone, Debug, Serialize)]
+pub struct PhraseDiff {
+    /// The kind of phrase (e.g., `afact` or `predicate`).
+    pub kind:  Option<String>,
+    /// The name the phrase declares, if it declares one.
+    pub name:  Option<String>,
+    /// The phrase as it is in the base policy, rendered as eFLINT, if it is there.
+    pub base:  Option<String>,
+    /// The phrase as it is in the other policy, rendered as eFLINT, if it is there.
+    pub other: Option<String>,
+}
+
+/// The semantic difference between two policies, phrase by phrase.
+///
+/// Phrases that declare something are matched by what they declare, the others by their contents. The order of phrases is ignored.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PolicyDiff {
+    /// Whether the policies consist of the same phrases.
+    pub identical: bool,
+    /// The phrases only in the other policy.
+    pub added:     Vec<PhraseDiff>,
+    /// The phrases only in the base policy.
+    pub removed:   Vec<PhraseDiff>,
+    /// The declarations that are in both, but differ.
+    pub changed:   Vec<PhraseDiff>,
+    /// The number of phrases that are in both policies alike.
+    pub unchanged: usize,
+}
+
+/// The policy active on a single checker, compared to the base.
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckerComparison {
+    /// The name of the checker.
+    pub checker: String,
+    /// The version of the policy active on it, if any.
+    pub version: Option<i64>,
+    /// Why its policy could not be compared, if it couldn't.
+    pub problem: Option<String>,
+    /// How its policy differs from the base, if it could be compared.
+    pub diff:    Option<PolicyDiff>,
+}
+
+/// The policies active on all checkers, compared to the one on the base checker.
+#[derive(Clone, Debug, Serialize)]
+pub struct Comparison {
+    /// The name of the checker that is compared against.
+    pub base:     String,
+    /// The version of the policy active on the base checker, if any.
+    pub version:  Option<i64>,
+    /// Every other checker.
+    pub checkers: Vec<CheckerComparison>,
+}
+
+/// The options of a comparison.
+#[derive(Debug, Default, Deserialize)]
+pub struct CompareQuery {
+    /// The checker to compare against. Defaults to the default checker.
+    pub base: Option<String>,
+}
+
+
+
+/// The body sent by the client to push a policy to other checkers.
+#[derive(Debug, Deserialize)]
+pub struct SyncPostModel {
+    /// The checker to copy the policy from.
+    pub from:     String,
+    /// The version to copy. Defaults to the one active on `from`.
+    pub version:  Option<i64>,
+    /// The checkers to copy the policy to. Defaults to all others.
+    #[serde(default)]
+    pub to:       Vec<String>,
+    /// Whether to also activate the policy (or propose to, if activation requires approval).
+    #[serde(default = "default_true")]
+    pub activate: bool,
+    /// If true, only reports what would happen.
+    #[serde(default)]
+    pub dry_run:  bool,
+}
+
+/// What happened (or would happen) to a checker while synchronizing.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// The checker already has the same policy active; nothing was done.
+    UpToDate,
+    /// It is a dry run; the policy would have been pushed.
+    WouldPush,
+    /// The policy was added, but not activated.
+    Added,
+    /// The policy was added and activated.
+    Activated,
+    /// The policy was added and its activation proposed.
+    Proposed,
+    /// Something went wrong; see the message.
+    Failed,
+}
+
+/// The result of synchronizing a single checker.
+#[derive(Clone, Debug, Serialize)]
+pub struct SyncResult {
+    /// The name of the checker.
+    pub checker:  String,
+    /// What happened.
+    pub status:   SyncStatus,
+    /// How the policy active on the checker (before synchronizing) differs from the pushed one, if known.
+    pub diff:     Option<PolicyDiff>,
+    /// The version the policy got on the checker, if it was added.
+    pub version:  Option<i64>,
+    /// The identifier of the activation proposal, if one was made.
+    pub proposal: Option<u64>,
+    /// What went wrong, if anything did.
+    pub message:  Option<String>,
+}
+impl SyncResult {
+    /// Constructor for a SyncResult that failed.
+    fn failed(checker: &str, diff: Option<PolicyDiff>, version: Option<i64>, message: String) -> Self {
+        Self { checker: checker.into(), status: SyncStatus::Failed, diff, version, proposal: None, message: Some(message) }
+    }
+}
+
+/// The result of synchronizing all checkers.
+#[derive(Clone, Debug, Serialize)]
+pub struct SyncReport {
+    /// The checker the policy was copied from.
+    pub from:    String,
+    /// The version that was copied.
+    pub version: i64,
+    /// Whether this was a dry run.
+    pub dry_run: bool,
+    /// The result per checker.
+    pub results: Vec<SyncResult>,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Serde default for fields that are `true` unless said otherwise.
+#[inline]
+fn default_true() -> bool { true }
+
+/// Retrieves a policy from a checker.
+///
+/// # Arguments
+/// - `checker`: The [`Checker`] to retrieve the policy from.
+/// - `token`: The management token to authenticate with.
+/// - `version`: The version to retrieve, or [`None`] for the active one.
+///
+/// # Returns
+/// The policy, or [`None`] if `version` is [`None`] and no policy is active.
+///
+/// # Errors
+/// This function errors if we failed to retrieve the policy.
+async fn fetch(checker: &Checker, token: &str, version: Option<i64>) -> Result<Option<Value>, CheckerError> {
+    let res = match version {
+        Some(version) => checker.get_policy(token, version).await,
+        None => match checker.get_active_policy(token).await {
+            Err(CheckerError::Status { code: 404, .. }) => return Ok(None),
+            res => res,
+        },
+    };
+    let body: String = res?.body;
+    serde_json::from_str(&body).map(Some).map_err(|err| CheckerError::Parse { url: format!("{}/v1/management/policies", checker.address), err })
+}
+
+/// Returns the eFLINT JSON phrases of a policy.
+///
+/// # Arguments
+/// - `policy`: The policy as returned by the checker, or [`None`] if there is none.
+///
+/// # Returns
+/// The phrases, which is empty if there is no policy or it has no eFLINT content.
+fn phrases(policy: Option<&Value>) -> Vec<Value> {
+    policy
+        .and_then(|p| p.get("content"))
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("content"))
+        .and_then(|c| c.get("phrases"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Returns what a phrase declares, if it declares anything.
+fn declares(phrase: &Value) -> Option<(&str, &str)> {
+    Some((phrase.get("kind")?.as_str()?, phrase.get("name")?.as_str()?))
+}
+
+/// Renders a single phrase as eFLINT, falling back to its JSON if that fails.
+async fn render(phrase: &Value) -> String {
+    let req: Value = json!({ "version": "0.1.0", "kind": "phrases", "phrases": [phrase], "updates": true });
+    to_eflint(req.to_string()).await.unwrap_or_else(|_| phrase.to_string())
+}
+
+/// Describes a phrase that is in either (or both) of two policies.
+async fn describe(base: Option<&Value>, other: Option<&Value>) -> PhraseDiff {
+    let phrase: Option<&Value> = base.or(other);
+    PhraseDiff {
+        kind:  phrase.and_then(|p| p.get("kind")).and_then(Value::as_str).map(String::from),
+        name:  phrase.and_then(|p| p.get("name")).and_then(Value::as_str).map(String::from),
+        base:  match base {
+            Some(base) => Some(render(base).await),
+            None => None,
+        },
+        other: match other {
+            Some(other) => Some(render(other).await),
+            None => None,
+        },
+    }
+}
+
+/// Computes the semantic difference between two lists of eFLINT JSON phrases.
+///
+/// # Arguments
+/// - `base`: The phrases of the base policy.
+/// - `other`: The phrases of the policy to compare with it.
+///
+/// # Returns
+/// The [`PolicyDiff`] between them.
+async fn diff(base: &[Value], other: &[Value]) -> PolicyDiff {
+    let mut diff: PolicyDiff = PolicyDiff::default();
+    let mut matched: Vec<bool> = vec![false; other.len()];
+    for b in base {
+        // Find the same phrase, or else the same declaration
+        let found: Option<usize> = (0..other.len()).find(|i| !matched[*i] && other[*i] == *b).or_else(|| {
+            let declared = declares(b)?;
+            (0..other.len()).find(|i| !matched[*i] && declares(&other[*i]) == Some(declared))
+        });
+        match found {
+            Some(i) => {
+                matched[i] = true;
+                if other[i] == *b {
+                    diff.unchanged += 1;
+                } else {
+                    diff.changed.push(describe(Some(b), Some(&other[i])).await);
+                }
+            },
+            None => diff.removed.push(describe(Some(b), None).await),
+        }
+    }
+    for (o, _) in other.iter().zip(matched).filter(|(_, matched)| !matched) {
+        diff.added.push(describe(None, Some(o)).await);
+    }
+    diff.identical = diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty();
+    diff
+}
+
+/// Pushes a policy to a single checker.
+///
+/// # Arguments
+/// - `state`: The [`AppState`] with the audit log and proposals.
+/// - `jar`: The cookies of the user, holding their tokens.
+/// - `checker`: The [`Checker`] to push to.
+/// - `policy`: The policy to push, as returned by the source checker.
+/// - `body`: The options of the synchronization.
+/// - `request`: The raw synchronization request, which is what the audit log records for the actions taken.
+///
+/// # Returns
+/// The [`SyncResult`] of this checker.
+async fn push(state: &AppState, jar: &PrivateCookieJar, checker: &Checker, policy: &Value, body: &SyncPostModel, request: &[u8]) -> SyncResult {
+    let token: String = match jar.get(&AuthDataType::Policy.cookie(&checker.name)) {
+        Some(token) => token.value().into(),
+        None => return SyncResult::failed(&checker.name, None, None, format!("Not logged in to checker '{}'", checker.name)),
+    };
+
+    // See if there is anything to do
+    let active: Option<Value> = match fetch(checker, &token, None).await {
+        Ok(active) => active,
+        Err(err) => return SyncResult::failed(&checker.name, None, None, err.to_string()),
+    };
+    let diff: PolicyDiff = diff(&phrases(active.as_ref()), &phrases(Some(policy))).await;
+    if diff.identical && active.is_some() {
+        return SyncResult { checker: checker.name.clone(), status: SyncStatus::UpToDate, diff: Some(diff), version: None, proposal: None, message: None };
+    }
+    if body.dry_run {
+        return SyncResult { checker: checker.name.clone(), status: SyncStatus::WouldPush, diff: Some(diff), version: None, proposal: None, message: None };
+    }
+
+    // Add it...
+    let version_description: String = format!(
+        "{} (copied from version {} on checker '{}')",
+        policy.get("version_description").and_then(Value::as_str).unwrap_or_default(),
+        policy.get("version").and_then(Value::as_i64).unwrap_or_default(),
+        body.from
+    );
+    let policy_request: Value = json!({
+        "description": policy.get("description").cloned().unwrap_or(Value::String(String::new())),
+        "version_description": version_description.trim_start(),
+        "content": policy.get("content").cloned().unwrap_or(Value::Array(vec![])),
+    });
+    let result = checker.add_policy(&token, policy_request.to_string()).await;
+    let version: Option<i64> = result
+        .as_ref()
+        .ok()
+        .and_then(|res| serde_json::from_str::<Value>(&res.body).ok())
+        .and_then(|policy| policy.get("version").and_then(Value::as_i64));
+    state.audit.record(Some(&token), &checker.name, AuditAction::AddPolicy, version, Some(&result), request).await;
+    state.events.changed(&checker.name, PolicyEventKind::Added, version, Some(&token), &result).await;
+    let version: i64 = match (result, version) {
+        (Ok(_), Some(version)) => version,
+        (Ok(res), None) => return SyncResult::failed(&checker.name, Some(diff), None, format!("Checker did not say which version it assigned: {}", res.body)),
+        (Err(err), _) => return SyncResult::failed(&checker.name, Some(diff), None, err.to_string()),
+    };
+    info!("Copied policy version {} from checker '{}' to checker '{}' as version {}", policy["version"], body.from, checker.name, version);
+    if !body.activate {
+        return SyncResult { checker: checker.name.clone(), status: SyncStatus::Added, diff: Some(diff), version: Some(version), proposal: None, message: None };
+    }
+
+    // ...and activate it (or propose to)
+    if state.four_eyes {
+        let user: String = match verified_subject(checker, &token).await {
+            Ok(user) => user,
+            Err(err) => return SyncResult::failed(&checker.name, Some(diff), Some(version), err.message),
+        };
+        return match state.proposals.propose(&checker.name, version, user).await {
+            Ok(proposal) => {
+                state.audit.record_by(&proposal.proposed_by, &checker.name, AuditAction::ProposeActivation, Some(version), None, request).await;
+                SyncResult {
+                    checker:  checker.name.clone(),
+                    status:   SyncStatus::Proposed,
+                    diff:     Some(diff),
+                    version:  Some(version),
+                    proposal: Some(proposal.id),
+                    message:  None,
+                }
+            },
+            Err(err) => SyncResult::failed(&checker.name, Some(diff), Some(version), err.to_string()),
+        };
+    }
+    let result = checker.activate_policy(&token, version).await;
+    state.audit.record(Some(&token), &checker.name, AuditAction::ActivatePolicy, Some(version), Some(&result), request).await;
+    state.events.changed(&checker.name, PolicyEventKind::Activated, Some(version), Some(&token), &result).await;
+    match result {
+        Ok(_) => SyncResult { checker: checker.name.clone(), status: SyncStatus::Activated, diff: Some(diff), version: Some(version), proposal: None, message: None },
+        Err(err) => SyncResult::failed(&checker.name, Some(diff), Some(version), err.to_string()),
+    }
+}
+
+
+
+
+
+/***** LIBRARY *****/
+// Compare the active policies of all checkers
+pub async fn get_compare_policies(State(state): State<AppState>, jar: PrivateCookieJar, Query(query): Query<CompareQuery>) -> (StatusCode, String) {
+    let base: &Checker = match state.checkers.get(query.base.as_deref()) {
+        Ok(base) => base,
+        Err(err) => return checker_err!(err),
+    };
+    let token = match jar.get(&AuthDataType::Policy.cookie(&base.name)) {
+        Some(token) => token,
+        None => return err!(StatusCode::UNAUTHORIZED, StringError(format!("Not logged in to checker '{}'", base.name))),
+    };
+    let policy: Option<Value> = match fetch(base, token.value(), None).await {
+        Ok(policy) => policy,
+        Err(err) => return checker_err!(err),
+    };
+    let version: Option<i64> = policy.as_ref().and_then(|p| p.get("version")).and_then(Value::as_i64);
+    let base_phrases: Vec<Value> = phrases(policy.as_ref());
+    debug!("Comparing policy version {:?} of checker '{}' with the other checkers", version, base.name);
+
+    // Compare it with all the others at the same time
+    let checkers: Vec<CheckerComparison> = join_all(state.checkers.iter().filter(|c| c.name != base.name).map(|checker| {
+        let (jar, base_phrases) = (&jar, &base_phrases);
+        async move {
+            let mut comparison: CheckerComparison = CheckerComparison { checker: checker.name.clone(), version: None, problem: None, diff: None };
+            let Some(token) = jar.get(&AuthDataType::Policy.cookie(&checker.name)) else {
+                comparison.problem = Some(format!("Not logged in to checker '{}'", checker.name));
+                return comparison;
+            };
+            match fetch(checker, token.value(), None).await {
+                Ok(policy) => {
+                    comparison.version = policy.as_ref().and_then(|p| p.get("version")).and_then(Value::as_i64);
+                    comparison.diff = Some(diff(base_phrases, &phrases(policy.as_ref())).await);
+                },
+                Err(err) => comparison.problem = Some(err.to_string()),
+            }
+            comparison
+        }
+    }))
+    .await;
+
+    match serde_json::to_string(&Comparison { base: base.name.clone(), version, checkers }) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+// Push a policy from one checker to others
+pub async fn post_sync_policies(State(state): State<AppState>, jar: PrivateCookieJar, raw: Bytes) -> (StatusCode, String) {
+    if !has_any_token(&jar, &state, AuthDataType::Policy) {
+        return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+    }
+    // Parse the body ourselves, as the audit log needs it exactly as it was sent
+    let body: SyncPostModel = match Json::from_bytes(&raw) {
+        Ok(Json(body)) => body,
+        Err(err) => return ApiError::new(err.status(), err.body_text()).into_parts(),
+    };
+
+    // Find the policy to push
+    let source: &Checker = match state.checkers.get(Some(&body.from)) {
+        Ok(source) => source,
+        Err(err) => return checker_err!(err),
+    };
+    let token = match jar.get(&AuthDataType::Policy.cookie(&source.name)) {
+        Some(token) => token,
+        None => return err!(StatusCode::UNAUTHORIZED, StringError(format!("Not logged in to checker '{}'", source.name))),
+    };
+    let policy: Value = match fetch(source, token.value(), body.version).await {
+        Ok(Some(policy)) => policy,
+        Ok(None) => return err!(StatusCode::NOT_FOUND, StringError(format!("Checker '{}' has no active policy to copy", source.name))),
+        Err(err) => return checker_err!(err),
+    };
+    let version: i64 = policy.get("version").and_then(Value::as_i64).or(body.version).unwrap_or_default();
+
+    // Find where to push it to
+    let mut targets: Vec<&Checker> = vec![];
+    if body.to.is_empty() {
+        targets.extend(state.checkers.iter().filter(|c| c.name != source.name));
+    }
+    for name in &body.to {
+        match state.checkers.get(Some(name)) {
+            Ok(target) if target.name == source.name => {
+                return err!(StatusCode::BAD_REQUEST, StringError(format!("Cannot copy a policy from checker '{name}' to itself")));
+            },
+            Ok(target) => targets.push(target),
+            Err(err) => return checker_err!(err),
+        }
+    }
+    debug!("Pushing policy version {} of checker '{}' to {} checker(s){}", version, source.name, targets.len(), if body.dry_run { " (dry run)" } else { "" });
+
+    // Push it everywhere at the same time
+    let results: Vec<SyncResult> = join_all(targets.into_iter().map(|target| push(&state, &jar, target, &policy, &body, &raw))).await;
+    match serde_json::to_string(&SyncReport { from: source.name.clone(), version, dry_run: body.dry_run, results }) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
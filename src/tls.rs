@@ -0,0 +1,144 @@
+//  TLS.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 19:05:37
+//  Last edited:
+//    19 Oct 2026, 19:05:37
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements serving HTTPS, including reloading the certificate when it
+//!   changes on disk and redirecting plain HTTP to HTTPS.
+//
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use axum::extract::Request;
+use axum::http::header::HOST;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse as _, Redirect};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use error_trace::ErrorTrace as _;
+use log::{debug, error, info};
+
+use crate::config::TlsConfig;
+use crate::errors::ApiError;
+
+
+/***** ERRORS *****/
+/// Defines errors originating from loading certificates.
+#[derive(Debug)]
+pub enum TlsError {
+    /// Failed to load the certificate or key.
+    Load { cert: PathBuf, key: PathBuf, err: std::io::Error },
+}
+impl Display for TlsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use TlsError::*;
+        match self {
+            Load { cert, key, .. } => write!(f, "Failed to load TLS certificate '{}' and key '{}'", cert.display(), key.display()),
+        }
+    }
+}
+impl Error for TlsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use TlsError::*;
+        match self {
+            Load { err, .. } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Returns when the given file was last modified.
+///
+/// # Arguments
+/// - `path`: The path of the file.
+///
+/// # Returns
+/// The time the file was last modified, or [`None`] if we could not find out.
+fn modified(path: &Path) -> Option<SystemTime> { std::fs::metadata(path).and_then(|meta| meta.modified()).ok() }
+
+
+
+
+
+/***** LIBRARY *****/
+/// Loads the certificate and key to serve HTTPS with.
+///
+/// # Arguments
+/// - `tls`: The [`TlsConfig`] that tells us where the certificate and key are.
+///
+/// # Returns
+/// A [`RustlsConfig`] that can be used to serve HTTPS and to reload the certificate with later.
+///
+/// # Errors
+/// This function errors if the certificate or key could not be read or are invalid.
+pub async fn load(tls: &TlsConfig) -> Result<RustlsConfig, TlsError> {
+    debug!("Loading TLS certificate '{}' and key '{}'", tls.cert.display(), tls.key.display());
+    RustlsConfig::from_pem_file(&tls.cert, &tls.key).await.map_err(|err| TlsError::Load { cert: tls.cert.clone(), key: tls.key.clone(), err })
+}
+
+/// Reloads the certificate and key whenever either of them changes on disk.
+///
+/// Runs forever, so spawn it in the background. If reloading fails, the previous certificate is kept and the error is logged.
+///
+/// # Arguments
+/// - `rustls`: The [`RustlsConfig`] that is being served with.
+/// - `tls`: The [`TlsConfig`] that tells us where the certificate and key are and how often to check them.
+pub async fn watch(rustls: RustlsConfig, tls: TlsConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(tls.reload_interval.max(1)));
+    let mut last: (Option<SystemTime>, Option<SystemTime>) = (modified(&tls.cert), modified(&tls.key));
+    loop {
+        interval.tick().await;
+        let current: (Option<SystemTime>, Option<SystemTime>) = (modified(&tls.cert), modified(&tls.key));
+        if current == last {
+            continue;
+        }
+        last = current;
+
+        match rustls.reload_from_pem_file(&tls.cert, &tls.key).await {
+            Ok(_) => info!("Reloaded TLS certificate '{}' and key '{}'", tls.cert.display(), tls.key.display()),
+            Err(err) => {
+                let err = TlsError::Load { cert: tls.cert.clone(), key: tls.key.clone(), err };
+                error!("{} (keeping the previous certificate)", err.trace());
+            },
+        }
+    }
+}
+
+/// Builds the [`Router`] that redirects every plain HTTP request to HTTPS.
+///
+/// # Arguments
+/// - `https_port`: The port on which HTTPS is served.
+///
+/// # Returns
+/// A new [`Router`] that is ready to be served.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |req: Request| async move {
+        // Find out where the client meant to go
+        let host: &str = match req.headers().get(HOST).and_then(|host| host.to_str().ok()) {
+            Some(host) => host,
+            None => return ApiError::new(StatusCode::BAD_REQUEST, "Missing Host header").into_response(),
+        };
+        let host: &str = match host.rsplit_once(':') {
+            // Only strip a port, not part of an IPv6 address
+            Some((host, port)) if !port.contains(']') => host,
+            _ => host,
+        };
+        let path: &str = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+        let target: String = if https_port == 443 { format!("https://{host}{path}") } else { format!("https://{host}:{https_port}{path}") };
+        Redirect::permanent(&target).into_response()
+    })
+}
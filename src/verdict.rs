@@ -0,0 +1,457 @@
+//  VERDICT.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 14:02:11
+//  Last edited:
+//    19 Oct 2026, 14:02:11
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Implements parsing the verdicts of the checker's deliberation API and
+//!   explaining denials in terms of the active policy and the workflow.
+//
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FResult};
+
+use axum::http::StatusCode;
+use axum::Json;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use brane_exe::pc::ProgramCounter;
+use brane_tsk::errors::StringError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::AuthDataType;
+use crate::bstowir::TaskCallInfo;
+use crate::checker::{Checker, CheckerError, SelectedChecker};
+use crate::conv::to_eflint;
+
+
+/***** CONSTANTS *****/
+/// The keywords with which eFLINT declarations start.
+const DECLARATION_KEYWORDS: [&str; 7] = ["Fact", "Placeholder", "Predicate", "Invariant", "Act", "Duty", "Event"];
+
+
+
+
+
+/***** ERRORS *****/
+/// Defines errors originating from explaining verdicts.
+#[derive(Debug)]
+pub enum VerdictError {
+    /// The response of the checker is not a verdict.
+    Parse { err: serde_json::Error },
+    /// The policy of the checker has no content we can explain with.
+    NoContent { version: Option<i64> },
+    /// Failed to convert the policy back to eFLINT.
+    Convert { version: Option<i64>, err: String },
+    /// Failed to retrieve the policy.
+    Checker { err: CheckerError },
+}
+impl VerdictError {
+    /// Returns the status code that best represents this error to our own clients.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Parse { .. } => StatusCode::BAD_REQUEST,
+            Self::NoContent { .. } | Self::Convert { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Checker { err } => err.status_code(),
+        }
+    }
+}
+impl Display for VerdictError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+        use VerdictError::*;
+        match self {
+            Parse { .. } => write!(f, "Failed to parse checker response as a verdict"),
+            NoContent { version: Some(version) } => write!(f, "Policy version {version} has no eFLINT content"),
+            NoContent { version: None } => write!(f, "Active policy has no eFLINT content"),
+            Convert { version: Some(version), err } => write!(f, "Failed to convert policy version {version} to eFLINT: {err}"),
+            Convert { version: None, err } => write!(f, "Failed to convert active policy to eFLINT: {err}"),
+            Checker { .. } => write!(f, "Failed to retrieve policy from checker"),
+        }
+    }
+}
+impl Error for VerdictError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use VerdictError::*;
+        match self {
+            Parse { err } => Some(err),
+            NoContent { .. } | Convert { .. } => None,
+            Checker { err } => Some(err),
+        }
+    }
+}
+
+
+
+
+
+/***** AUXILLARY *****/
+/// A verdict of the checker's deliberation API.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "verdict", rename_all = "lowercase")]
+pub enum Verdict {
+    /// The request is allowed.
+    Allow {
+        /// The reference with which the checker logged the verdict.
+        verdict_reference: String,
+        /// The checker's signature over the verdict.
+        signature: String,
+    },
+    /// The request is denied.
+    Deny {
+        /// The reference with which the checker logged the verdict.
+        verdict_reference: String,
+        /// The reasons the checker gave, if it was configured to leak them.
+        #[serde(default)]
+        reasons_for_denial: Option<Vec<String>>,
+    },
+}
+impl Verdict {
+    /// Parses a verdict from the body of a checker response.
+    ///
+    /// # Arguments
+    /// - `body`: The body of the checker's response.
+    ///
+    /// # Returns
+    /// The parsed [`Verdict`].
+    ///
+    /// # Errors
+    /// This function errors if the body is not a verdict.
+    pub fn parse(body: &str) -> Result<Self, VerdictError> { serde_json::from_str(body).map_err(|err| VerdictError::Parse { err }) }
+
+    /// Returns the verdict as the checker writes it, i.e., `allow` or `deny`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow { .. } => "allow",
+            Self::Deny { .. } => "deny",
+        }
+    }
+
+    /// Returns the reasons for this verdict (which is empty for allowed requests or if the checker does not leak them).
+    pub fn reasons(&self) -> &[String] {
+        match self {
+            Self::Allow { .. } => &[],
+            Self::Deny { reasons_for_denial, .. } => reasons_for_denial.as_deref().unwrap_or(&[]),
+        }
+    }
+}
+
+
+
+/// A line in the eFLINT source of a policy.
+#[derive(Clone, Debug, Serialize)]
+pub struct SourceLine {
+    /// The (1-indexed) line number.
+    pub line: usize,
+    /// The contents of the line.
+    pub text: String,
+}
+
+/// The eFLINT source of a policy, indexed by the names it declares.
+#[derive(Clone, Debug, Default)]
+pub struct PolicySource {
+    /// The version of the policy, if it is known.
+    pub version: Option<i64>,
+    /// Maps the names of declared types to the lines declaring or extending them.
+    pub declarations: HashMap<String, Vec<SourceLine>>,
+}
+impl PolicySource {
+    /// Indexes the given eFLINT source.
+    ///
+    /// # Arguments
+    /// - `version`: The version of the policy the source belongs to, if known.
+    /// - `source`: The eFLINT source as shown on the Policies screen.
+    ///
+    /// # Returns
+    /// A new [`PolicySource`].
+    pub fn new(version: Option<i64>, source: &str) -> Self {
+        let mut declarations: HashMap<String, Vec<SourceLine>> = HashMap::new();
+        for (i, text) in source.lines().enumerate() {
+            let mut words = text.split_whitespace();
+            let mut keyword: Option<&str> = words.next();
+            if keyword == Some("Extend") {
+                keyword = words.next();
+            }
+            if !keyword.map(|k| DECLARATION_KEYWORDS.contains(&k)).unwrap_or(false) {
+                continue;
+            }
+            if let Some(name) = words.next() {
+                declarations.entry(name.into()).or_default().push(SourceLine { line: i + 1, text: text.into() });
+            }
+        }
+        Self { version, declarations }
+    }
+
+    /// Retrieves the policy from the checker and indexes its eFLINT source.
+    ///
+    /// # Arguments
+    /// - `checker`: The [`Checker`] to retrieve the policy from.
+    /// - `token`: The management token to authenticate ourselves with.
+    /// - `version`: The version of the policy to retrieve, or [`None`] for the active one.
+    ///
+    /// # Returns
+    /// A new [`PolicySource`].
+    ///
+    /// # Errors
+    /// This function errors if we failed to retrieve the policy or if it has no eFLINT content.
+    pub async fn fetch(checker: &Checker, token: &str, version: Option<i64>) -> Result<Self, VerdictError> {
+        let res = match version {
+            Some(version) => checker.get_policy(token, version).await,
+            None => checker.get_active_policy(token).await,
+        };
+        let policy: Value = match res {
+            Ok(res) => serde_json::from_str(&res.body).map_err(|err| VerdictError::Checker {
+                err: CheckerError::Parse { url: format!("{}/v1/management/policies", checker.address), err },
+            })?,
+            Err(err) => return Err(VerdictError::Checker { err }),
+        };
+        let version: Option<i64> = policy.get("version").and_then(Value::as_i64).or(version);
+
+        // Find the eFLINT JSON and render it the same way the Policies screen does
+        let content: &Value = match policy.get("content").and_then(|c| c.get(0)).and_then(|c| c.get("content")) {
+            Some(content) => content,
+            None => return Err(VerdictError::NoContent { version }),
+        };
+        let source: String = to_eflint(content.to_string()).await.map_err(|err| VerdictError::Convert { version, err })?;
+        Ok(Self::new(version, &source))
+    }
+}
+
+
+
+/// The explanation of a single reason for denial.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReasonExplanation {
+    /// The reason as given by the checker.
+    pub reason: String,
+    /// The declarations in the policy that the reason refers to, together with their source lines.
+    pub policy: HashMap<String, Vec<SourceLine>>,
+    /// The task calls in the workflow that the reason refers to.
+    pub tasks:  Vec<ProgramCounter>,
+}
+
+/// The explanation of a whole verdict.
+#[derive(Clone, Debug, Serialize)]
+pub struct Explanation {
+    /// The verdict as parsed from the checker's response.
+    #[serde(flatten)]
+    pub verdict: Verdict,
+    /// The version of the policy the reasons were mapped to, if any.
+    pub policy_version: Option<i64>,
+    /// The explanation of every reason the checker gave.
+    pub reasons: Vec<ReasonExplanation>,
+}
+
+/// The body of a request to explain a verdict.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExplainPostModel {
+    /// The checker's response, either as the raw body or as already parsed JSON.
+    pub response: Value,
+    /// The task calls of the workflow the verdict is about (as returned by the BraneScript conversion).
+    #[serde(default)]
+    pub tasks:    Vec<TaskCallInfo>,
+    /// The policy version to map the reasons to. If omitted, the active policy is used.
+    pub version:  Option<i64>,
+}
+
+
+
+
+
+/***** HELPER FUNCTIONS *****/
+/// Splits a reason into the identifiers it might mention.
+///
+/// # Arguments
+/// - `reason`: The reason to split.
+///
+/// # Returns
+/// An iterator over every word that may be an eFLINT identifier or a [`ProgramCounter`].
+fn words(reason: &str) -> impl Iterator<Item = &str> {
+    reason
+        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == ':' || c == '<' || c == '>'))
+        .map(|w| w.trim_matches(':'))
+        .filter(|w| !w.is_empty())
+}
+
+
+
+
+
+/***** LIBRARY *****/
+/// Explains a verdict in terms of a policy and the task calls of a workflow.
+///
+/// # Arguments
+/// - `verdict`: The [`Verdict`] to explain.
+/// - `source`: The [`PolicySource`] of the policy that produced the verdict, if available.
+/// - `tasks`: The task calls in the workflow that the verdict is about.
+///
+/// # Returns
+/// An [`Explanation`] that links every reason to policy declarations and task calls.
+pub fn explain(verdict: Verdict, source: Option<&PolicySource>, tasks: &[TaskCallInfo]) -> Explanation {
+    let reasons: Vec<ReasonExplanation> = verdict
+        .reasons()
+        .iter()
+        .map(|reason| {
+            // Find the policy declarations mentioned
+            let mut policy: HashMap<String, Vec<SourceLine>> = HashMap::new();
+            if let Some(source) = source {
+                for word in words(reason) {
+                    if let Some(lines) = source.declarations.get(word) {
+                        policy.insert(word.into(), lines.clone());
+                    }
+                }
+            }
+
+            // Find the tasks mentioned, preferring their location over their (possibly ambiguous) name
+            let mentioned: Vec<&str> = words(reason).collect();
+            let mut pcs: Vec<ProgramCounter> = tasks.iter().filter(|t| mentioned.contains(&t.pg.to_string().as_str())).map(|t| t.pg).collect();
+            if pcs.is_empty() {
+                pcs = tasks.iter().filter(|t| mentioned.contains(&t.name.as_str())).map(|t| t.pg).collect();
+            }
+
+            ReasonExplanation { reason: reason.clone(), policy, tasks: pcs }
+        })
+        .collect();
+
+    Explanation { verdict, policy_version: source.and_then(|s| s.version), reasons }
+}
+
+
+
+// Explain why a request was allowed or denied
+pub async fn post_explain(checker: SelectedChecker, jar: PrivateCookieJar, Json(body): Json<ExplainPostModel>) -> (StatusCode, String) {
+    if jar.get(&AuthDataType::Deliberation.cookie(&checker.name)).is_none() {
+        return err!(StatusCode::UNAUTHORIZED, StringError("Unauthorized".into()));
+    }
+
+    // Parse the verdict
+    let res: Result<Verdict, VerdictError> = match body.response {
+        Value::String(raw) => Verdict::parse(&raw),
+        other => serde_json::from_value(other).map_err(|err| VerdictError::Parse { err }),
+    };
+    let verdict: Verdict = match res {
+        Ok(verdict) => verdict,
+        Err(err) => return err!(err.status_code(), err),
+    };
+
+    // Only bother with the policy if there is something to explain and we are allowed to see it
+    let source: Option<PolicySource> = match (verdict.reasons().is_empty(), jar.get(&AuthDataType::Policy.cookie(&checker.name))) {
+        (false, Some(token)) => match PolicySource::fetch(&checker, token.value(), body.version).await {
+            Ok(source) => Some(source),
+            Err(err) => return err!(err.status_code(), err),
+        },
+        (_, _) => None,
+    };
+
+    match serde_json::to_string(&explain(verdict, source.as_ref(), &body.tasks)) {
+        Ok(body) => (StatusCode::OK, body),
+        Err(err) => err!(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+
+
+
+
+/***** TESTS *****/
+#[cfg(test)]
+mod tests {
+    use brane_ast::func_id::FunctionId;
+
+    use super::*;
+
+    /// Builds a task call in the toplevel script for [`explain()`].
+    fn task(edge: usize, name: &str) -> TaskCallInfo {
+        TaskCallInfo {
+            pg: ProgramCounter::new(FunctionId::Main, edge),
+            name: name.into(),
+            datasets: vec![],
+            at: None,
+            result: None,
+            function: "<main>".into(),
+            in_loop: false,
+            in_branch: false,
+            in_parallel: false,
+            reachable: true,
+            call_chain: vec![],
+        }
+    }
+
+    #[test]
+    fn test_verdict_parse() {
+        // The body, and the verdict and reasons it parses to (if it parses at all)
+        let cases: [(&str, Option<(&str, &[&str])>); 8] = [
+            (r#"{"verdict":"allow","verdict_reference":"r","signature":"s"}"#, Some(("allow", &[]))),
+            (r#"{"verdict":"deny","verdict_reference":"r","reasons_for_denial":["a","b"]}"#, Some(("deny", &["a", "b"]))),
+            (r#"{"verdict":"deny","verdict_reference":"r"}"#, Some(("deny", &[]))),
+            (r#"{"verdict":"deny","verdict_reference":"r","reasons_for_denial":null}"#, Some(("deny", &[]))),
+            (r#"{"verdict":"allow","verdict_reference":"r"}"#, None),
+            (r#"{"verdict":"maybe","verdict_reference":"r"}"#, None),
+            ("allow", None),
+            ("", None),
+        ];
+        for (body, expected) in cases {
+            match (Verdict::parse(body), expected) {
+                (Ok(verdict), Some((kind, reasons))) => {
+                    assert_eq!(verdict.as_str(), kind, "{body}");
+                    assert_eq!(verdict.reasons(), reasons, "{body}");
+                },
+                (Err(err), None) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST, "{body}"),
+                (res, _) => panic!("Unexpected result {res:?} for {body}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_policy_source_new() {
+        let source: PolicySource = PolicySource::new(
+            Some(3),
+            "Fact user\nFact dataset Identified by String\n  Act read Actor user\nExtend Fact user Derived from read\n// Fact comment\nFact\nExtend \
+             Extend odd\nDuty pay Holder user\n",
+        );
+        assert_eq!(source.version, Some(3));
+
+        // Every declared name, and the lines declaring or extending it
+        let cases: [(&str, &[usize]); 5] = [("user", &[1, 4]), ("dataset", &[2]), ("read", &[3]), ("pay", &[8]), ("comment", &[])];
+        for (name, lines) in cases {
+            let found: Vec<usize> = source.declarations.get(name).map(|l| l.iter().map(|l| l.line).collect()).unwrap_or_default();
+            assert_eq!(found, lines, "{name}");
+        }
+        assert_eq!(source.declarations.len(), 4);
+        assert_eq!(source.declarations["read"][0].text, "  Act read Actor user");
+    }
+
+    #[test]
+    fn test_explain() {
+        let source: PolicySource = PolicySource::new(Some(1), "Fact user\nAct read Actor user\n");
+        let tasks: [TaskCallInfo; 2] = [task(2, "hello_world"), task(5, "hello_world")];
+        let verdict: Verdict = Verdict::Deny {
+            verdict_reference: "r".into(),
+            reasons_for_denial: Some(vec!["unknown-fact".into(), "read: user".into(), "hello_world".into(), format!("hello_world at {}", tasks[1].pg)]),
+        };
+        let explanation: Explanation = explain(verdict, Some(&source), &tasks);
+        assert_eq!(explanation.policy_version, Some(1));
+
+        // A fact the policy does not declare (nor the workflow call) is kept, but explained by nothing
+        assert_eq!(explanation.reasons[0].reason, "unknown-fact");
+        assert!(explanation.reasons[0].policy.is_empty() && explanation.reasons[0].tasks.is_empty());
+        // Declarations are found by name...
+        let mut names: Vec<&String> = explanation.reasons[1].policy.keys().collect();
+        names.sort();
+        assert_eq!(names, ["read", "user"]);
+        // ...and tasks by name, or by location if that narrows it down
+        assert_eq!(explanation.reasons[2].tasks, [tasks[0].pg, tasks[1].pg]);
+        assert_eq!(explanation.reasons[3].tasks, [tasks[1].pg]);
+
+        // Nothing to explain without reasons or a policy
+        let allow: Verdict = Verdict::Allow { verdict_reference: "r".into(), signature: "s".into() };
+        assert!(explain(allow, Some(&source), &tasks).reasons.is_empty());
+        let deny: Verdict = Verdict::Deny { verdict_reference: "r".into(), reasons_for_denial: Some(vec!["user".into()]) };
+        assert!(explain(deny, None, &tasks).reasons[0].policy.is_empty());
+    }
+}
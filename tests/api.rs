@@ -0,0 +1,793 @@
+//  API.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 16:03:27
+//  Last edited:
+//    19 Oct 2026, 16:03:27
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   End-to-end tests that drive the backend's [`Router`] in-process,
+//!   against the mock policy reasoner.
+//
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use http_body_util::BodyExt as _;
+use policy_reasoner_client_backend::app::build_router;
+use policy_reasoner_client_backend::approval::ProposalStore;
+use policy_reasoner_client_backend::audit::{hash, AuditAction, AuditLog};
+use policy_reasoner_client_backend::auth::{AppState, KeyRing};
+use policy_reasoner_client_backend::bstowir::AnalyzedWir;
+use policy_reasoner_client_backend::checker::{Checker, Checkers, DEFAULT_CHECKER};
+use policy_reasoner_client_backend::checker_tls::CheckerTlsConfig;
+use policy_reasoner_client_backend::config::{ConvConfig, ServerConfig};
+use policy_reasoner_client_backend::errors::{report_panic, ApiError, REQUEST_ID_HEADER};
+use policy_reasoner_client_backend::events::{EventBus, EventOrigin, PolicyEvent, PolicyEventKind};
+use policy_reasoner_client_backend::health::{HealthMonitor, Probe};
+use policy_reasoner_client_backend::history::HistoryStore;
+use policy_reasoner_client_backend::mock::{MockReasoner, MockVerdict};
+use policy_reasoner_client_backend::resilience::ResilienceConfig;
+use policy_reasoner_client_backend::rules::WorkflowRules;
+use policy_reasoner_client_backend::schedule::{run_due, ScheduleStore};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tower::ServiceExt as _;
+
+
+/***** HELPERS *****/
+/// A backend wired to a mock reasoner, with all of its files in a temporary directory.
+struct Harness {
+    /// The backend's router.
+    app:   Router,
+    /// The state shared by the backend's handlers.
+    state: AppState,
+    /// The mock reasoner behind the default checker.
+    mock:  MockReasoner,
+    /// The mock reasoners behind every checker, in the order they were given.
+    mocks: Vec<MockReasoner>,
+    /// The directory with the backend's files. Kept so it lives as long as the harness.
+    _dir:  TempDir,
+}
+impl Harness {
+    /// Starts a mock reasoner and builds a backend that uses it.
+    async fn new() -> Self { Self::with_rules(WorkflowRules::default()).await }
+
+    /// Starts a mock reasoner and builds a backend that uses it and checks workflows against the given rules.
+    async fn with_rules(workflow_rules: WorkflowRules) -> Self { Self::with_checkers(&[DEFAULT_CHECKER], workflow_rules).await }
+
+    /// Starts a mock reasoner per checker and builds a backend that uses them, the first being the default.
+    async fn with_checkers(names: &[&str], workflow_rules: WorkflowRules) -> Self {
+        let dir: TempDir = TempDir::new().unwrap();
+        let mut mocks: Vec<MockReasoner> = vec![];
+        let mut checkers: Vec<Checker> = vec![];
+        for name in names {
+            let mock: MockReasoner = MockReasoner::new();
+            let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let checker_address = format!("http://{}", listener.local_addr().unwrap()).parse().unwrap();
+            let server: MockReasoner = mock.clone();
+            tokio::spawn(async move { server.serve(listener).await });
+            checkers.push(Checker::new(*name, checker_address, Duration::from_secs(10), &ResilienceConfig::default(), &CheckerTlsConfig::default()).unwrap());
+            mocks.push(mock);
+        }
+
+        let state = AppState {
+            checkers: Arc::new(Checkers::new(checkers, None).unwrap()),
+            keys: Arc::new(KeyRing::load(dir.path().join("key")).unwrap()),
+            proposals: Arc::new(ProposalStore::new(dir.path().join("proposals.json"), chrono::Duration::hours(1)).unwrap()),
+            schedules: Arc::new(ScheduleStore::new(dir.path().join("schedules.json")).unwrap()),
+            audit: Arc::new(AuditLog::new(dir.path().join("audit.jsonl")).unwrap()),
+            history: Arc::new(HistoryStore::new(dir.path().join("history.jsonl")).unwrap()),
+            health: Arc::new(HealthMonitor::new(HashMap::from([(names[0].to_string(), "probe-jwt".to_string())]), 10)),
+            events: Arc::new(EventBus::new()),
+            workflow_rules: Arc::new(workflow_rules),
+            conv: Arc::new(ConvConfig::default()),
+            four_eyes: false,
+        };
+        let app: Router = build_router(state.clone(), &ServerConfig { static_dir: dir.path().into(), ..Default::default() });
+        Self { app, state, mock: mocks[0].clone(), mocks, _dir: dir }
+    }
+
+    /// Rebuilds the backend with the four-eyes principle enforced.
+    fn with_four_eyes(mut self) -> Self {
+        self.state.four_eyes = true;
+        self.app = build_router(self.state.clone(), &ServerConfig { static_dir: self._dir.path().into(), ..Default::default() });
+        self
+    }
+
+    /// Rotates the cookie keys, keeping at most `keep`, and rebuilds the backend with them.
+    fn with_rotated_keys(mut self, keep: usize) -> Self {
+        self.state.keys = Arc::new(KeyRing::rotate(self._dir.path().join("key"), keep).unwrap());
+        self.app = build_router(self.state.clone(), &ServerConfig { static_dir: self._dir.path().into(), ..Default::default() });
+        self
+    }
+
+    /// Sends a request to the backend and returns the status and body of its response.
+    async fn send(&self, method: Method, uri: &str, cookies: &str, body: impl Into<Body>) -> (StatusCode, String) {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::COOKIE, cookies)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .unwrap();
+        let res = self.app.clone().oneshot(req).await.unwrap();
+        let status: StatusCode = res.status();
+        let body: Vec<u8> = res.into_body().collect().await.unwrap().to_bytes().to_vec();
+        (status, String::from_utf8(body).unwrap())
+    }
+
+    /// Logs in with the given kind of token and returns the resulting cookie.
+    async fn login(&self, t: &str, token: &str) -> String { self.login_at("/api/authenticate", t, token).await }
+
+    /// Logs in at the given URI (e.g., to select a checker) and returns the resulting cookie.
+    async fn login_at(&self, uri: &str, t: &str, token: &str) -> String {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "t": t, "token": token }).to_string()))
+            .unwrap();
+        let res = self.app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie: &str = res.headers().get(header::SET_COOKIE).expect("no cookie set").to_str().unwrap();
+        cookie.split(';').next().unwrap().into()
+    }
+
+    /// Logs in with both kinds of tokens and returns the resulting cookies.
+    async fn login_both(&self) -> String { format!("{}; {}", self.login("policy", "policy-jwt").await, self.login("deliberation", "deliberation-jwt").await) }
+}
+
+/// Builds a (not actually signed) JWT that was issued to the given user.
+fn jwt(sub: &str) -> String { format!("eyJhbGciOiJIUzI1NiJ9.{}.signature", general_purpose::URL_SAFE_NO_PAD.encode(json!({ "sub": sub }).to_string())) }
+
+/// Converts a BraneScript fixture to an [`AnalyzedWir`] through the backend.
+async fn convert(harness: &Harness, name: &str) -> (StatusCode, String) {
+    let bs: String = fs::read_to_string(format!("./tests/branescript/{name}")).unwrap();
+    harness.send(Method::POST, "/api/conv?from=branescript&to=wir", "", bs).await
+}
+
+
+
+
+
+/***** TESTS *****/
+#[tokio::test]
+async fn test_authenticate_cookies() {
+    let harness = Harness::new().await;
+
+    // Without cookies, nothing is known and nothing is allowed
+    let (status, body) = harness.send(Method::GET, "/api/authenticate", "", Body::empty()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "policy": "", "deliberation": "" }));
+    assert_eq!(harness.send(Method::GET, "/api/policies", "", Body::empty()).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(harness.send(Method::POST, "/api/deliberation/workflow", "", "{}").await.0, StatusCode::UNAUTHORIZED);
+
+    // The cookies carry the tokens, encrypted
+    let cookies: String = harness.login_both().await;
+    assert!(!cookies.contains("policy-jwt") && !cookies.contains("deliberation-jwt"));
+    let (status, body) = harness.send(Method::GET, "/api/authenticate", &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "policy": "policy-jwt", "deliberation": "deliberation-jwt" }));
+
+    // Forged cookies are ignored
+    let (_, body) = harness.send(Method::GET, "/api/authenticate", "reasoner_policy_auth=policy-jwt", Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["policy"], "");
+}
+
+#[tokio::test]
+async fn test_migrate_cookies() {
+    let harness = Harness::new().await;
+    let cookies: String = harness.login("policy", "policy-jwt").await;
+
+    // After a rotation, the old cookie is still accepted, and re-encrypted with the new key
+    let harness = harness.with_rotated_keys(2);
+    let req = Request::builder().uri("/api/authenticate").header(header::COOKIE, &cookies).body(Body::empty()).unwrap();
+    let res = harness.app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let migrated: String = res.headers().get(header::SET_COOKIE).expect("cookie not migrated").to_str().unwrap().split(';').next().unwrap().into();
+    assert_ne!(migrated, cookies);
+    let body: Vec<u8> = res.into_body().collect().await.unwrap().to_bytes().to_vec();
+    assert_eq!(serde_json::from_slice::<Value>(&body).unwrap()["policy"], "policy-jwt");
+
+    // Once the old key is rotated out, only the migrated cookie still works
+    let harness = harness.with_rotated_keys(2);
+    let (_, body) = harness.send(Method::GET, "/api/authenticate", &cookies, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["policy"], "");
+    let (_, body) = harness.send(Method::GET, "/api/authenticate", &migrated, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["policy"], "policy-jwt");
+
+    // Cookies encrypted with the current key are left alone
+    let cookies: String = harness.login("policy", "policy-jwt").await;
+    let req = Request::builder().uri("/api/authenticate").header(header::COOKIE, &cookies).body(Body::empty()).unwrap();
+    assert!(harness.app.clone().oneshot(req).await.unwrap().headers().get(header::SET_COOKIE).is_none());
+}
+
+#[tokio::test]
+async fn test_conv_branescript_fixtures() {
+    let harness = Harness::new().await;
+
+    // Not every fixture is plannable (some omit locations or use packages that aren't there), but none may crash the backend, and every
+    // successful conversion must survive a round trip through our own types
+    let mut converted: usize = 0;
+    for entry in fs::read_dir("./tests/branescript").unwrap() {
+        let name: String = entry.unwrap().file_name().to_string_lossy().into();
+        let (status, body) = convert(&harness, &name).await;
+        if status != StatusCode::OK {
+            assert!(!body.is_empty(), "Conversion of '{name}' failed with {status} without a message");
+            continue;
+        }
+
+        let wir: AnalyzedWir = serde_json::from_str(&body).unwrap_or_else(|err| panic!("Conversion of '{name}' is not an AnalyzedWir: {err}"));
+        let original: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(serde_json::to_value(&wir).unwrap(), original, "Conversion of '{name}' does not round-trip");
+        converted += 1;
+    }
+    assert!(converted > 0);
+
+    // The EPI use-case is fully planned, so it must convert
+    let (status, body) = convert(&harness, "epi.bs").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let wir: AnalyzedWir = serde_json::from_str(&body).unwrap();
+    assert!(!wir.tasks.is_empty());
+}
+
+#[tokio::test]
+async fn test_conv_eflint_json_fixtures() {
+    let harness = Harness::new().await;
+    for entry in fs::read_dir("./tests/eflint-json").unwrap() {
+        let path = entry.unwrap().path();
+        let (status, body) = harness.send(Method::POST, "/api/conv?from=eflintjson&to=eflint", "", fs::read_to_string(&path).unwrap()).await;
+        assert_eq!(status, StatusCode::OK, "Conversion of '{}' failed: {body}", path.display());
+        assert!(!body.is_empty());
+    }
+
+    let (status, _) = harness.send(Method::POST, "/api/conv?from=eflint&to=eflint", "", "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_policy_proxy() {
+    let harness = Harness::new().await;
+    let token: String = jwt("alice");
+    let cookies: String = harness.login("policy", &token).await;
+    let content: Value = serde_json::from_str(&fs::read_to_string("./tests/eflint-json/no-dan.json").unwrap()).unwrap();
+
+    // Add a policy
+    let policy: Value = json!({
+        "description": "",
+        "version_description": "test",
+        "content": [{ "reasoner": "eflint-json", "reasoner_version": "0.1.0", "content": content }],
+    });
+    let (status, body) = harness.send(Method::POST, "/api/policies", &cookies, policy.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let version: i64 = serde_json::from_str::<Value>(&body).unwrap()["version"].as_i64().unwrap();
+
+    // It is listed and retrievable, but not active
+    let (_, body) = harness.send(Method::GET, "/api/policies", &cookies, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()[0]["version"], version);
+    let (status, body) = harness.send(Method::GET, &format!("/api/policies/{version}"), &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["content"][0]["content"], content);
+    assert_eq!(harness.send(Method::GET, "/api/policies/active", &cookies, Body::empty()).await.0, StatusCode::NOT_FOUND);
+
+    // Activate and deactivate it
+    let activation: String = format!("{{ \"version\" : {version} }}");
+    let (status, body) = harness.send(Method::POST, "/api/policies/active", &cookies, activation.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(harness.mock.active().await, Some(version));
+    let (_, body) = harness.send(Method::GET, "/api/policies/active", &cookies, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["version"], version);
+    assert_eq!(harness.send(Method::DELETE, "/api/policies/active", &cookies, Body::empty()).await.0, StatusCode::OK);
+    assert_eq!(harness.mock.active().await, None);
+
+    // Unknown versions are refused by the reasoner, and we pass that on
+    assert_eq!(harness.send(Method::POST, "/api/policies/active", &cookies, json!({ "version": 42 }).to_string()).await.0, StatusCode::BAD_REQUEST);
+
+    // Every action ended up in a valid audit log
+    let (_, body) = harness.send(Method::GET, "/api/audit/verify", &cookies, Body::empty()).await;
+    let verification: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(verification["valid"], true);
+    assert_eq!(verification["entries"], 4);
+
+    // Each entry hashes the request exactly as it was sent, or its method and path if it had no body
+    let (_, body) = harness.send(Method::GET, "/api/audit", &cookies, Body::empty()).await;
+    let entries: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(entries[0]["request_hash"], hash(policy.to_string().as_bytes()));
+    assert_eq!(entries[1]["request_hash"], hash(activation.as_bytes()));
+    assert_eq!(entries[2]["request_hash"], hash(b"DELETE /api/policies/active"));
+
+    // The reasoner saw our token, not the cookies
+    assert!(harness.mock.requests().await.iter().all(|r| r.token == token));
+}
+
+#[tokio::test]
+async fn test_manager_access() {
+    let harness = Harness::new().await;
+    let alice: String = jwt("alice");
+    harness.mock.accept_only([alice.clone()]).await;
+
+    // What others did is only readable with a management token the checker accepts, not with any token that happens to be in a cookie
+    let uris: [&str; 5] = ["/api/audit", "/api/audit/export", "/api/audit/verify", "/api/deliberation/history", "/api/deliberation/history/0"];
+    let forged: String = format!("{}; {}", harness.login("policy", &jwt("mallory")).await, harness.login("deliberation", &jwt("mallory")).await);
+    for uri in uris {
+        assert_eq!(harness.send(Method::GET, uri, "", Body::empty()).await.0, StatusCode::UNAUTHORIZED, "{uri}");
+        assert_eq!(harness.send(Method::GET, uri, &forged, Body::empty()).await.0, StatusCode::UNAUTHORIZED, "{uri}");
+    }
+    let cookies: String = harness.login("policy", &alice).await;
+    for uri in uris {
+        let expected: StatusCode = if uri.ends_with("/0") { StatusCode::NOT_FOUND } else { StatusCode::OK };
+        assert_eq!(harness.send(Method::GET, uri, &cookies, Body::empty()).await.0, expected, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_four_eyes() {
+    let harness = Harness::new().await.with_four_eyes();
+    let (alice, bob): (String, String) = (jwt("alice"), jwt("bob"));
+    harness.mock.accept_only([alice.clone(), bob.clone()]).await;
+    let version: i64 = harness.mock.add_policy(json!([]), false).await;
+    let alice_cookies: String = harness.login("policy", &alice).await;
+    let bob_cookies: String = harness.login("policy", &bob).await;
+
+    // Nobody can (de)activate a policy on their own
+    let (status, _) = harness.send(Method::POST, "/api/policies/active", &alice_cookies, json!({ "version": version }).to_string()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(harness.send(Method::DELETE, "/api/policies/active", &alice_cookies, Body::empty()).await.0, StatusCode::FORBIDDEN);
+
+    // A token that merely claims to be someone is not accepted, neither to propose nor to approve
+    let forged: String = harness.login("policy", &format!("x.{}.y", general_purpose::URL_SAFE_NO_PAD.encode(r#"{"sub":"bob"}"#))).await;
+    let (status, _) = harness.send(Method::POST, "/api/proposals", &forged, json!({ "version": version }).to_string()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = harness.send(Method::POST, "/api/proposals", &alice_cookies, json!({ "version": version }).to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let id: u64 = serde_json::from_str::<Value>(&body).unwrap()["id"].as_u64().unwrap();
+    assert_eq!(harness.send(Method::POST, &format!("/api/proposals/{id}/approve"), &forged, Body::empty()).await.0, StatusCode::UNAUTHORIZED);
+
+    // The proposer cannot approve their own proposal
+    let (status, body) = harness.send(Method::POST, &format!("/api/proposals/{id}/approve"), &alice_cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    assert_eq!(harness.mock.active().await, None);
+
+    // But another user can
+    let (status, body) = harness.send(Method::POST, &format!("/api/proposals/{id}/approve"), &bob_cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let proposal: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(proposal["status"], "approved");
+    assert_eq!((proposal["proposed_by"].as_str(), proposal["decided_by"].as_str()), (Some("alice"), Some("bob")));
+    assert_eq!(harness.mock.active().await, Some(version));
+
+    // The audit log knows who did what, and to which request
+    let (_, body) = harness.send(Method::GET, "/api/audit", &bob_cookies, Body::empty()).await;
+    let entries: Value = serde_json::from_str(&body).unwrap();
+    let actions: Vec<(&str, &str)> = entries.as_array().unwrap().iter().map(|e| (e["action"].as_str().unwrap(), e["subject"].as_str().unwrap())).collect();
+    assert_eq!(actions, [("propose_activation", "alice"), ("approve_activation", "bob")]);
+    assert_eq!(entries[0]["request_hash"], hash(json!({ "version": version }).to_string().as_bytes()));
+    assert_eq!(entries[1]["request_hash"], hash(format!("POST /api/proposals/{id}/approve").as_bytes()));
+}
+
+#[tokio::test]
+async fn test_audit_log_corrupt() {
+    let dir: TempDir = TempDir::new().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    let log: AuditLog = AuditLog::new(&path).unwrap();
+    log.append(Some("alice".into()), DEFAULT_CHECKER, AuditAction::AddPolicy, Some(1), Some(200), b"first").await.unwrap();
+    log.append(Some("alice".into()), DEFAULT_CHECKER, AuditAction::ActivatePolicy, Some(1), Some(200), b"second").await.unwrap();
+    drop(log);
+    assert!(AuditLog::new(&path).is_ok());
+
+    // A log with a broken chain is not continued
+    let raw: String = fs::read_to_string(&path).unwrap();
+    fs::write(&path, raw.lines().skip(1).collect::<Vec<_>>().join("\n")).unwrap();
+    let err = AuditLog::new(&path).unwrap_err();
+    assert!(err.to_string().contains("at line 1"), "{err}");
+}
+
+#[tokio::test]
+async fn test_scheduled_activation() {
+    let harness = Harness::new().await;
+    let token: String = jwt("alice");
+    let cookies: String = format!("{}; {}", harness.login("policy", &token).await, harness.login("deliberation", "deliberation-jwt").await);
+    let v1: i64 = harness.mock.add_policy(json!({}), true).await;
+    let v2: i64 = harness.mock.add_policy(json!({}), false).await;
+    let v3: i64 = harness.mock.add_policy(json!({}), false).await;
+    let check: Value = json!({ "kind": "workflow", "request": { "use_case": "central", "workflow": {} }, "expected": "allow" });
+    let schedule = |version: i64, at: chrono::DateTime<chrono::Utc>| json!({ "version": version, "at": at, "checks": [check.clone()] }).to_string();
+
+    // Schedules only run once their time has come
+    let (status, body) = harness.send(Method::POST, "/api/schedules", &cookies, schedule(v2, chrono::Utc::now() + chrono::Duration::hours(1))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let later: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(later["status"], "scheduled");
+    run_due(&harness.state).await;
+    assert_eq!(harness.mock.active().await, Some(v1));
+
+    // A failing health check rolls the activation back
+    harness.mock.script([(MockVerdict::Deny, vec!["no".into()])]).await;
+    let (status, body) = harness.send(Method::POST, "/api/schedules", &cookies, schedule(v3, chrono::Utc::now())).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    run_due(&harness.state).await;
+    assert_eq!(harness.mock.active().await, Some(v1));
+
+    // A passing one keeps it
+    let (status, body) = harness.send(Method::POST, "/api/schedules", &cookies, schedule(v2, chrono::Utc::now())).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    run_due(&harness.state).await;
+    assert_eq!(harness.mock.active().await, Some(v2));
+
+    // Which the schedules show, newest first; a claimed schedule is never run twice
+    run_due(&harness.state).await;
+    let (_, body) = harness.send(Method::GET, "/api/schedules", &cookies, Body::empty()).await;
+    let schedules: Value = serde_json::from_str(&body).unwrap();
+    let statuses: Vec<&str> = schedules.as_array().unwrap().iter().map(|s| s["status"].as_str().unwrap()).collect();
+    assert_eq!(statuses, ["activated", "rolled_back", "scheduled"]);
+    assert_eq!(schedules[1]["previous_version"], v1);
+    assert_eq!(schedules[1]["outcomes"][0]["actual"], "deny");
+    assert_eq!(schedules[0]["created_by"], "alice");
+    assert!(!body.contains(&token));
+    let activations: usize = harness.mock.requests().await.iter().filter(|r| r.method == "PUT").count();
+    assert_eq!(activations, 3);
+
+    // The pending schedule's tokens are stored, but only for us to read
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        let path = harness._dir.path().join("schedules.json");
+        assert!(fs::read_to_string(&path).unwrap().contains(&token));
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+}
+
+#[tokio::test]
+async fn test_multiple_checkers() {
+    let harness = Harness::with_checkers(&["north", "south"], WorkflowRules::default()).await;
+    let (north, south): (&MockReasoner, &MockReasoner) = (&harness.mocks[0], &harness.mocks[1]);
+
+    // Both checkers are listed, the first being the default
+    let (_, body) = harness.send(Method::GET, "/api/checkers", "", Body::empty()).await;
+    let checkers: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(checkers[0]["name"], "north");
+    assert_eq!(checkers[0]["default"], true);
+    assert_eq!(checkers[1]["name"], "south");
+    assert_eq!(checkers[1]["default"], false);
+
+    // Tokens are kept per checker
+    let cookies: String = harness.login_at("/api/authenticate?checker=south", "policy", "south-jwt").await;
+    assert_eq!(harness.send(Method::GET, "/api/policies", &cookies, Body::empty()).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(harness.send(Method::GET, "/api/policies?checker=south", &cookies, Body::empty()).await.0, StatusCode::OK);
+    assert_eq!(harness.send(Method::GET, "/api/policies?checker=west", &cookies, Body::empty()).await.0, StatusCode::NOT_FOUND);
+
+    // Requests only go to the selected checker
+    let policy: Value = json!({ "description": "", "version_description": "test", "content": [] });
+    let (status, body) = harness.send(Method::POST, "/api/policies?checker=south", &cookies, policy.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(north.requests().await.is_empty());
+    assert!(south.requests().await.iter().all(|r| r.token == "south-jwt"));
+
+    // And are audited as such
+    let auditor: String = format!(
+        "{}; {}",
+        harness.login_at("/api/authenticate?checker=north", "policy", &jwt("auditor")).await,
+        harness.login_at("/api/authenticate?checker=south", "policy", &jwt("auditor")).await
+    );
+    let (_, body) = harness.send(Method::GET, "/api/audit?checker=south", &auditor, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()[0]["checker"], "south");
+    let (_, body) = harness.send(Method::GET, "/api/audit?checker=north", &auditor, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!([]));
+}
+
+#[tokio::test]
+async fn test_replay_multiple_checkers() {
+    let harness = Harness::with_checkers(&["north", "south"], WorkflowRules::default()).await;
+    let (north, south): (&MockReasoner, &MockReasoner) = (&harness.mocks[0], &harness.mocks[1]);
+    let token: String = jwt("alice");
+    let mut cookies: Vec<String> = vec![];
+    for checker in ["north", "south"] {
+        for t in ["policy", "deliberation"] {
+            cookies.push(harness.login_at(&format!("/api/authenticate?checker={checker}"), t, &token).await);
+        }
+    }
+    let cookies: String = cookies.join("; ");
+
+    // Ask the non-default checker something
+    let request: String = json!({ "use_case": "central", "workflow": {} }).to_string();
+    let (status, body) = harness.send(Method::POST, "/api/deliberation/workflow?checker=south", &cookies, request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, body) = harness.send(Method::GET, "/api/deliberation/history", &cookies, Body::empty()).await;
+    let history: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(history[0]["checker"], "south");
+    let id: u64 = history[0]["id"].as_u64().unwrap();
+
+    // Replaying it goes to the checker that answered it, even if that one is not selected...
+    let (asked_north, asked_south): (usize, usize) = (north.requests().await.len(), south.requests().await.len());
+    let (status, body) = harness.send(Method::POST, &format!("/api/deliberation/history/{id}/replay"), &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let replay: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(replay["replay"]["checker"], "south");
+    assert_eq!(replay["changed"], false);
+    assert_eq!(north.requests().await.len(), asked_north);
+    assert!(south.requests().await.len() > asked_south);
+
+    // ...and selecting another checker is refused
+    let (status, _) = harness.send(Method::POST, &format!("/api/deliberation/history/{id}/replay?checker=north"), &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = harness.send(Method::POST, &format!("/api/deliberation/history/{id}/replay?checker=south"), &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+#[tokio::test]
+async fn test_policy_sync() {
+    let harness = Harness::with_checkers(&["north", "south"], WorkflowRules::default()).await;
+    let (north, south): (&MockReasoner, &MockReasoner) = (&harness.mocks[0], &harness.mocks[1]);
+    let cookies: String = format!(
+        "{}; {}",
+        harness.login_at("/api/authenticate?checker=north", "policy", "north-jwt").await,
+        harness.login_at("/api/authenticate?checker=south", "policy", "south-jwt").await
+    );
+    let no_dan: Value = serde_json::from_str(&fs::read_to_string("./tests/eflint-json/no-dan.json").unwrap()).unwrap();
+    let full: Value = serde_json::from_str(&fs::read_to_string("./tests/eflint-json/no-dan-full-invariant.json").unwrap()).unwrap();
+    north.add_policy(no_dan, true).await;
+    south.add_policy(full, true).await;
+
+    // The policies differ
+    let (status, body) = harness.send(Method::GET, "/api/checkers/policies?base=north", &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let comparison: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(comparison["checkers"][0]["checker"], "south");
+    assert_eq!(comparison["checkers"][0]["version"], 1);
+    assert_eq!(comparison["checkers"][0]["diff"]["identical"], false);
+
+    // A dry run changes nothing...
+    let sync: Value = json!({ "from": "north", "dry_run": true });
+    let (status, body) = harness.send(Method::POST, "/api/checkers/sync", &cookies, sync.to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["results"][0]["status"], "would_push");
+    assert_eq!(south.active().await, Some(1));
+
+    // ...but the real thing does
+    let sync: Value = json!({ "from": "north" });
+    let (_, body) = harness.send(Method::POST, "/api/checkers/sync", &cookies, sync.to_string()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["results"][0]["status"], "activated", "{body}");
+    assert_eq!(south.active().await, Some(2));
+    let (_, body) = harness.send(Method::GET, "/api/checkers/policies?base=north", &cookies, Body::empty()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["checkers"][0]["diff"]["identical"], true);
+
+    // After which there is nothing left to do
+    let (_, body) = harness.send(Method::POST, "/api/checkers/sync", &cookies, sync.to_string()).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["results"][0]["status"], "up_to_date");
+}
+
+#[tokio::test]
+async fn test_checker_health() {
+    let harness = Harness::new().await;
+    harness.mock.add_policy(json!({}), true).await;
+
+    // Until the checker has been probed, the backend is alive but not ready
+    let (status, body) = harness.send(Method::GET, "/api/health?ready=true", "", Body::empty()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["live"], true);
+
+    // Probing it finds out everything we want to know
+    let checker: &Checker = harness.state.checkers.default();
+    let probe: Probe = harness.state.health.probe(checker).await;
+    assert!(probe.reachable, "{probe:?}");
+    assert_eq!(probe.context_hash.as_deref(), Some("mock"));
+    assert_eq!(probe.active_version, Some(1));
+    assert!(harness.mock.requests().await.iter().all(|r| r.token == "probe-jwt"));
+    harness.state.health.record(&checker.name, probe).await;
+    let (status, _) = harness.send(Method::GET, "/api/health?ready=true", "", Body::empty()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A checker that is down is reported as such
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap()).parse().unwrap();
+    drop(listener);
+    let down: Checker = Checker::new(DEFAULT_CHECKER, address, Duration::from_secs(1), &ResilienceConfig::default(), &CheckerTlsConfig::default()).unwrap();
+    let probe: Probe = harness.state.health.probe(&down).await;
+    assert!(!probe.reachable && probe.error.is_some(), "{probe:?}");
+    harness.state.health.record(&checker.name, probe).await;
+
+    // Which the status shows, including history
+    let (status, body) = harness.send(Method::GET, "/api/checker/status", "", Body::empty()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let statuses: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(statuses[0]["checker"], DEFAULT_CHECKER);
+    assert_eq!(statuses[0]["latest"]["reachable"], false);
+    assert_eq!(statuses[0]["history"][0]["reachable"], true);
+    assert_eq!(statuses[0]["since"], statuses[0]["latest"]["at"]);
+    let (status, _) = harness.send(Method::GET, "/api/health?ready=true", "", Body::empty()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_error_responses() {
+    let harness = Harness::new().await;
+
+    // Whether a handler, an extractor or the checker fails, the error looks the same
+    for (method, uri, body, status, code) in [
+        (Method::GET, "/api/policies", "", StatusCode::UNAUTHORIZED, "unauthorized"),
+        (Method::GET, "/api/policies?checker=nope", "", StatusCode::NOT_FOUND, "unknown_checker"),
+        (Method::POST, "/api/conv?from=wir&to=wir", "", StatusCode::BAD_REQUEST, "bad_request"),
+        (Method::POST, "/api/policies/active", "{", StatusCode::BAD_REQUEST, "bad_request"),
+    ] {
+        let (actual, body) = harness.send(method, uri, "", body).await;
+        assert_eq!(actual, status, "{uri}: {body}");
+        let err: ApiError = serde_json::from_str(&body).unwrap_or_else(|err| panic!("{uri}: not an ApiError ({err}): {body}"));
+        assert_eq!(err.code, code, "{uri}: {body}");
+        assert!(!err.message.is_empty() && err.request_id.is_some(), "{uri}: {body}");
+    }
+
+    // A request ID given by the client is kept
+    let req = Request::builder().uri("/api/policies").header(REQUEST_ID_HEADER, "my-request-1").body(Body::empty()).unwrap();
+    let res = harness.app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "my-request-1");
+    let body: Vec<u8> = res.into_body().collect().await.unwrap().to_bytes().to_vec();
+    assert_eq!(serde_json::from_slice::<ApiError>(&body).unwrap().request_id.as_deref(), Some("my-request-1"));
+
+    // Panics are reported without revealing what they were about
+    let res = report_panic(Box::new("secret"));
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body: Vec<u8> = res.into_body().collect().await.unwrap().to_bytes().to_vec();
+    let err: ApiError = serde_json::from_slice(&body).unwrap();
+    assert_eq!(err.code, "internal_panic");
+    assert!(!String::from_utf8(body).unwrap().contains("secret"));
+}
+
+#[tokio::test]
+async fn test_policy_events() {
+    let harness = Harness::new().await;
+    let cookies: String = harness.login("policy", "policy-jwt").await;
+    let version: i64 = harness.mock.add_policy(json!({}), false).await;
+    assert_eq!(harness.send(Method::GET, "/api/events", "", Body::empty()).await.0, StatusCode::UNAUTHORIZED);
+
+    // Changes through the backend are published...
+    let mut events = harness.state.events.subscribe();
+    let (status, body) = harness.send(Method::POST, "/api/policies/active", &cookies, json!({ "version": version }).to_string()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let event: PolicyEvent = events.try_recv().unwrap();
+    assert_eq!(
+        (event.checker.as_str(), event.kind, event.version, event.origin),
+        (DEFAULT_CHECKER, PolicyEventKind::Activated, Some(version), EventOrigin::Backend)
+    );
+
+    // ...but only if they succeeded
+    assert_eq!(harness.send(Method::POST, "/api/policies/active", &cookies, json!({ "version": 42 }).to_string()).await.0, StatusCode::BAD_REQUEST);
+    assert!(events.try_recv().is_err());
+
+    // Probing the checker does not report them again, but does notice changes made elsewhere
+    harness.state.events.observe(DEFAULT_CHECKER, Some(version)).await;
+    assert!(events.try_recv().is_err());
+    harness.state.events.observe(DEFAULT_CHECKER, None).await;
+    let event: PolicyEvent = events.try_recv().unwrap();
+    assert_eq!((event.kind, event.version, event.origin), (PolicyEventKind::Deactivated, None, EventOrigin::Detected));
+}
+
+#[tokio::test]
+async fn test_deliberation_proxy() {
+    let harness = Harness::new().await;
+    let cookies: String = format!("{}; {}", harness.login("policy", &jwt("alice")).await, harness.login("deliberation", &jwt("alice")).await);
+    let active: i64 = harness.mock.add_policy(json!({}), true).await;
+    let (_, body) = convert(&harness, "epi.bs").await;
+    let wir: AnalyzedWir = serde_json::from_str(&body).unwrap();
+    let workflow: Value = serde_json::to_value(&wir.workflow).unwrap();
+
+    // Verdicts are passed on verbatim
+    harness.mock.script([(MockVerdict::Allow, vec![]), (MockVerdict::Deny, vec!["no-dan".into()])]).await;
+    let task: Value = json!({ "use_case": "central", "workflow": workflow, "task_id": wir.tasks[0].pg });
+    let (status, body) = harness.send(Method::POST, "/api/deliberation/task", &cookies, task.to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["verdict"], "allow");
+    let (status, body) = harness.send(Method::POST, "/api/deliberation/workflow", &cookies, json!({ "use_case": "central", "workflow": workflow }).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    let verdict: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(verdict["verdict"], "deny");
+    assert_eq!(verdict["reasons_for_denial"], json!(["no-dan"]));
+
+    // Both ended up in the history
+    let (_, body) = harness.send(Method::GET, "/api/deliberation/history", &cookies, Body::empty()).await;
+    let history: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(history.as_array().unwrap().len(), 2);
+
+    // They can be replayed against the active policy, but the backend never switches policies to replay them
+    let other: i64 = harness.mock.add_policy(json!({}), false).await;
+    let id: u64 = history[0]["id"].as_u64().unwrap();
+    let (status, body) = harness.send(Method::POST, &format!("/api/deliberation/history/{id}/replay?version={active}"), &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = harness.send(Method::POST, &format!("/api/deliberation/history/{id}/replay?version={other}"), &cookies, Body::empty()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(harness.mock.active().await, Some(active));
+
+    // A batch asks about everything
+    let (status, body) = harness.send(Method::POST, "/api/deliberation/batch", &cookies, serde_json::to_string(&wir).unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let report: Value = serde_json::from_str(&body).unwrap();
+    let questions: usize = 1 + wir.tasks.iter().map(|t| 1 + t.datasets.len()).sum::<usize>() + wir.results.len();
+    assert_eq!(report["summary"]["allowed"].as_u64().unwrap() as usize, questions);
+    assert_eq!(report["tasks"].as_array().unwrap().len(), wir.tasks.len());
+}
+
+#[tokio::test]
+async fn test_federated_deliberation() {
+    let harness = Harness::with_checkers(&["st_antonius", "umc_utrecht"], WorkflowRules::default()).await;
+    let (st_antonius, umc_utrecht): (&MockReasoner, &MockReasoner) = (&harness.mocks[0], &harness.mocks[1]);
+    let cookies: String = format!(
+        "{}; {}",
+        harness.login_at("/api/authenticate?checker=st_antonius", "deliberation", "sta-jwt").await,
+        harness.login_at("/api/authenticate?checker=umc_utrecht", "deliberation", "umc-jwt").await
+    );
+    // Only the St. Antonius has a policy, so the UMC Utrecht denies everything
+    st_antonius.add_policy(json!({}), true).await;
+
+    let bs: String = fs::read_to_string("./tests/branescript/epi.bs").unwrap();
+    let (status, body) = harness.send(Method::POST, "/api/deliberation/federated?from=branescript", &cookies, bs).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let report: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(report["ok"], false);
+    let domains: &Vec<Value> = report["domains"].as_array().unwrap();
+    let domain = |location: &str| domains.iter().find(|d| d["location"] == location).unwrap().clone();
+    assert_eq!(domain("st_antonius")["ok"], true);
+    assert_eq!(domain("umc_utrecht")["ok"], false);
+    assert_eq!(domain("umc_utrecht")["summary"]["allowed"], 0);
+    assert!(domain("surf")["checker"].is_null());
+    assert!(domain("surf")["problem"].as_str().unwrap().contains("no checker"));
+
+    // Every domain was only asked about its own tasks, with its own token
+    assert!(st_antonius.requests().await.iter().all(|r| r.token == "sta-jwt"));
+    assert!(umc_utrecht.requests().await.iter().all(|r| r.token == "umc-jwt"));
+    let asked: usize = st_antonius.requests().await.len() + umc_utrecht.requests().await.len();
+    let summary: &Value = &report["summary"];
+    assert_eq!(asked as u64, summary["allowed"].as_u64().unwrap() + summary["denied"].as_u64().unwrap());
+}
+
+#[tokio::test]
+async fn test_workflow_check() {
+    let dir: TempDir = TempDir::new().unwrap();
+    let path = dir.path().join("rules.yml");
+    fs::write(
+        &path,
+        r#"
+rules:
+  - name: sta-stays-home
+    rule: dataset_location
+    dataset: st_antonius_ect
+    locations: [umc_utrecht]
+  - rule: forbidden_package
+    package: epi
+    location: surf
+  - rule: dataset_location
+    dataset: umc_utrecht_ect
+    locations: [umc_utrecht]
+"#,
+    )
+    .unwrap();
+    let harness = Harness::with_rules(WorkflowRules::load(&path).unwrap()).await;
+
+    // The EPI workflow breaks the first two rules, but not the third
+    let (_, wir) = convert(&harness, "epi.bs").await;
+    let (status, body) = harness.send(Method::POST, "/api/workflow/check", "", wir).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let report: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(report["ok"], false);
+    let violations: &Vec<Value> = report["violations"].as_array().unwrap();
+    assert_eq!(violations.iter().filter(|v| v["rule"] == 0).count(), 1);
+    assert_eq!(violations.iter().find(|v| v["rule"] == 0).unwrap()["name"], "sta-stays-home");
+    assert!(violations.iter().any(|v| v["rule"] == 1));
+    assert!(violations.iter().all(|v| v["rule"] != 2));
+
+    // Without rules, everything is fine
+    let harness = Harness::new().await;
+    let (_, wir) = convert(&harness, "epi.bs").await;
+    let (_, body) = harness.send(Method::POST, "/api/workflow/check", "", wir).await;
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "ok": true, "violations": [] }));
+}
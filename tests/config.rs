@@ -0,0 +1,139 @@
+//  CONFIG.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 18:40:16
+//  Last edited:
+//    19 Oct 2026, 18:40:16
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Tests the layering of the backend's configuration.
+//
+
+use std::fs;
+use std::time::Duration;
+
+use policy_reasoner_client_backend::bstowir::IndexSource;
+use policy_reasoner_client_backend::checker::DEFAULT_CHECKER;
+use policy_reasoner_client_backend::config::{Config, ConfigError, ConfigOverrides, LogFormat};
+use policy_reasoner_client_backend::resilience::Endpoint;
+use tempfile::TempDir;
+
+
+/***** TESTS *****/
+#[test]
+fn test_config_layering() {
+    let dir: TempDir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    fs::write(
+        &path,
+        r#"
+[server]
+bind = "127.0.0.1:8080"
+request_timeout = 10
+
+[checker]
+timeout = 5
+
+[checker.resilience]
+timeouts = { deliberation = 60 }
+
+[conv]
+packages = { local_client = "/packages" }
+
+[log]
+format = "json"
+"#,
+    )
+    .unwrap();
+
+    // The file overrides the defaults, and the overrides override the file
+    let overrides = ConfigOverrides {
+        request_timeout: Some(20),
+        data: Some("remote:http://registry:50051".parse().unwrap()),
+        four_eyes: Some(true),
+        checker_probe_token: Some("probe-jwt".into()),
+        ..Default::default()
+    };
+    let config: Config = Config::resolve(Some(&path), overrides).unwrap();
+    assert_eq!(config.server.bind.to_string(), "127.0.0.1:8080");
+    assert_eq!(config.server.request_timeout, 20);
+    assert_eq!(config.checker.timeout, 5);
+    assert_eq!(config.checker.resilience.timeout(Endpoint::Deliberation, Duration::from_secs(5)), Duration::from_secs(60));
+    assert_eq!(config.checker.resilience.timeout(Endpoint::GetPolicy, Duration::from_secs(5)), Duration::from_secs(5));
+    assert_eq!(config.checker.address.to_string(), "http://localhost:3030");
+    assert!(matches!(&config.conv.packages, IndexSource::LocalClient(path) if path.to_str() == Some("/packages")));
+    assert!(matches!(&config.conv.data, IndexSource::Remote(_)));
+    assert_eq!(config.log.format, LogFormat::Json);
+    assert!(config.activation.four_eyes);
+    assert_eq!(config.checker.probe_token.as_deref(), Some("probe-jwt"));
+    assert_eq!(config.health.probe_interval, 15);
+
+    // What is printed can be read back, except for secrets
+    let printed: String = config.to_toml().unwrap();
+    assert!(!printed.contains("probe-jwt"), "{printed}");
+    let printed: Config = toml::from_str(&printed).unwrap();
+    assert_eq!(printed.to_toml().unwrap(), config.to_toml().unwrap());
+}
+
+#[test]
+fn test_config_checkers() {
+    // Without named checkers, the single one is the default
+    let config: Config = Config::resolve(None, ConfigOverrides::default()).unwrap();
+    assert_eq!(config.checkers().iter().map(|(name, _)| *name).collect::<Vec<&str>>(), vec![DEFAULT_CHECKER]);
+
+    // With them, it is ignored
+    let mut config: Config = toml::from_str(
+        r#"
+default_checker = "south"
+
+[checkers.north]
+address = "http://north:3030"
+
+[checkers.south]
+address = "http://south:3030"
+timeout = 5
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.checkers().iter().map(|(name, _)| *name).collect::<Vec<&str>>(), vec!["north", "south"]);
+    assert_eq!(config.checkers()[1].1.timeout, 5);
+    assert_eq!(config.default_checker.as_deref(), Some("south"));
+    config.apply(ConfigOverrides { default_checker: Some("north".into()), ..Default::default() }).unwrap();
+    assert_eq!(config.default_checker.as_deref(), Some("north"));
+
+    // The flags about the single checker would not affect them, so they are refused
+    let overrides: [(ConfigOverrides, &str); 3] = [
+        (ConfigOverrides { checker_address: Some("http://west:3030".parse().unwrap()), ..Default::default() }, "checker-address"),
+        (ConfigOverrides { checker_pins: Some(vec!["ab".into()]), ..Default::default() }, "checker-pins"),
+        (ConfigOverrides { checker_probe_token: Some("probe-jwt".into()), ..Default::default() }, "checker-probe-token"),
+    ];
+    for (overrides, expected) in overrides {
+        assert!(matches!(config.clone().apply(overrides), Err(ConfigError::CheckerOverride { flag }) if flag == expected), "{expected}");
+    }
+}
+
+#[test]
+fn test_config_errors() {
+    let dir: TempDir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+
+    // Missing files and unknown settings are reported instead of ignored
+    assert!(Config::load(&path).is_err());
+    fs::write(&path, "[server]\nport = 3001\n").unwrap();
+    assert!(Config::load(&path).is_err());
+
+    // So is only half a TLS configuration
+    let overrides = ConfigOverrides { tls_cert: Some("./cert.pem".into()), ..Default::default() };
+    assert!(matches!(Config::resolve(None, overrides), Err(ConfigError::IncompleteTls)));
+    let overrides = ConfigOverrides { tls_redirect_from: Some("0.0.0.0:80".parse().unwrap()), ..Default::default() };
+    assert!(matches!(Config::resolve(None, overrides), Err(ConfigError::IncompleteTls)));
+    let overrides = ConfigOverrides { tls_cert: Some("./cert.pem".into()), tls_key: Some("./key.pem".into()), ..Default::default() };
+    assert_eq!(Config::resolve(None, overrides).unwrap().server.tls.unwrap().reload_interval, 10);
+
+    // So are index sources without a kind
+    assert!("./tests/packages".parse::<IndexSource>().is_err());
+    assert!("somewhere:./tests/packages".parse::<IndexSource>().is_err());
+}
@@ -0,0 +1,108 @@
+//  GOLDEN.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 16:40:13
+//  Last edited:
+//    19 Oct 2026, 16:40:13
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Snapshot tests that compare the analysis of every BraneScript fixture
+//!   in `tests/branescript/` against golden JSON in `tests/golden/`.
+//!
+//!   Run with `UPDATE_GOLDEN=1` to regenerate the golden files after an
+//!   intended change (or to record those of a new fixture). Missing golden
+//!   files fail the test otherwise.
+//
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use policy_reasoner_client_backend::bstowir::{bs_to_wir, AnalyzedWir, IndexSource};
+use serde_json::{json, Value};
+
+
+/***** CONSTANTS *****/
+/// The directory with the BraneScript fixtures.
+const FIXTURES_DIR: &str = "./tests/branescript";
+/// The directory with the golden files.
+const GOLDEN_DIR: &str = "./tests/golden";
+/// The environment variable that, if set, regenerates the golden files instead of checking them.
+const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+
+
+
+
+/***** HELPERS *****/
+/// Analyzes a BraneScript fixture into the snapshot we compare.
+///
+/// Only the `tasks`, `results` and `lineage` are kept, since the workflow itself carries generated identifiers. Failing fixtures are snapshotted too,
+/// so changes in what we reject are caught as well.
+async fn snapshot(path: &Path) -> Value {
+    let bs: String = fs::read_to_string(path).unwrap();
+    match bs_to_wir(IndexSource::LocalTest("./tests/packages".into()), IndexSource::LocalTest("./tests/data".into()), bs).await {
+        Ok(wir) => {
+            let wir: AnalyzedWir = serde_json::from_str(&wir).unwrap();
+            json!({ "tasks": wir.tasks, "results": wir.results, "lineage": wir.lineage })
+        },
+        Err(err) => json!({ "error": { "status": err.status.as_u16(), "code": err.code, "message": err.message, "causes": err.causes } }),
+    }
+}
+
+
+
+
+
+/***** TESTS *****/
+#[tokio::test]
+async fn test_bs_to_wir_golden() {
+    let update: bool = std::env::var_os(UPDATE_ENV).is_some();
+
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(FIXTURES_DIR).unwrap().map(|e| e.unwrap().path()).filter(|p| p.extension().map(|e| e == "bs").unwrap_or(false)).collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+
+    if update {
+        fs::create_dir_all(GOLDEN_DIR).unwrap();
+    }
+    let mut mismatches: Vec<String> = vec![];
+    for fixture in fixtures {
+        let golden: PathBuf = Path::new(GOLDEN_DIR).join(fixture.file_stem().unwrap()).with_extension("json");
+        let actual: Value = snapshot(&fixture).await;
+
+        // Write the golden file if asked to
+        if update {
+            fs::write(&golden, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            eprintln!("Wrote golden file '{}'", golden.display());
+            continue;
+        }
+        if !golden.exists() {
+            mismatches.push(format!("'{}' has no golden file '{}'\n", fixture.display(), golden.display()));
+            continue;
+        }
+
+        let expected: Value = serde_json::from_str(&fs::read_to_string(&golden).unwrap()).unwrap();
+        if actual != expected {
+            mismatches.push(format!(
+                "'{}' does not match '{}'\n\nExpected:\n{}\n\nActual:\n{}\n",
+                fixture.display(),
+                golden.display(),
+                serde_json::to_string_pretty(&expected).unwrap(),
+                serde_json::to_string_pretty(&actual).unwrap()
+            ));
+        }
+    }
+    assert!(mismatches.is_empty(), "{}\n(re-run with {UPDATE_ENV}=1 if the change is intended)", mismatches.join("\n"));
+}
+
+#[tokio::test]
+async fn test_bs_to_wir_missing_index() {
+    // A bad index directory is reported, not panicked over
+    let err = bs_to_wir(IndexSource::LocalTest("./tests/nonexistent".into()), IndexSource::LocalTest("./tests/data".into()), String::new()).await.unwrap_err();
+    assert_eq!(err.status.as_u16(), 500);
+    assert_eq!(err.message, "Failed to collect package index");
+    assert!(err.causes[0].contains("./tests/nonexistent"), "{:?}", err.causes);
+}
@@ -0,0 +1,112 @@
+//  RESILIENCE.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 21:31:46
+//  Last edited:
+//    19 Oct 2026, 21:31:46
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Tests timing out and retrying calls to a checker that is down, and
+//!   the circuit breaker that stops calling it.
+//
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use policy_reasoner_client_backend::checker::{Checker, CheckerError, DeliberationKind};
+use policy_reasoner_client_backend::checker_tls::CheckerTlsConfig;
+use policy_reasoner_client_backend::resilience::{Endpoint, ResilienceConfig};
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+
+/***** HELPER FUNCTIONS *****/
+/// Starts a "checker" that accepts connections but never responds.
+///
+/// # Returns
+/// Its address, and the number of connections it accepted so far.
+async fn hanging_checker() -> (String, Arc<AtomicUsize>) {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address: String = format!("http://{}", listener.local_addr().unwrap());
+    let accepted: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    let counter: Arc<AtomicUsize> = accepted.clone();
+    tokio::spawn(async move {
+        let mut conns = vec![];
+        while let Ok((conn, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            conns.push(conn);
+        }
+    });
+    (address, accepted)
+}
+
+/// Builds a checker with the given resilience settings.
+fn checker(address: &str, resilience: ResilienceConfig) -> Checker {
+    Checker::new("down", address.parse().unwrap(), Duration::from_secs(30), &resilience, &CheckerTlsConfig::default()).unwrap()
+}
+
+
+
+
+
+/***** TESTS *****/
+#[tokio::test]
+async fn test_checker_timeouts() {
+    let (address, accepted) = hanging_checker().await;
+    let resilience = ResilienceConfig {
+        timeouts: BTreeMap::from([(Endpoint::ActivePolicy, 1), (Endpoint::Deliberation, 1)]),
+        retries: 1,
+        retry_backoff: 10,
+        breaker_threshold: 0,
+        ..Default::default()
+    };
+    let checker: Checker = checker(&address, resilience);
+
+    // Reading is retried before giving up...
+    let err: CheckerError = checker.get_active_policy("token").await.unwrap_err();
+    assert!(matches!(err, CheckerError::Timeout { .. }), "{err:?}");
+    assert_eq!(err.status_code(), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(serde_json::from_str::<Value>(&err.body()).unwrap()["code"], "checker_timeout");
+    assert_eq!(accepted.load(Ordering::SeqCst), 2);
+
+    // ...but asking questions is not
+    let err: CheckerError = checker.deliberate("token", DeliberationKind::Task, "{}".into()).await.unwrap_err();
+    assert!(matches!(err, CheckerError::Timeout { .. }), "{err:?}");
+    assert_eq!(accepted.load(Ordering::SeqCst), 3);
+    assert!(!checker.is_down().await);
+}
+
+#[tokio::test]
+async fn test_checker_circuit_breaker() {
+    // Find an address nobody listens on
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address: String = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let checker: Checker = checker(&address, ResilienceConfig { retries: 0, breaker_threshold: 2, breaker_cooldown: 60, ..Default::default() });
+
+    // The checker is considered down after failing twice
+    for _ in 0..2 {
+        let err: CheckerError = checker.list_policies("token").await.unwrap_err();
+        assert!(matches!(err, CheckerError::Request { .. }), "{err:?}");
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(serde_json::from_str::<Value>(&err.body()).unwrap()["code"], "checker_unavailable");
+    }
+    assert!(checker.is_down().await);
+
+    // After which it is not called anymore
+    let err: CheckerError = checker.activate_policy("token", 1).await.unwrap_err();
+    assert!(matches!(err, CheckerError::Unavailable { .. }), "{err:?}");
+    assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = serde_json::from_str(&err.body()).unwrap();
+    assert_eq!(body["code"], "checker_circuit_open");
+    assert!(body["retry_after"].as_u64().unwrap() > 50);
+
+    // Which every clone agrees on
+    assert!(checker.clone().is_down().await);
+}
@@ -0,0 +1,62 @@
+//  TLS.rs
+//    by Lut99
+//
+//  Created:
+//    19 Oct 2026, 19:31:02
+//  Last edited:
+//    19 Oct 2026, 19:31:02
+//  Auto updated?
+//    Yes
+//
+//  Description:
+//!   Tests redirecting plain HTTP to HTTPS and setting up TLS for the
+//!   checker connection.
+//
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use policy_reasoner_client_backend::checker_tls::{CheckerTlsConfig, CheckerTlsError};
+use policy_reasoner_client_backend::tls::redirect_router;
+use tower::ServiceExt as _;
+
+
+/***** HELPER FUNCTIONS *****/
+/// Sends a request to the redirect router and returns the status and location of its response.
+async fn redirect(https_port: u16, host: Option<&str>, uri: &str) -> (StatusCode, Option<String>) {
+    let mut req = Request::builder().uri(uri);
+    if let Some(host) = host {
+        req = req.header(header::HOST, host);
+    }
+    let res = redirect_router(https_port).oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    (res.status(), res.headers().get(header::LOCATION).map(|l| l.to_str().unwrap().to_string()))
+}
+
+
+
+
+
+/***** TESTS *****/
+#[tokio::test]
+async fn test_redirect_to_https() {
+    assert_eq!(
+        redirect(3001, Some("gui.example.com:3000"), "/api/policies?x=1").await,
+        (StatusCode::PERMANENT_REDIRECT, Some("https://gui.example.com:3001/api/policies?x=1".into()))
+    );
+    assert_eq!(redirect(443, Some("gui.example.com"), "/").await, (StatusCode::PERMANENT_REDIRECT, Some("https://gui.example.com/".into())));
+    assert_eq!(redirect(443, Some("[::1]:80"), "/").await, (StatusCode::PERMANENT_REDIRECT, Some("https://[::1]/".into())));
+    assert_eq!(redirect(443, None, "/").await.0, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_checker_tls_errors() {
+    // Mistakes in the checker's TLS settings are reported specifically
+    let tls = CheckerTlsConfig { cert: Some("./cert.pem".into()), ..Default::default() };
+    assert!(matches!(tls.client_config(), Err(CheckerTlsError::IncompleteIdentity)));
+    let tls = CheckerTlsConfig { ca: Some("./does-not-exist.pem".into()), ..Default::default() };
+    assert!(matches!(tls.client_config(), Err(CheckerTlsError::Read { what: "CA", .. })));
+    let tls = CheckerTlsConfig { ca: Some("./Cargo.toml".into()), ..Default::default() };
+    assert!(matches!(tls.client_config(), Err(CheckerTlsError::NoCertificates { what: "CA", .. })));
+    let tls = CheckerTlsConfig { pins: vec!["AB:CD".into()], ..Default::default() };
+    assert!(matches!(tls.client_config(), Err(CheckerTlsError::InvalidPin { .. })));
+    assert!(CheckerTlsConfig::default().is_default());
+}